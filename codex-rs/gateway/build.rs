@@ -0,0 +1,22 @@
+use std::process::Command;
+
+fn main() {
+    // Prefer an explicit GIT_SHA (Docker builds have no .git), then ask git.
+    let git_sha = std::env::var("GIT_SHA")
+        .ok()
+        .filter(|sha| !sha.trim().is_empty())
+        .or_else(|| {
+            Command::new("git")
+                .args(["rev-parse", "--short=12", "HEAD"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+        })
+        .map(|sha| sha.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=CODEX_GATEWAY_GIT_SHA={git_sha}");
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-changed=../../.git/HEAD");
+}
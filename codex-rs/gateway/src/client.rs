@@ -0,0 +1,317 @@
+//! Typed client for the gateway's streaming exec API
+//!
+//! Built with the `client` feature. [`ExecClient::exec_stream`] posts an
+//! [`ExecRequest`] to `POST /exec/ndjson` and yields each line as a
+//! [`CodexEvent`], so services calling the gateway don't have to parse the
+//! NDJSON framing themselves.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), codex_gateway::client::ClientError> {
+//! use codex_gateway::client::CodexEvent;
+//! use codex_gateway::client::ExecClient;
+//! use codex_gateway::handlers::exec::ExecRequest;
+//! use futures::StreamExt;
+//!
+//! let client = ExecClient::new("https://gateway.example.com")?.with_api_key("key");
+//! let request = ExecRequest {
+//!     prompt: "list the files in this repo".to_string(),
+//!     ..Default::default()
+//! };
+//! let mut events = client.exec_stream(&request).await?;
+//! while let Some(event) = events.next().await {
+//!     if let CodexEvent::ExecCompleted(summary) = event? {
+//!         println!("finished: {}", summary.status);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::handlers::exec::EVENT_TYPES;
+use crate::handlers::exec::ExecRequest;
+use crate::handlers::exec::ExecStatus;
+use crate::handlers::exec::TurnLimits;
+use crate::services::exec_results::ExecRetries;
+use codex_exec::exec_events::ThreadEvent;
+use futures::Stream;
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+use url::Url;
+
+/// Errors from [`ExecClient`]
+#[derive(Error, Debug)]
+pub enum ClientError {
+    /// The request could not be sent or the stream broke off
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The gateway base URL is not a valid URL
+    #[error("Invalid gateway URL: {0}")]
+    Url(#[from] url::ParseError),
+
+    /// The gateway rejected the request before streaming, e.g. a 400 or 401
+    #[error("Gateway returned {status}: {body}")]
+    Status { status: u16, body: String },
+
+    /// A line of the stream was not a valid event
+    #[error("Malformed event: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+/// One line of an `/exec/ndjson` stream
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodexEvent {
+    /// The exec was accepted; always the first event, with its effective time bounds
+    TaskStarted(TurnLimits),
+    /// An agent event, as emitted by `codex exec --json`
+    Thread(Box<ThreadEvent>),
+    /// Where the exec's time went, sent just before [`CodexEvent::ExecCompleted`]
+    Timings(ExecPhaseTimings),
+    /// The exec finished; always the last event of a successful stream
+    ExecCompleted(ExecSummary),
+    /// The exec could not run; always the last event of a failed stream
+    ExecError { message: String },
+    /// An event this client does not know yet
+    Other { event: String, data: Value },
+}
+
+/// The `/exec` response without its events
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ExecSummary {
+    pub conversation_id: String,
+    pub status: ExecStatus,
+    #[serde(default)]
+    pub created_files: Vec<String>,
+    #[serde(default)]
+    pub prompt_prefix: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub retries: ExecRetries,
+}
+
+/// Milliseconds the exec spent in each phase
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ExecPhaseTimings {
+    pub queue_ms: f64,
+    pub session_ms: f64,
+    pub exec_ms: f64,
+    pub teardown_ms: f64,
+    pub total_ms: f64,
+}
+
+/// Events of one exec, in the order the gateway sent them
+pub type EventStream = BoxStream<'static, Result<CodexEvent, ClientError>>;
+
+/// Client for one gateway instance
+#[derive(Debug, Clone)]
+pub struct ExecClient {
+    http: reqwest::Client,
+    base_url: Url,
+    api_key: Option<String>,
+}
+
+impl ExecClient {
+    /// Client for the gateway at `base_url`, e.g. `https://gateway.example.com`
+    pub fn new(base_url: &str) -> Result<Self, ClientError> {
+        Ok(Self::with_http_client(
+            Url::parse(base_url)?,
+            reqwest::Client::new(),
+        ))
+    }
+
+    /// Client sending its requests through `http`
+    pub fn with_http_client(base_url: Url, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            base_url,
+            api_key: None,
+        }
+    }
+
+    /// Send `api_key` in `X-API-Key` with every request
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Start an exec and stream its events
+    ///
+    /// Fails with [`ClientError::Status`] if the gateway rejects the request;
+    /// once streaming has started, problems are reported as stream items.
+    pub async fn exec_stream(&self, request: &ExecRequest) -> Result<EventStream, ClientError> {
+        let mut http_request = self
+            .http
+            .post(self.base_url.join("exec/ndjson")?)
+            .json(request);
+        if let Some(api_key) = &self.api_key {
+            http_request = http_request.header("x-api-key", api_key);
+        }
+
+        let response = http_request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(ClientError::Status {
+                status: status.as_u16(),
+                body,
+            });
+        }
+
+        Ok(ndjson_events(response.bytes_stream()).boxed())
+    }
+}
+
+/// Split a byte stream into lines and parse each one as a [`CodexEvent`]
+///
+/// Lines may span chunks; a last line without a newline is still parsed.
+/// A transport error ends the stream after it is reported.
+fn ndjson_events<S, B>(bytes: S) -> impl Stream<Item = Result<CodexEvent, ClientError>>
+where
+    S: Stream<Item = Result<B, reqwest::Error>> + Send + 'static,
+    B: AsRef<[u8]> + Send,
+{
+    let state = (Box::pin(bytes), Vec::new(), false);
+    futures::stream::unfold(state, |(mut bytes, mut buffer, mut done)| async move {
+        loop {
+            if let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                if line.trim().is_empty() {
+                    continue;
+                }
+                return Some((parse_line(line.trim()), (bytes, buffer, done)));
+            }
+            if done {
+                let line = String::from_utf8_lossy(&std::mem::take(&mut buffer)).into_owned();
+                if line.trim().is_empty() {
+                    return None;
+                }
+                return Some((parse_line(line.trim()), (bytes, buffer, done)));
+            }
+            match bytes.next().await {
+                Some(Ok(chunk)) => buffer.extend_from_slice(chunk.as_ref()),
+                Some(Err(err)) => {
+                    buffer.clear();
+                    return Some((Err(ClientError::Http(err)), (bytes, buffer, true)));
+                }
+                None => done = true,
+            }
+        }
+    })
+}
+
+/// Parse one `{"event": ..., "data": ...}` line
+fn parse_line(line: &str) -> Result<CodexEvent, ClientError> {
+    #[derive(Deserialize)]
+    struct Line {
+        event: String,
+        #[serde(default)]
+        data: Value,
+    }
+
+    let Line { event, data } = serde_json::from_str(line)?;
+    Ok(match event.as_str() {
+        "task_started" => CodexEvent::TaskStarted(serde_json::from_value(data)?),
+        "timings" => CodexEvent::Timings(serde_json::from_value(data)?),
+        "exec.completed" => CodexEvent::ExecCompleted(serde_json::from_value(data)?),
+        "exec.error" => CodexEvent::ExecError {
+            message: data["error"].as_str().unwrap_or_default().to_string(),
+        },
+        name if EVENT_TYPES.contains(&name) => {
+            CodexEvent::Thread(Box::new(serde_json::from_value(data)?))
+        }
+        _ => CodexEvent::Other { event, data },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::HeaderMap;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use axum::routing::post;
+    use std::convert::Infallible;
+
+    /// Stand-in for `/exec/ndjson` that splits its lines across chunks
+    async fn fake_exec_ndjson(headers: HeaderMap) -> impl IntoResponse {
+        if headers.get("x-api-key").and_then(|v| v.to_str().ok()) != Some("test-key") {
+            return (StatusCode::UNAUTHORIZED, "missing key").into_response();
+        }
+        let body = concat!(
+            r#"{"event":"thread.started","data":{"type":"thread.started","thread_id":"thread-1"}}"#,
+            "\n",
+            r#"{"event":"turn.started","data":{"type":"turn.started"}}"#,
+            "\n",
+            r#"{"event":"timings","data":{"queue_ms":0.0,"session_ms":2.5,"exec_ms":900.0,"teardown_ms":1.0,"total_ms":903.5}}"#,
+            "\n",
+            r#"{"event":"exec.completed","data":{"conversation_id":"conv-1","status":"completed","created_files":["hello.py"]}}"#,
+            "\n",
+        );
+        let chunks: Vec<Result<String, Infallible>> = body
+            .as_bytes()
+            .chunks(37)
+            .map(|chunk| Ok(String::from_utf8_lossy(chunk).into_owned()))
+            .collect();
+        Body::from_stream(futures::stream::iter(chunks)).into_response()
+    }
+
+    async fn serve() -> Result<String, Box<dyn std::error::Error>> {
+        let app = Router::new().route("/exec/ndjson", post(fake_exec_ndjson));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        Ok(format!("http://{addr}"))
+    }
+
+    #[tokio::test]
+    async fn test_exec_stream_yields_typed_events() -> Result<(), Box<dyn std::error::Error>> {
+        let client = ExecClient::new(&serve().await?)?.with_api_key("test-key");
+        let request = ExecRequest {
+            prompt: "say hi".to_string(),
+            ..Default::default()
+        };
+
+        let events: Vec<CodexEvent> = client
+            .exec_stream(&request)
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()?;
+
+        assert_eq!(events.len(), 4);
+        assert!(matches!(
+            &events[0],
+            CodexEvent::Thread(event) if matches!(**event, ThreadEvent::ThreadStarted(_))
+        ));
+        assert!(matches!(&events[2], CodexEvent::Timings(t) if t.exec_ms == 900.0));
+        let CodexEvent::ExecCompleted(summary) = &events[3] else {
+            panic!("expected exec.completed last, got {:?}", events[3]);
+        };
+        assert_eq!(summary.status, ExecStatus::Completed);
+        assert_eq!(summary.created_files, vec!["hello.py".to_string()]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exec_stream_reports_rejection() -> Result<(), Box<dyn std::error::Error>> {
+        let client = ExecClient::new(&serve().await?)?;
+
+        let err = client
+            .exec_stream(&ExecRequest::default())
+            .await
+            .err()
+            .ok_or("expected the request to be rejected")?;
+        assert!(matches!(err, ClientError::Status { status: 401, .. }));
+        Ok(())
+    }
+}
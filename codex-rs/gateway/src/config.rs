@@ -1,7 +1,11 @@
 //! Configuration types for the Codex Gateway
 
+use crate::error::GatewayError;
+use crate::error::GatewayResult;
+use codex_protocol::config_types::SandboxMode;
 use serde::Deserialize;
 use serde::Serialize;
+use std::path::PathBuf;
 use std::time::Duration;
 
 /// Gateway configuration settings
@@ -24,12 +28,24 @@ pub struct GatewayConfig {
 
     /// Request body size limits configuration
     pub body_limits: BodyLimitsConfig,
+
+    /// Exec execution limits configuration
+    pub exec: ExecConfig,
+
+    /// Cross-origin (CORS) configuration
+    pub cors: CorsConfig,
+
+    /// Whether `/debug/*` endpoints are served (`CODEX_ENABLE_DEBUG_ENDPOINTS=1`)
+    pub debug_endpoints: bool,
 }
 
 /// Timeout configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimeoutConfig {
-    /// Request timeout duration
+    /// Request timeout duration (`REQUEST_TIMEOUT_SECS`)
+    ///
+    /// Covers every route but `/exec`, `/exec/batch` and `/exec/ndjson`,
+    /// whose turns are bounded by `timeout_ms` and `max_lifetime_ms` instead.
     pub request_timeout: Duration,
 
     /// Keep-alive timeout duration
@@ -56,12 +72,21 @@ pub struct WebSocketConfig {
 
     /// Maximum number of concurrent WebSocket connections
     pub max_connections: usize,
+
+    /// Responses buffered per streaming exec before the agent's event loop waits for the client
+    pub channel_capacity: usize,
+
+    /// How long a single write may block before the client is considered stalled and dropped
+    pub send_timeout: Duration,
+
+    /// Largest message sent to a client; bigger responses are split into chunks
+    pub max_event_bytes: usize,
 }
 
 /// Request body size limits configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BodyLimitsConfig {
-    /// Global default body size limit in bytes
+    /// Body size limit in bytes for routes without a limit of their own
     pub default_limit: usize,
 
     /// JSON-RPC specific body size limit in bytes
@@ -73,11 +98,93 @@ pub struct BodyLimitsConfig {
     /// Health check body size limit in bytes (usually very small)
     pub health_limit: usize,
 
+    /// `/exec`, `/exec/batch` and `/exec/ndjson` body size limit in bytes
+    /// (`CODEX_MAX_BODY_BYTES`), enforced before the JSON is parsed; may be
+    /// larger than `default_limit`
+    pub exec_limit: usize,
+
     /// Whether to enable body size limits (can be disabled for development)
     pub enabled: bool,
     pub(crate) websocket_limit: usize,
 }
 
+/// Exec execution limits configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecConfig {
+    /// Maximum number of agent turns running at the same time
+    pub max_concurrency: usize,
+
+    /// Maximum prompt size in bytes
+    pub max_prompt_bytes: usize,
+
+    /// Upper bound applied to a request's `timeout_ms`
+    ///
+    /// Exec routes are not subject to the request timeout, so this may be
+    /// longer than `REQUEST_TIMEOUT_SECS`.
+    pub max_timeout_ms: u64,
+
+    /// Longest any turn may run, however busy, and the cap on a request's `max_lifetime_ms`
+    pub max_lifetime_ms: u64,
+
+    /// Models a request may select; empty allows any model
+    pub allowed_models: Vec<String>,
+
+    /// Model providers a request may select; empty allows any provider configured in Codex
+    pub allowed_providers: Vec<String>,
+
+    /// How long a reply stays cached under its `Idempotency-Key`
+    pub idempotency_ttl: Duration,
+
+    /// Working directory used when a request sets no `cwd`; falls back to the Codex config's cwd
+    pub default_workdir: Option<PathBuf>,
+
+    /// Directory every request `cwd` must resolve inside; `None` allows any directory
+    pub workdir_root: Option<PathBuf>,
+
+    /// Instruction prepended to every prompt; requests can add to it but not remove it
+    pub prompt_prefix: Option<String>,
+
+    /// Failed execs within `breaker_window` that open the circuit breaker; 0 disables it
+    pub breaker_threshold: usize,
+
+    /// Window over which failed execs are counted
+    pub breaker_window: Duration,
+
+    /// How long an open breaker rejects execs before letting a probe through
+    pub breaker_cooldown: Duration,
+
+    /// Whether execs keep their rollout and result when a request sets no `persist`
+    pub persist_default: bool,
+
+    /// Refuse to start unless some model provider has valid credentials
+    pub require_provider_credentials: bool,
+
+    /// Longest line of command output kept in events; longer lines are truncated
+    pub max_line_bytes: usize,
+
+    /// Execs running or queued for a slot before new ones get 503; 0 is unbounded
+    pub max_inflight: usize,
+
+    /// Redact recognizable secrets from events before they reach clients, and
+    /// from session rollouts once each turn ends
+    pub redact_output: bool,
+
+    /// Sandbox mode for requests that set no `sandbox_mode`; `None` uses the Codex config's policy
+    pub default_sandbox_mode: Option<SandboxMode>,
+
+    /// Execs each API key may start in any rolling 24 hours; 0 is unlimited
+    pub daily_quota: usize,
+}
+
+/// Cross-origin resource sharing configuration
+///
+/// CORS is disabled (same-origin only) while `allowed_origins` is empty.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CorsConfig {
+    /// Origins allowed to call the gateway from a browser; `"*"` allows any origin
+    pub allowed_origins: Vec<String>,
+}
+
 impl Default for GatewayConfig {
     fn default() -> Self {
         Self {
@@ -87,6 +194,9 @@ impl Default for GatewayConfig {
             max_connections: 10000,
             websocket: WebSocketConfig::default(),
             body_limits: BodyLimitsConfig::default(),
+            exec: ExecConfig::default(),
+            cors: CorsConfig::default(),
+            debug_endpoints: false,
         }
     }
 }
@@ -111,6 +221,11 @@ impl Default for WebSocketConfig {
             // Limite baseado em padrões de mercado e capacidade do servidor
             // nginx default: 1024, cloudflare: 10000, optamos por um meio termo robusto
             max_connections: 5000,
+            // Eventos são pequenos; 256 cobre rajadas de deltas sem crescer sem limite
+            channel_capacity: 256,
+            send_timeout: Duration::from_secs(30),
+            // 1MB fica abaixo do limite de frame dos proxies mais comuns
+            max_event_bytes: 1024 * 1024,
         }
     }
 }
@@ -130,6 +245,9 @@ impl Default for BodyLimitsConfig {
             // Health: 1KB - endpoints de health são mínimos
             health_limit: 1024,
 
+            // Exec: 1MB - prompt (até 100KB) + imagens em data URI pequenas
+            exec_limit: 1024 * 1024,
+
             // Websocket: 1MB - suficiente para payloads de integração robustos (ex: GitHub webhooks com diffs grandes)
             websocket_limit: 1024,
 
@@ -139,6 +257,64 @@ impl Default for BodyLimitsConfig {
     }
 }
 
+impl Default for ExecConfig {
+    fn default() -> Self {
+        Self {
+            // Cada turno mantém um agente completo em memória; 4 cabe numa instância Cloud Run padrão
+            max_concurrency: 4,
+
+            // 100KB de prompt já é bem mais que qualquer uso interativo
+            max_prompt_bytes: 100_000,
+
+            // 10 minutos por turno
+            max_timeout_ms: 10 * 60 * 1000,
+
+            // 1 hora de vida no máximo, mesmo produzindo eventos sem parar
+            max_lifetime_ms: 60 * 60 * 1000,
+
+            // Sem allowlist: qualquer modelo/provider configurado no Codex
+            allowed_models: Vec::new(),
+            allowed_providers: Vec::new(),
+
+            // Retries de clientes costumam acontecer em minutos; 1 hora cobre com folga
+            idempotency_ttl: Duration::from_secs(3600),
+
+            // Sem override: usa o cwd da config do Codex e aceita qualquer diretório
+            default_workdir: None,
+            workdir_root: None,
+
+            // Sem guardrail global por padrão
+            prompt_prefix: None,
+
+            // 5 falhas em 1 minuto abrem o circuito por 30 segundos
+            breaker_threshold: 5,
+            breaker_window: Duration::from_secs(60),
+            breaker_cooldown: Duration::from_secs(30),
+
+            // Rollouts ficam em CODEX_HOME como no CLI
+            persist_default: true,
+
+            // Só registra os providers configurados; não impede a subida
+            require_provider_credentials: false,
+
+            // 64KB por linha: cabe qualquer log legível, corta blobs base64 sem quebra
+            max_line_bytes: 64 * 1024,
+
+            // Sem limite de fila: execs esperam por um slot como antes
+            max_inflight: 0,
+
+            // Resultados guardados são sempre redigidos; eventos e rollouts só com CODEX_REDACT_OUTPUT
+            redact_output: false,
+
+            // Sem override: vale a sandbox_policy da config do Codex
+            default_sandbox_mode: None,
+
+            // Sem cota diária: só o rate limit por minuto se aplica
+            daily_quota: 0,
+        }
+    }
+}
+
 impl BodyLimitsConfig {
     /// Create body limits config from environment variables
     pub fn from_env() -> Self {
@@ -158,6 +334,7 @@ impl BodyLimitsConfig {
             jsonrpc_limit: parse_size("GATEWAY_BODY_LIMIT_JSONRPC", 1024 * 1024),
             webhook_limit: parse_size("GATEWAY_BODY_LIMIT_WEBHOOK", 10 * 1024 * 1024),
             health_limit: parse_size("GATEWAY_BODY_LIMIT_HEALTH", 1024),
+            exec_limit: parse_size("CODEX_MAX_BODY_BYTES", 1024 * 1024),
             websocket_limit: parse_size("GATEWAY_BODY_LIMIT_WEBSOCKET", 1024 * 1024),
             enabled,
         }
@@ -173,11 +350,209 @@ impl BodyLimitsConfig {
             p if p.starts_with("/health") => self.health_limit,
             p if p.starts_with("/jsonrpc") || p.starts_with("/rpc") => self.jsonrpc_limit,
             p if p.starts_with("/webhook") || p.starts_with("/hook") => self.webhook_limit,
+            "/exec" => self.exec_limit,
             _ => self.default_limit,
         }
     }
 }
 
+impl ExecConfig {
+    /// Create exec config from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        let max_concurrency = std::env::var("CODEX_MAX_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(defaults.max_concurrency);
+
+        let max_prompt_bytes = std::env::var("CODEX_MAX_PROMPT_BYTES")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(defaults.max_prompt_bytes);
+
+        let max_timeout_ms = std::env::var("CODEX_MAX_TIMEOUT_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(defaults.max_timeout_ms);
+
+        let max_lifetime_ms = std::env::var("CODEX_MAX_LIFETIME_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(defaults.max_lifetime_ms);
+
+        let allowed_models = std::env::var("CODEX_ALLOWED_MODELS")
+            .map(|v| parse_list(&v))
+            .unwrap_or_default();
+
+        let allowed_providers = std::env::var("CODEX_ALLOWED_PROVIDERS")
+            .map(|v| parse_list(&v))
+            .unwrap_or_default();
+
+        let idempotency_ttl = std::env::var("CODEX_IDEMPOTENCY_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(defaults.idempotency_ttl);
+
+        let default_workdir = std::env::var("CODEX_DEFAULT_WORKDIR")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from);
+
+        let workdir_root = std::env::var("CODEX_WORKDIR_ROOT")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from);
+
+        let prompt_prefix = std::env::var("CODEX_PROMPT_PREFIX")
+            .ok()
+            .filter(|v| !v.trim().is_empty());
+
+        let breaker_threshold = std::env::var("CODEX_BREAKER_THRESHOLD")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(defaults.breaker_threshold);
+
+        let breaker_window = std::env::var("CODEX_BREAKER_WINDOW_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(defaults.breaker_window);
+
+        let breaker_cooldown = std::env::var("CODEX_BREAKER_COOLDOWN_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(defaults.breaker_cooldown);
+
+        let persist_default = std::env::var("CODEX_PERSIST_DEFAULT")
+            .ok()
+            .and_then(|v| match v.trim().to_ascii_lowercase().as_str() {
+                "true" | "1" | "yes" => Some(true),
+                "false" | "0" | "no" => Some(false),
+                _ => None,
+            })
+            .unwrap_or(defaults.persist_default);
+
+        let require_provider_credentials = std::env::var("CODEX_REQUIRE_PROVIDER_CREDENTIALS")
+            .or_else(|_| std::env::var("CODEX_REQUIRE_PROVIDER"))
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "true" | "1" | "yes"))
+            .unwrap_or(defaults.require_provider_credentials);
+
+        let max_line_bytes = std::env::var("CODEX_MAX_LINE_BYTES")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(defaults.max_line_bytes);
+
+        let max_inflight = std::env::var("CODEX_MAX_INFLIGHT")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(defaults.max_inflight);
+
+        let daily_quota = std::env::var("CODEX_DAILY_QUOTA")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(defaults.daily_quota);
+
+        let redact_output = std::env::var("CODEX_REDACT_OUTPUT")
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "true" | "1" | "yes"))
+            .unwrap_or(defaults.redact_output);
+
+        Self {
+            max_concurrency,
+            max_prompt_bytes,
+            max_timeout_ms,
+            max_lifetime_ms,
+            allowed_models,
+            allowed_providers,
+            idempotency_ttl,
+            default_workdir,
+            workdir_root,
+            prompt_prefix,
+            breaker_threshold,
+            breaker_window,
+            breaker_cooldown,
+            persist_default,
+            require_provider_credentials,
+            max_line_bytes,
+            max_inflight,
+            daily_quota,
+            redact_output,
+            // Fallible, so read separately by default_sandbox_mode_from_env
+            default_sandbox_mode: defaults.default_sandbox_mode,
+        }
+    }
+
+    /// Read `CODEX_DEFAULT_SANDBOX_POLICY`
+    ///
+    /// An unknown value is an error rather than a silent fallback to the
+    /// Codex config's (possibly more permissive) policy, so startup fails.
+    pub fn default_sandbox_mode_from_env() -> GatewayResult<Option<SandboxMode>> {
+        match std::env::var(DEFAULT_SANDBOX_POLICY_ENV) {
+            Ok(value) if !value.trim().is_empty() => parse_default_sandbox_mode(&value).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Environment variable with the deployment-wide default sandbox mode
+pub const DEFAULT_SANDBOX_POLICY_ENV: &str = "CODEX_DEFAULT_SANDBOX_POLICY";
+
+/// Parse a `CODEX_DEFAULT_SANDBOX_POLICY` value
+pub fn parse_default_sandbox_mode(value: &str) -> GatewayResult<SandboxMode> {
+    serde_json::from_value(serde_json::Value::String(value.trim().to_string())).map_err(|_| {
+        GatewayError::Config(format!(
+            "{DEFAULT_SANDBOX_POLICY_ENV} must be one of read-only, workspace-write, danger-full-access (got '{value}')"
+        ))
+    })
+}
+
+impl CorsConfig {
+    /// Create CORS config from `CODEX_CORS_ALLOWED_ORIGINS` (comma-separated, or `*`)
+    pub fn from_env() -> Self {
+        let allowed_origins = std::env::var("CODEX_CORS_ALLOWED_ORIGINS")
+            .map(|v| parse_origins(&v))
+            .unwrap_or_default();
+
+        Self { allowed_origins }
+    }
+}
+
+/// Split a comma-separated origin list, dropping blanks and trailing slashes
+fn parse_origins(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|origin| origin.trim().trim_end_matches('/'))
+        .filter(|origin| !origin.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether `CODEX_ENABLE_DEBUG_ENDPOINTS` turns the debug endpoints on
+pub fn debug_endpoints_from_env() -> bool {
+    std::env::var("CODEX_ENABLE_DEBUG_ENDPOINTS")
+        .is_ok_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+}
+
+/// Split a comma-separated list, dropping blanks
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 impl GatewayConfig {
     /// Create a new config from environment variables
     pub fn from_env() -> Self {
@@ -203,6 +578,9 @@ impl GatewayConfig {
             port,
             body_limits,
             websocket,
+            exec: ExecConfig::from_env(),
+            cors: CorsConfig::from_env(),
+            debug_endpoints: debug_endpoints_from_env(),
             ..Default::default()
         }
     }
@@ -1,5 +1,43 @@
 //! Error types for the Codex Gateway
+//!
+//! Every error response has the same JSON body:
+//!
+//! ```json
+//! {"error": "Invalid request: field 'prompt' must not be empty", "status": 400, "code": "invalid_request"}
+//! ```
+//!
+//! `error` is meant for people and may change; `code` is stable and meant
+//! for programs:
+//!
+//! | code                     | status | meaning                                         |
+//! |--------------------------|--------|-------------------------------------------------|
+//! | `invalid_request`        | 400    | a field is missing, malformed or out of range   |
+//! | `invalid_json`           | 400    | the body is not valid JSON                      |
+//! | `prompt_too_large`       | 400    | the prompt exceeds `CODEX_MAX_PROMPT_BYTES`     |
+//! | `bad_request`            | 400    | any other malformed request                     |
+//! | `unauthorized`           | 401    | no valid API key or token                       |
+//! | `forbidden`              | 403    | the caller may not do this                      |
+//! | `not_found`              | 404    | no such route or resource                       |
+//! | `method_not_allowed`     | 405    | the route does not accept this method           |
+//! | `timeout`                | 408    | the request took longer than allowed            |
+//! | `conflict`               | 409    | the resource is busy, e.g. a turn is running    |
+//! | `payload_too_large`      | 413    | the body exceeds the endpoint's size limit      |
+//! | `unsupported_media_type` | 415    | the body is not `application/json`              |
+//! | `idempotency_key_reused` | 422    | the `Idempotency-Key` came with another body    |
+//! | `rate_limited`           | 429    | the API key's per-minute limit is used up       |
+//! | `quota_exceeded`         | 429    | the API key's daily exec quota is used up       |
+//! | `internal_error`         | 500    | the gateway failed                              |
+//! | `service_unavailable`    | 503    | a dependency is down or the breaker is open     |
+//! | `overloaded`             | 503    | too many execs in flight                        |
+//!
+//! 429 and `overloaded` responses also carry `Retry-After`. Errors raised
+//! outside the gateway's handlers, such as axum's JSON extractor or the body
+//! size limit, get the same body from
+//! [`crate::middleware::json_errors::json_error_middleware`].
 
+use axum::http::StatusCode;
+use serde_json::Value;
+use serde_json::json;
 use thiserror::Error;
 
 /// Errors that can occur in the gateway
@@ -33,10 +71,38 @@ pub enum GatewayError {
     #[error("Service unavailable: {0}")]
     ServiceUnavailable(String),
 
+    /// Too much work in flight; answered with 503 and `Retry-After`
+    #[error("Service overloaded: {message}")]
+    Overloaded {
+        message: String,
+        /// Seconds the client should wait before retrying
+        retry_after_secs: u64,
+    },
+
+    /// Caller used up its per-minute rate limit; answered with 429 and `Retry-After`
+    #[error("Rate limited: {message}")]
+    RateLimited {
+        message: String,
+        /// Seconds until the key's bucket has room again
+        retry_after_secs: u64,
+    },
+
+    /// Caller used up its `CODEX_DAILY_QUOTA`; answered with 429 and `Retry-After`
+    #[error("Quota exceeded: {message}")]
+    QuotaExceeded {
+        message: String,
+        /// Seconds until the oldest counted exec leaves the window
+        retry_after_secs: u64,
+    },
+
     /// Authentication/Authorization errors
     #[error("Auth error: {0}")]
     Auth(String),
 
+    /// Authenticated caller is not allowed to do this
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     /// Timeout errors
     #[error("Timeout: {0}")]
     Timeout(String),
@@ -59,40 +125,241 @@ pub enum GatewayError {
     /// Invalid request error (malformed or invalid parameters)
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
+
+    /// Prompt larger than `CODEX_MAX_PROMPT_BYTES`
+    #[error("Prompt too large: {0}")]
+    PromptTooLarge(String),
+
+    /// Requested resource does not exist
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// Request conflicts with the current state of the resource
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// `Idempotency-Key` already used by a request with a different body
+    #[error("Idempotency key reused: {0}")]
+    IdempotencyKeyReused(String),
 }
 
 /// Result type alias for gateway operations
 pub type GatewayResult<T> = Result<T, GatewayError>;
 
+impl GatewayError {
+    /// HTTP status the error is answered with
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            GatewayError::Http(_)
+            | GatewayError::Json(_)
+            | GatewayError::WebSocket(_)
+            | GatewayError::InvalidRequest(_)
+            | GatewayError::PromptTooLarge(_) => StatusCode::BAD_REQUEST,
+            GatewayError::ServerStart(_)
+            | GatewayError::Config(_)
+            | GatewayError::Internal(_)
+            | GatewayError::Generic(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            GatewayError::ServiceUnavailable(_) | GatewayError::Overloaded { .. } => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            GatewayError::RateLimited { .. } | GatewayError::QuotaExceeded { .. } => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            GatewayError::Auth(_) => StatusCode::UNAUTHORIZED,
+            GatewayError::Forbidden(_) => StatusCode::FORBIDDEN,
+            GatewayError::Timeout(_) => StatusCode::REQUEST_TIMEOUT,
+            GatewayError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            GatewayError::NotFound(_) => StatusCode::NOT_FOUND,
+            GatewayError::Conflict(_) => StatusCode::CONFLICT,
+            GatewayError::IdempotencyKeyReused(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    /// Machine-readable `code` of the error body
+    pub fn code(&self) -> &'static str {
+        match self {
+            GatewayError::Http(_) | GatewayError::WebSocket(_) => "bad_request",
+            GatewayError::Json(_) => "invalid_json",
+            GatewayError::InvalidRequest(_) => "invalid_request",
+            GatewayError::PromptTooLarge(_) => "prompt_too_large",
+            GatewayError::ServerStart(_)
+            | GatewayError::Config(_)
+            | GatewayError::Internal(_)
+            | GatewayError::Generic(_) => "internal_error",
+            GatewayError::ServiceUnavailable(_) => "service_unavailable",
+            GatewayError::Overloaded { .. } => "overloaded",
+            GatewayError::RateLimited { .. } => "rate_limited",
+            GatewayError::QuotaExceeded { .. } => "quota_exceeded",
+            GatewayError::Auth(_) => "unauthorized",
+            GatewayError::Forbidden(_) => "forbidden",
+            GatewayError::Timeout(_) => "timeout",
+            GatewayError::PayloadTooLarge { .. } => "payload_too_large",
+            GatewayError::NotFound(_) => "not_found",
+            GatewayError::Conflict(_) => "conflict",
+            GatewayError::IdempotencyKeyReused(_) => "idempotency_key_reused",
+        }
+    }
+}
+
+/// `code` for an error response that did not come from a [`GatewayError`]
+pub fn code_for_status(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => "invalid_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+        StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => "timeout",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limited",
+        StatusCode::SERVICE_UNAVAILABLE => "service_unavailable",
+        status if status.is_client_error() => "bad_request",
+        _ => "internal_error",
+    }
+}
+
+/// JSON body of every error response
+pub fn error_body(status: StatusCode, code: &str, message: &str) -> Value {
+    json!({
+        "error": message,
+        "status": status.as_u16(),
+        "code": code,
+    })
+}
+
 impl axum::response::IntoResponse for GatewayError {
     fn into_response(self) -> axum::response::Response {
         use axum::Json;
-        use axum::http::StatusCode;
-
-        let (status, error_message) = match self {
-            GatewayError::Http(_) => (StatusCode::BAD_REQUEST, self.to_string()),
-            GatewayError::Json(_) => (StatusCode::BAD_REQUEST, self.to_string()),
-            GatewayError::WebSocket(_) => (StatusCode::BAD_REQUEST, self.to_string()),
-            GatewayError::ServerStart(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            GatewayError::Config(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            GatewayError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            GatewayError::ServiceUnavailable(_) => {
-                (StatusCode::SERVICE_UNAVAILABLE, self.to_string())
+        use axum::http::HeaderValue;
+        use axum::http::header;
+
+        let retry_after = match &self {
+            GatewayError::Overloaded {
+                retry_after_secs, ..
             }
-            GatewayError::Auth(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
-            GatewayError::Timeout(_) => (StatusCode::REQUEST_TIMEOUT, self.to_string()),
-            GatewayError::Generic(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            GatewayError::PayloadTooLarge { .. } => {
-                (StatusCode::PAYLOAD_TOO_LARGE, self.to_string())
+            | GatewayError::RateLimited {
+                retry_after_secs, ..
             }
-            GatewayError::InvalidRequest(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            | GatewayError::QuotaExceeded {
+                retry_after_secs, ..
+            } => Some(*retry_after_secs),
+            _ => None,
         };
+        let status = self.status_code();
+        let body = Json(error_body(status, self.code(), &self.to_string()));
+
+        let mut response = (status, body).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
 
-        let body = Json(serde_json::json!({
-            "error": error_message,
-            "status": status.as_u16()
-        }));
+    #[tokio::test]
+    async fn test_every_error_has_its_documented_code() -> Result<(), Box<dyn std::error::Error>> {
+        let cases = [
+            (
+                GatewayError::InvalidRequest("field 'prompt' must not be empty".to_string()),
+                400,
+                "invalid_request",
+            ),
+            (
+                GatewayError::Json(serde_json::from_str::<Value>("{").unwrap_err()),
+                400,
+                "invalid_json",
+            ),
+            (
+                GatewayError::PromptTooLarge("field 'prompt' is 17 bytes".to_string()),
+                400,
+                "prompt_too_large",
+            ),
+            (
+                GatewayError::Auth("missing key".to_string()),
+                401,
+                "unauthorized",
+            ),
+            (
+                GatewayError::Forbidden("inactive".to_string()),
+                403,
+                "forbidden",
+            ),
+            (
+                GatewayError::NotFound("session".to_string()),
+                404,
+                "not_found",
+            ),
+            (GatewayError::Timeout("slow".to_string()), 408, "timeout"),
+            (GatewayError::Conflict("busy".to_string()), 409, "conflict"),
+            (
+                GatewayError::IdempotencyKeyReused("key-1".to_string()),
+                422,
+                "idempotency_key_reused",
+            ),
+            (
+                GatewayError::PayloadTooLarge {
+                    max_size: 1024,
+                    actual_size: None,
+                    path: "/exec".to_string(),
+                },
+                413,
+                "payload_too_large",
+            ),
+            (
+                GatewayError::RateLimited {
+                    message: "2/min".to_string(),
+                    retry_after_secs: 30,
+                },
+                429,
+                "rate_limited",
+            ),
+            (
+                GatewayError::QuotaExceeded {
+                    message: "100/day".to_string(),
+                    retry_after_secs: 3600,
+                },
+                429,
+                "quota_exceeded",
+            ),
+            (
+                GatewayError::Internal("boom".to_string()),
+                500,
+                "internal_error",
+            ),
+            (
+                GatewayError::ServiceUnavailable("breaker open".to_string()),
+                503,
+                "service_unavailable",
+            ),
+            (
+                GatewayError::Overloaded {
+                    message: "queue full".to_string(),
+                    retry_after_secs: 5,
+                },
+                503,
+                "overloaded",
+            ),
+        ];
 
-        (status, body).into_response()
+        for (error, status, code) in cases {
+            let message = error.to_string();
+            let response = error.into_response();
+            assert_eq!(response.status().as_u16(), status, "{code}");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+            let body: Value = serde_json::from_slice(&body)?;
+            assert_eq!(body["code"], code);
+            assert_eq!(body["status"], status);
+            assert_eq!(body["error"], message);
+        }
+        Ok(())
     }
 }
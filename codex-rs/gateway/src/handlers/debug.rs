@@ -0,0 +1,152 @@
+//! Debug handlers
+//!
+//! Served only when `CODEX_ENABLE_DEBUG_ENDPOINTS=1`; otherwise they answer
+//! 404 as if the routes did not exist. They sit behind API key auth like any
+//! other route.
+
+use crate::error::GatewayError;
+use crate::error::GatewayResult;
+use crate::state::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::Json;
+use codex_core::config::types::ShellEnvironmentPolicyInherit;
+use serde_json::Value;
+use serde_json::json;
+use std::collections::BTreeSet;
+
+/// Effective configuration endpoint
+///
+/// Shows which settings took effect after environment variables and the
+/// Codex config were resolved. Secrets are never included: environment
+/// variables are listed by name only, and the prompt prefix is reported as
+/// set or not. `request_timeout_secs` does not apply to the exec routes,
+/// which are bounded by `exec.max_timeout_ms` and `exec.max_lifetime_ms`.
+///
+/// ## Response
+///
+/// ```json
+/// {
+///   "exec": {"max_concurrency": 4, "max_prompt_bytes": 100000, "max_timeout_ms": 600000, ...},
+///   "request_timeout_secs": 30,
+///   "persistence": {"backend": "rollout_files", "codex_home": "/home/gateway/.codex", "persist_default": true},
+///   "cors_allowed_origins": ["https://app.example.com"],
+///   "codex": {"model": "gpt-5", "model_provider": "openai", "sandbox_policy": "read-only"},
+///   "environment": {"inherit": "all", "set": ["CI"], "provider_env_keys": ["OPENAI_API_KEY"]}
+/// }
+/// ```
+///
+/// Returns 404 when debug endpoints are disabled.
+pub async fn debug_config_handler(
+    State(state): State<AppState>,
+) -> GatewayResult<(StatusCode, Json<Value>)> {
+    let config = state.config();
+    if !config.debug_endpoints {
+        return Err(GatewayError::NotFound("Not found".to_string()));
+    }
+
+    let exec = &config.exec;
+    let codex_config = state.codex_service.codex_config();
+    let env_policy = &codex_config.shell_environment_policy;
+    let inherit = match env_policy.inherit {
+        ShellEnvironmentPolicyInherit::Core => "core",
+        ShellEnvironmentPolicyInherit::All => "all",
+        ShellEnvironmentPolicyInherit::None => "none",
+    };
+    let set: BTreeSet<&str> = env_policy.r#set.keys().map(String::as_str).collect();
+    let provider_env_keys: BTreeSet<&str> = codex_config
+        .model_providers
+        .values()
+        .filter_map(|provider| provider.env_key.as_deref())
+        .collect();
+
+    let response = json!({
+        "exec": {
+            "max_concurrency": exec.max_concurrency,
+            "max_prompt_bytes": exec.max_prompt_bytes,
+            "max_timeout_ms": exec.max_timeout_ms,
+            "max_lifetime_ms": exec.max_lifetime_ms,
+            "max_line_bytes": exec.max_line_bytes,
+            "max_inflight": exec.max_inflight,
+            "daily_quota": exec.daily_quota,
+            "redact_output": exec.redact_output,
+            "allowed_models": exec.allowed_models,
+            "allowed_providers": exec.allowed_providers,
+            "default_workdir": exec.default_workdir,
+            "workdir_root": exec.workdir_root,
+            "default_sandbox_mode": exec.default_sandbox_mode,
+            "prompt_prefix_set": exec.prompt_prefix.is_some(),
+            "breaker_threshold": exec.breaker_threshold,
+        },
+        "request_timeout_secs": config.timeouts.request_timeout.as_secs(),
+        "body_limits": {
+            "default": config.body_limits.default_limit,
+            "exec": config.body_limits.exec_limit,
+            "jsonrpc": config.body_limits.jsonrpc_limit,
+            "enabled": config.body_limits.enabled,
+        },
+        "persistence": {
+            "backend": "rollout_files",
+            "codex_home": codex_config.codex_home.display().to_string(),
+            "persist_default": exec.persist_default,
+        },
+        "cors_allowed_origins": config.cors.allowed_origins,
+        "codex": {
+            "model": codex_config.model,
+            "model_provider": codex_config.model_provider_id,
+            "sandbox_policy": codex_config.sandbox_policy.to_string(),
+        },
+        "environment": {
+            "inherit": inherit,
+            "set": set,
+            "provider_env_keys": provider_env_keys,
+        },
+    });
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GatewayConfig;
+    use axum::response::IntoResponse;
+
+    #[tokio::test]
+    async fn test_debug_config_is_404_when_disabled() -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+
+        let err = debug_config_handler(State(state)).await.unwrap_err();
+        assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_debug_config_reports_effective_settings() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let mut config = GatewayConfig {
+            debug_endpoints: true,
+            ..Default::default()
+        };
+        config.exec.max_concurrency = 7;
+        config.exec.prompt_prefix = Some("secret guardrail".to_string());
+        config.cors.allowed_origins = vec!["https://app.example.com".to_string()];
+        let state = AppState::new(config).await?;
+
+        let (status, Json(body)) = debug_config_handler(State(state)).await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["exec"]["max_concurrency"], 7);
+        assert_eq!(body["exec"]["prompt_prefix_set"], true);
+        assert_eq!(body["cors_allowed_origins"][0], "https://app.example.com");
+        for key in [
+            "request_timeout_secs",
+            "persistence",
+            "codex",
+            "environment",
+        ] {
+            assert!(body.get(key).is_some(), "missing {key}");
+        }
+        assert!(!body.to_string().contains("secret guardrail"));
+        Ok(())
+    }
+}
@@ -31,35 +31,93 @@
 //! - **Output Schema**: Supports JSON schema validation
 //! - **Resumable**: Can resume conversations via session_id
 
+use crate::config::ExecConfig;
 use crate::error::GatewayError;
 use crate::error::GatewayResult;
+use crate::handlers::sessions::check_session_owner;
+use crate::metrics::ExecOutcome;
+use crate::middleware::ApiKeyId;
+use crate::middleware::ApiKeyScope;
+use crate::services::audit_log::AuditRecord;
+use crate::services::exec_results::ExecResult;
+use crate::services::exec_results::ExecRetries;
+use crate::services::idempotency::CachedReply;
+use crate::services::idempotency::IDEMPOTENCY_KEY_HEADER;
+use crate::services::idempotency::IDEMPOTENT_REPLAYED_HEADER;
+use crate::services::idempotency::MAX_KEY_LEN;
+use crate::services::idempotency::request_fingerprint;
+use crate::services::prompt_store::PromptStore;
+use crate::services::redaction;
 use crate::state::AppState;
+use crate::telemetry;
+use axum::body::Body;
+use axum::extract::Extension;
+use axum::extract::Query;
 use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::http::HeaderValue;
 use axum::http::StatusCode;
+use axum::http::header;
+use axum::response::IntoResponse;
 use axum::response::Json;
+use axum::response::Response;
 use codex_exec::event_processor_with_jsonl_output::EventProcessorWithJsonOutput;
+use codex_exec::exec_events::ItemCompletedEvent;
+use codex_exec::exec_events::ItemStartedEvent;
+use codex_exec::exec_events::ItemUpdatedEvent;
+use codex_exec::exec_events::PatchApplyStatus;
+use codex_exec::exec_events::PatchChangeKind;
 use codex_exec::exec_events::ThreadEvent;
+use codex_exec::exec_events::ThreadItemDetails;
+use codex_exec::exec_events::Usage;
+use codex_protocol::config_types::SandboxMode;
+use codex_protocol::protocol::AskForApproval;
 use codex_protocol::protocol::EventMsg;
 use codex_protocol::protocol::Op;
+use codex_protocol::protocol::SandboxPolicy;
+use codex_protocol::protocol::TurnAbortReason;
 use codex_protocol::user_input::UserInput;
+use futures::Stream;
+use futures::StreamExt;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::Value;
+use serde_json::json;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::path::Path;
 use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
 use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tracing::Instrument;
 use tracing::debug;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
+use url::Url;
+use uuid::Uuid;
 
 /// Request structure for exec endpoint
 ///
 /// Accepts a prompt and optional parameters for customizing the execution.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ExecRequest {
-    /// User prompt to execute
+    /// User prompt to execute (omit when `prompt_ref` is set)
+    #[serde(default)]
     pub prompt: String,
 
+    /// `gs://bucket/object` holding the prompt, for prompts too large to inline
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_ref: Option<String>,
+
     /// Optional session ID for resuming conversations
     #[serde(skip_serializing_if = "Option::is_none")]
     pub session_id: Option<String>,
@@ -74,17 +132,518 @@ pub struct ExecRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub output_schema: Option<Value>,
 
-    /// Current working directory override
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Working directory for the turn (defaults to `CODEX_DEFAULT_WORKDIR`);
+    /// must be an existing directory inside `CODEX_WORKDIR_ROOT` when that is set
+    #[serde(alias = "working_dir", skip_serializing_if = "Option::is_none")]
     pub cwd: Option<PathBuf>,
 
-    /// Model override (e.g., "gpt-5", "o3")
+    /// Model override (e.g., "gpt-5", "o3"), checked against `CODEX_ALLOWED_MODELS`
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
 
+    /// Model provider override (a provider id from the Codex config, e.g. "openai"),
+    /// checked against `CODEX_ALLOWED_PROVIDERS`; applies when the session's
+    /// conversation is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+
     /// Sandbox mode override ("read-only", "workspace-write", "danger-full-access")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sandbox_mode: Option<String>,
+
+    /// When the agent stops to ask for approval ("never", "on-request",
+    /// "on-failure", "unless-trusted"); defaults to "never" since `/exec`
+    /// has no way to answer an approval request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<String>,
+
+    /// Longest the turn may go without producing an event, in milliseconds
+    /// (clamped to `CODEX_MAX_TIMEOUT_MS`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+
+    /// Longest the turn may run in total, even while it keeps producing
+    /// events, in milliseconds (clamped to `CODEX_MAX_LIFETIME_MS`, which
+    /// also applies when this is unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_lifetime_ms: Option<u64>,
+
+    /// URL that receives the terminal payload as a signed POST instead of
+    /// holding the request open (requires `CODEX_WEBHOOK_SECRET`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_url: Option<String>,
+
+    /// Extra instructions placed after `CODEX_PROMPT_PREFIX` and before the prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_prefix: Option<String>,
+
+    /// Validate the request and return the resolved turn parameters without
+    /// running the agent
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// Values substituted for `{{name}}` placeholders in the prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variables: Option<HashMap<String, String>>,
+
+    /// Reject the request when the prompt references a variable that was not
+    /// given, instead of leaving the placeholder as is
+    #[serde(default)]
+    pub strict_templating: bool,
+
+    /// Keep the session rollout and `/sessions/{id}/result` entry (defaults
+    /// to `CODEX_PERSIST_DEFAULT`); events are returned either way. `false`
+    /// is rejected for a `session_id` that already has a conversation, whose
+    /// rollout holds its earlier turns.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persist: Option<bool>,
+
+    /// `key_id` of the API key that sent the request, for the audit log
+    #[serde(skip)]
+    pub api_key_id: Option<String>,
+
+    /// Receives each event as it is collected, for streaming endpoints
+    #[serde(skip)]
+    pub event_sink: Option<mpsc::UnboundedSender<ThreadEvent>>,
+
+    /// `/healthz/deep` probe: kept out of metrics, the audit log and
+    /// `/sessions/active`
+    #[serde(skip)]
+    pub smoke: bool,
+}
+
+impl ExecRequest {
+    /// Whether the turn's rollout and result may be stored
+    pub fn persist(&self, limits: &ExecConfig) -> bool {
+        self.persist.unwrap_or(limits.persist_default)
+    }
+
+    /// Replace `prompt_ref` with the prompt text it points to
+    ///
+    /// Rejects requests that set both `prompt` and `prompt_ref`. The object
+    /// may be at most `CODEX_MAX_PROMPT_BYTES` long.
+    pub async fn resolve_prompt_ref(
+        &mut self,
+        store: &PromptStore,
+        limits: &ExecConfig,
+    ) -> GatewayResult<()> {
+        let Some(prompt_ref) = self.prompt_ref.take() else {
+            return Ok(());
+        };
+        if !self.prompt.is_empty() {
+            return Err(GatewayError::InvalidRequest(
+                "set either 'prompt' or 'prompt_ref', not both".to_string(),
+            ));
+        }
+
+        self.prompt = store.fetch(&prompt_ref, limits.max_prompt_bytes).await?;
+        info!(
+            "Resolved prompt_ref {} ({} bytes)",
+            prompt_ref,
+            self.prompt.len()
+        );
+        Ok(())
+    }
+
+    /// Validate the request against the configured exec limits
+    ///
+    /// Renders `variables` into the prompt, rejects an empty or oversized
+    /// prompt, resolves `cwd` and clamps `timeout_ms` and `max_lifetime_ms`
+    /// to the configured maximums.
+    pub fn validate(&mut self, limits: &ExecConfig) -> GatewayResult<()> {
+        if self.variables.is_some() || self.strict_templating {
+            let variables = self.variables.take().unwrap_or_default();
+            self.prompt = render_prompt(&self.prompt, &variables, self.strict_templating)?;
+        }
+        validate_prompt(&self.prompt, limits)?;
+        if let Some(session_id) = &self.session_id {
+            validate_session_id(session_id)?;
+        }
+        validate_model_selection(self.model.as_deref(), self.provider.as_deref(), limits)?;
+        self.cwd = resolve_workdir(self.cwd.as_deref(), limits)?;
+
+        if let Some(mode) = &self.sandbox_mode {
+            parse_sandbox_mode(mode)?;
+        }
+        resolve_approval_policy(self.approval_policy.as_deref())?;
+
+        if let Some(callback_url) = &self.callback_url {
+            validate_callback_url(callback_url)?;
+        }
+
+        if let Some(timeout_ms) = self.timeout_ms
+            && timeout_ms > limits.max_timeout_ms
+        {
+            debug!(
+                "Clamping timeout_ms from {} to {}",
+                timeout_ms, limits.max_timeout_ms
+            );
+            self.timeout_ms = Some(limits.max_timeout_ms);
+        }
+        if let Some(max_lifetime_ms) = self.max_lifetime_ms
+            && max_lifetime_ms > limits.max_lifetime_ms
+        {
+            debug!(
+                "Clamping max_lifetime_ms from {} to {}",
+                max_lifetime_ms, limits.max_lifetime_ms
+            );
+            self.max_lifetime_ms = Some(limits.max_lifetime_ms);
+        }
+
+        Ok(())
+    }
+
+    /// Inactivity and lifetime bounds the turn runs under, after clamping
+    pub fn turn_limits(&self, limits: &ExecConfig) -> TurnLimits {
+        TurnLimits {
+            timeout_ms: self.timeout_ms,
+            max_lifetime_ms: self.max_lifetime_ms.unwrap_or(limits.max_lifetime_ms),
+        }
+    }
+}
+
+/// Time bounds of one turn, reported in `task_started`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnLimits {
+    /// Longest the turn may go without an event; `None` means no bound
+    pub timeout_ms: Option<u64>,
+    /// Longest the turn may run in total
+    pub max_lifetime_ms: u64,
+}
+
+impl TurnLimits {
+    /// Bounds for a turn that asked for `timeout_ms` and `max_lifetime_ms`,
+    /// clamped to the configured maximums
+    pub fn clamped(
+        timeout_ms: Option<u64>,
+        max_lifetime_ms: Option<u64>,
+        limits: &ExecConfig,
+    ) -> Self {
+        Self {
+            timeout_ms: timeout_ms.map(|timeout_ms| timeout_ms.min(limits.max_timeout_ms)),
+            max_lifetime_ms: max_lifetime_ms
+                .unwrap_or(limits.max_lifetime_ms)
+                .min(limits.max_lifetime_ms),
+        }
+    }
+}
+
+/// Substitute `{{name}}` placeholders in `prompt` with `variables`
+///
+/// Names may be padded with spaces (`{{ name }}`) and consist of letters,
+/// digits, `_`, `-` and `.`. Anything else between braces is left untouched,
+/// as are placeholders without a value unless `strict` is set, in which case
+/// they are rejected with 400.
+pub fn render_prompt(
+    prompt: &str,
+    variables: &HashMap<String, String>,
+    strict: bool,
+) -> GatewayResult<String> {
+    let mut rendered = String::with_capacity(prompt.len());
+    let mut missing: Vec<&str> = Vec::new();
+    let mut rest = prompt;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+        let Some(end) = after_open.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let name = after_open[..end].trim();
+        let is_placeholder = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+        if !is_placeholder {
+            rendered.push_str("{{");
+            rest = after_open;
+            continue;
+        }
+
+        match variables.get(name) {
+            Some(value) => rendered.push_str(value),
+            None => {
+                if !missing.contains(&name) {
+                    missing.push(name);
+                }
+                rendered.push_str(&rest[start..start + 2 + end + 2]);
+            }
+        }
+        rest = &after_open[end + 2..];
+    }
+    rendered.push_str(rest);
+
+    if strict && !missing.is_empty() {
+        return Err(GatewayError::InvalidRequest(format!(
+            "prompt references undefined variables: {}",
+            missing.join(", ")
+        )));
+    }
+    Ok(rendered)
+}
+
+/// Parse a `sandbox_mode` value ("read-only", "workspace-write", "danger-full-access")
+pub fn parse_sandbox_mode(mode: &str) -> GatewayResult<SandboxMode> {
+    match mode {
+        "read-only" => Ok(SandboxMode::ReadOnly),
+        "workspace-write" => Ok(SandboxMode::WorkspaceWrite),
+        "danger-full-access" => Ok(SandboxMode::DangerFullAccess),
+        other => Err(GatewayError::InvalidRequest(format!(
+            "field 'sandbox_mode' must be one of read-only, workspace-write, danger-full-access (got '{other}')"
+        ))),
+    }
+}
+
+/// Resolve an `approval_policy` value, defaulting to `never`
+pub fn resolve_approval_policy(policy: Option<&str>) -> GatewayResult<AskForApproval> {
+    match policy {
+        None | Some("never") => Ok(AskForApproval::Never),
+        Some("on-request") => Ok(AskForApproval::OnRequest),
+        Some("on-failure") => Ok(AskForApproval::OnFailure),
+        Some("unless-trusted" | "untrusted") => Ok(AskForApproval::UnlessTrusted),
+        Some(other) => Err(GatewayError::InvalidRequest(format!(
+            "field 'approval_policy' must be one of never, on-request, on-failure, unless-trusted (got '{other}')"
+        ))),
+    }
+}
+
+/// Reject a turn whose sandbox policy the calling API key is not scoped for
+///
+/// `policy` is the one the turn actually runs under (see
+/// [`effective_sandbox_policy`]), so leaving out `sandbox_mode` does not get
+/// around the scope when the default policy is broader.
+pub fn check_sandbox_scope(policy: &SandboxPolicy, scope: &ApiKeyScope) -> GatewayResult<()> {
+    let mode = match policy {
+        SandboxPolicy::ReadOnly => SandboxMode::ReadOnly,
+        SandboxPolicy::WorkspaceWrite { .. } => SandboxMode::WorkspaceWrite,
+        SandboxPolicy::DangerFullAccess => SandboxMode::DangerFullAccess,
+    };
+
+    if scope.allows_sandbox_mode(mode) {
+        Ok(())
+    } else {
+        Err(GatewayError::Forbidden(format!(
+            "API key is not allowed to run with sandbox_mode '{mode}'"
+        )))
+    }
+}
+
+/// Sandbox policy of a turn requesting `sandbox_mode`, with the gateway's
+/// `CODEX_DEFAULT_SANDBOX_POLICY` and configured policy applied
+pub fn effective_sandbox_policy(
+    state: &AppState,
+    sandbox_mode: Option<&str>,
+) -> GatewayResult<SandboxPolicy> {
+    resolve_sandbox_policy(
+        sandbox_mode,
+        state.config().exec.default_sandbox_mode,
+        &state.codex_service.codex_config().sandbox_policy,
+    )
+}
+
+/// Resolve the sandbox policy for a turn
+///
+/// Without an override `default_mode` (`CODEX_DEFAULT_SANDBOX_POLICY`)
+/// applies, and without that the configured policy is used as-is. A
+/// `workspace-write` mode keeps the configured writable roots when the
+/// configured policy is already workspace-write.
+pub fn resolve_sandbox_policy(
+    sandbox_mode: Option<&str>,
+    default_mode: Option<SandboxMode>,
+    configured: &SandboxPolicy,
+) -> GatewayResult<SandboxPolicy> {
+    let mode = match (sandbox_mode, default_mode) {
+        (Some(mode), _) => parse_sandbox_mode(mode)?,
+        (None, Some(mode)) => mode,
+        (None, None) => return Ok(configured.clone()),
+    };
+
+    let policy = match mode {
+        SandboxMode::ReadOnly => SandboxPolicy::new_read_only_policy(),
+        SandboxMode::WorkspaceWrite => match configured {
+            SandboxPolicy::WorkspaceWrite { .. } => configured.clone(),
+            _ => SandboxPolicy::new_workspace_write_policy(),
+        },
+        SandboxMode::DangerFullAccess => SandboxPolicy::DangerFullAccess,
+    };
+    Ok(policy)
+}
+
+/// Resolve the working directory for a turn
+///
+/// Uses `requested`, else `CODEX_DEFAULT_WORKDIR`; `None` means the Codex
+/// config's cwd applies. The directory must exist (400) and, when
+/// `CODEX_WORKDIR_ROOT` is set, resolve inside it after following `..` and
+/// symlinks (403). Relative paths are taken from the root when one is set.
+pub fn resolve_workdir(
+    requested: Option<&Path>,
+    limits: &ExecConfig,
+) -> GatewayResult<Option<PathBuf>> {
+    let Some(dir) = requested.or(limits.default_workdir.as_deref()) else {
+        return Ok(None);
+    };
+
+    let dir = match &limits.workdir_root {
+        Some(root) if dir.is_relative() => root.join(dir),
+        _ => dir.to_path_buf(),
+    };
+    let resolved = std::fs::canonicalize(&dir).map_err(|e| {
+        GatewayError::InvalidRequest(format!("field 'cwd' {} does not exist: {e}", dir.display()))
+    })?;
+    if !resolved.is_dir() {
+        return Err(GatewayError::InvalidRequest(format!(
+            "field 'cwd' {} is not a directory",
+            dir.display()
+        )));
+    }
+
+    if let Some(root) = &limits.workdir_root {
+        let root = std::fs::canonicalize(root).map_err(|e| {
+            GatewayError::Config(format!(
+                "CODEX_WORKDIR_ROOT {} is not usable: {e}",
+                root.display()
+            ))
+        })?;
+        if !resolved.starts_with(&root) {
+            return Err(GatewayError::Forbidden(format!(
+                "field 'cwd' {} is outside the allowed root",
+                dir.display()
+            )));
+        }
+    }
+
+    Ok(Some(resolved))
+}
+
+/// Longest accepted client-supplied `session_id`
+const MAX_SESSION_ID_LEN: usize = 64;
+
+/// How long a turn whose client disconnected may keep running before it is
+/// interrupted, so turns that are about to finish are not cut short
+const DISCONNECT_GRACE: Duration = Duration::from_secs(2);
+
+/// Content type of `/exec/ndjson` responses
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Header breaking an `/exec` reply's time down by phase
+pub const SERVER_TIMING_HEADER: &str = "server-timing";
+
+/// Require a client-supplied `session_id` to be 1-64 of `[A-Za-z0-9_-]`
+///
+/// Session IDs end up in logs and storage keys, so separators like `/`, `.`
+/// or whitespace are rejected outright. UUIDs pass as-is.
+pub fn validate_session_id(session_id: &str) -> GatewayResult<()> {
+    let valid = !session_id.is_empty()
+        && session_id.len() <= MAX_SESSION_ID_LEN
+        && session_id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+    if valid {
+        Ok(())
+    } else {
+        Err(GatewayError::InvalidRequest(format!(
+            "field 'session_id' must be 1 to {MAX_SESSION_ID_LEN} characters of A-Z, a-z, 0-9, '_' or '-'"
+        )))
+    }
+}
+
+/// Reject `persist: false` for a session that already has a conversation
+///
+/// Discarding the rollout would delete the session's earlier turns too.
+/// Only an explicit `false` is rejected; under `CODEX_PERSIST_DEFAULT=false`
+/// an existing session simply keeps its rollout.
+pub async fn check_persist(
+    state: &AppState,
+    session_id: Option<&str>,
+    persist: Option<bool>,
+) -> GatewayResult<()> {
+    if persist == Some(false)
+        && let Some(session_id) = session_id
+        && state.codex_service.has_session(session_id).await
+    {
+        return Err(GatewayError::InvalidRequest(format!(
+            "field 'persist' cannot be false for session '{session_id}', which already has stored turns"
+        )));
+    }
+    Ok(())
+}
+
+/// Require `callback_url` to be an absolute http(s) URL
+fn validate_callback_url(callback_url: &str) -> GatewayResult<()> {
+    let url = Url::parse(callback_url).map_err(|e| {
+        GatewayError::InvalidRequest(format!("field 'callback_url' is not a valid URL: {e}"))
+    })?;
+    match url.scheme() {
+        "http" | "https" => Ok(()),
+        other => Err(GatewayError::InvalidRequest(format!(
+            "field 'callback_url' must use http or https (got '{other}')"
+        ))),
+    }
+}
+
+/// Reject a `model` or `provider` outside `CODEX_ALLOWED_MODELS` / `CODEX_ALLOWED_PROVIDERS`
+///
+/// An empty allowlist accepts any value.
+pub fn validate_model_selection(
+    model: Option<&str>,
+    provider: Option<&str>,
+    limits: &ExecConfig,
+) -> GatewayResult<()> {
+    let check = |field: &str, value: Option<&str>, allowed: &[String]| match value {
+        Some(value) if !allowed.is_empty() && !allowed.iter().any(|a| a == value) => {
+            Err(GatewayError::InvalidRequest(format!(
+                "field '{field}' must be one of {} (got '{value}')",
+                allowed.join(", ")
+            )))
+        }
+        _ => Ok(()),
+    };
+
+    check("model", model, &limits.allowed_models)?;
+    check("provider", provider, &limits.allowed_providers)
+}
+
+/// Instructions prepended to a prompt: `CODEX_PROMPT_PREFIX`, then the
+/// request's own `prompt_prefix`
+///
+/// A request can only add instructions; the configured prefix always comes first.
+pub fn prompt_prefix(limits: &ExecConfig, request_prefix: Option<&str>) -> Option<String> {
+    let parts: Vec<&str> = [limits.prompt_prefix.as_deref(), request_prefix]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("\n\n"))
+}
+
+/// Prepend `prefix` to `prompt`, separated by a blank line
+pub fn apply_prompt_prefix(prefix: Option<&str>, prompt: &str) -> String {
+    match prefix {
+        Some(prefix) => format!("{prefix}\n\n{prompt}"),
+        None => prompt.to_string(),
+    }
+}
+
+/// Reject prompts that are blank or larger than `CODEX_MAX_PROMPT_BYTES`
+pub fn validate_prompt(prompt: &str, limits: &ExecConfig) -> GatewayResult<()> {
+    if prompt.trim().is_empty() {
+        return Err(GatewayError::InvalidRequest(
+            "field 'prompt' must not be empty".to_string(),
+        ));
+    }
+
+    if prompt.len() > limits.max_prompt_bytes {
+        return Err(GatewayError::PromptTooLarge(format!(
+            "field 'prompt' is {} bytes, exceeds the maximum of {} bytes",
+            prompt.len(),
+            limits.max_prompt_bytes
+        )));
+    }
+
+    Ok(())
 }
 
 /// Response structure for exec endpoint
@@ -98,12 +657,192 @@ pub struct ExecResponse {
     /// Array of JSONL events (matches `codex exec --json` format)
     pub events: Vec<ThreadEvent>,
 
-    /// Final status: "completed", "failed", or "error"
-    pub status: String,
+    /// Final status of the turn
+    ///
+    /// A "timeout" or "lifetime_exceeded" response (HTTP 408) still carries
+    /// every event produced before the turn was stopped.
+    pub status: ExecStatus,
+
+    /// Files the agent created or modified during the turn, in first-seen order
+    pub created_files: Vec<String>,
+
+    /// Instructions that were prepended to the prompt, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_prefix: Option<String>,
 
-    /// Optional error message if status is "error"
+    /// Why the exec did not succeed, for "error", "timeout", "lifetime_exceeded"
+    /// and "unknown" statuses
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+
+    /// Retries made while the turn ran, by category
+    pub retries: ExecRetries,
+
+    /// Where the exec's time went; sent as `Server-Timing`, not in the body
+    #[serde(skip)]
+    pub timings: ExecTimings,
+}
+
+/// Time one exec spent in each phase
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecTimings {
+    /// Waiting for an execution slot
+    pub queue: Duration,
+    /// Getting or creating the session's conversation
+    pub session: Duration,
+    /// Submitting the turn and collecting its events
+    pub exec: Duration,
+    /// Metrics, audit, rollout and result bookkeeping after the turn
+    pub teardown: Duration,
+}
+
+impl ExecTimings {
+    /// Phases by their `Server-Timing` metric name, in order
+    pub fn phases(&self) -> [(&'static str, Duration); 5] {
+        [
+            ("queue", self.queue),
+            ("session", self.session),
+            ("exec", self.exec),
+            ("teardown", self.teardown),
+            (
+                "total",
+                self.queue + self.session + self.exec + self.teardown,
+            ),
+        ]
+    }
+
+    /// `Server-Timing` header value, e.g. `queue;dur=0.1, session;dur=3.2, ...`
+    ///
+    /// Durations are in milliseconds, as the header defines.
+    pub fn server_timing(&self) -> String {
+        self.phases()
+            .iter()
+            .map(|(name, duration)| format!("{name};dur={:.1}", millis(*duration)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The phases as a JSON object of milliseconds
+    pub fn to_json(&self) -> Value {
+        self.phases()
+            .iter()
+            .map(|(name, duration)| (format!("{name}_ms"), json!(millis(*duration))))
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+}
+
+/// `duration` in fractional milliseconds
+fn millis(duration: Duration) -> f64 {
+    duration.as_micros() as f64 / 1000.0
+}
+
+/// How an exec turn finished
+///
+/// Serialized as a lowercase string ("completed", "failed", ...), which is
+/// the wire format clients already parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecStatus {
+    /// The turn finished with a `turn.completed` event
+    Completed,
+    /// The turn ended with a `turn.failed` event
+    Failed,
+    /// The agent reported an `error` event
+    Error,
+    /// The turn was interrupted through `/sessions/{id}/cancel`
+    Cancelled,
+    /// `timeout_ms` elapsed without the turn producing an event
+    Timeout,
+    /// The turn was still running when its lifetime (`max_lifetime_ms` or
+    /// `CODEX_MAX_LIFETIME_MS`) ran out
+    LifetimeExceeded,
+    /// The event stream ended without a terminal event
+    Unknown,
+}
+
+impl ExecStatus {
+    /// Wire name of the status
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExecStatus::Completed => "completed",
+            ExecStatus::Failed => "failed",
+            ExecStatus::Error => "error",
+            ExecStatus::Cancelled => "cancelled",
+            ExecStatus::Timeout => "timeout",
+            ExecStatus::LifetimeExceeded => "lifetime_exceeded",
+            ExecStatus::Unknown => "unknown",
+        }
+    }
+
+    /// Metrics bucket the status is counted under
+    pub fn outcome(self) -> ExecOutcome {
+        match self {
+            ExecStatus::Completed => ExecOutcome::Completed,
+            ExecStatus::Cancelled => ExecOutcome::Cancelled,
+            ExecStatus::Timeout | ExecStatus::LifetimeExceeded => ExecOutcome::TimedOut,
+            ExecStatus::Failed | ExecStatus::Error | ExecStatus::Unknown => ExecOutcome::Failed,
+        }
+    }
+}
+
+impl std::fmt::Display for ExecStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Every `type` a [`ThreadEvent`] can have
+pub const EVENT_TYPES: &[&str] = &[
+    "thread.started",
+    "turn.started",
+    "turn.completed",
+    "turn.failed",
+    "item.started",
+    "item.updated",
+    "item.completed",
+    "error",
+];
+
+/// Query parameters for `POST /exec`
+#[derive(Debug, Default, Deserialize)]
+pub struct ExecQuery {
+    /// Comma-separated event types to return (e.g. `turn.completed,error`);
+    /// all events are returned when absent
+    pub events: Option<String>,
+}
+
+impl ExecQuery {
+    /// Parse the `events` allowlist, rejecting unknown event types
+    pub fn event_filter(&self) -> GatewayResult<Option<Vec<String>>> {
+        let Some(events) = &self.events else {
+            return Ok(None);
+        };
+
+        let mut allowed = Vec::new();
+        for event_type in events.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            if !EVENT_TYPES.contains(&event_type) {
+                return Err(GatewayError::InvalidRequest(format!(
+                    "query 'events' has unknown event type '{event_type}'; expected one of: {}",
+                    EVENT_TYPES.join(", ")
+                )));
+            }
+            allowed.push(event_type.to_string());
+        }
+        Ok(Some(allowed))
+    }
+}
+
+/// Keep only the events whose `type` is in `allowed` in a reply body's `events`
+fn filter_events(body: &mut Value, allowed: &[String]) {
+    if let Some(Value::Array(events)) = body.get_mut("events") {
+        events.retain(|event| {
+            event
+                .get("type")
+                .and_then(Value::as_str)
+                .is_some_and(|event_type| allowed.iter().any(|a| a == event_type))
+        });
+    }
 }
 
 /// Request structure for resume endpoint
@@ -165,64 +904,675 @@ pub struct ResumeResponse {
 ///   "status": "completed"
 /// }
 /// ```
+///
+/// ## Callbacks
+///
+/// With `callback_url` set the endpoint returns 202 right away with the
+/// `session_id` (generated when absent) and POSTs the response above to the
+/// URL once the turn finishes, signed as described in [`crate::services::callback`].
+/// A timed-out turn is delivered as a "timeout" (or "lifetime_exceeded")
+/// response with its partial events; if the turn cannot run at all the
+/// callback body is `{"status": "error", "session_id": ..., "error": ...}`.
+/// A `callback_url` whose host resolves to a non-public address, or is not
+/// in `CODEX_CALLBACK_ALLOWED_HOSTS` when that is set, is rejected with 400.
+///
+/// ## Idempotency
+///
+/// Requests with an `Idempotency-Key` header run once per key within
+/// `CODEX_IDEMPOTENCY_TTL_SECS`. Repeats, even while the first is still
+/// running, receive the same status and body with `Idempotent-Replayed: true`
+/// instead of starting another turn. Failed requests are not cached. Keys
+/// are scoped to the API key that sent them, and reusing one with a
+/// different body returns 422 with `"code": "idempotency_key_reused"`.
+///
+/// ## Dry run
+///
+/// With `"dry_run": true` the request is validated and the resolved model,
+/// provider, cwd, sandbox and approval policy are returned with status
+/// `"dry_run"`; no agent turn runs and nothing is billed.
+///
+/// ## Scopes
+///
+/// Keys from `CODEX_API_KEYS_JSON` may only run under the sandbox modes
+/// listed for them. The mode checked is the one the turn would run with:
+/// `sandbox_mode`, else `CODEX_DEFAULT_SANDBOX_POLICY`, else the configured
+/// policy. Any other mode returns 403.
+///
+/// ## Prompt allowlist
+///
+/// When `CODEX_PROMPT_ALLOWLIST` is set, a prompt that matches none of its
+/// patterns is rejected with 403 before any turn starts.
+///
+/// ## Prompt references
+///
+/// Instead of `prompt`, a request may send `"prompt_ref": "gs://bucket/object"`;
+/// the object is read from GCS (up to `CODEX_MAX_PROMPT_BYTES`) and used as the
+/// prompt. Setting both is a 400.
+///
+/// ## Event filter
+///
+/// `POST /exec?events=turn.completed,error` returns only the listed event
+/// types in `events`. Status, `created_files` and `error` are still derived
+/// from the full stream, and callbacks always receive every event.
+///
+/// ## Client disconnects
+///
+/// If the client disconnects while the turn runs, the turn is interrupted
+/// unless it finishes within a short grace period. It is still counted in
+/// metrics and the audit log, its rollout is still discarded under
+/// `persist: false`, and with a `session_id` its result is still stored.
+///
+/// ## Overload
+///
+/// With `CODEX_MAX_INFLIGHT` set, an exec arriving while that many are
+/// running or queued for a slot is rejected with 503 and `Retry-After`.
+///
+/// ## Daily quota
+///
+/// With `CODEX_DAILY_QUOTA` set, a key that has started that many execs in
+/// the last 24 hours gets 429 with `"code": "quota_exceeded"` and
+/// `Retry-After`. Dry runs, idempotent replays and execs rejected for any
+/// other reason (overload, open circuit breaker, ...) are not counted.
+///
+/// ## Timings
+///
+/// Replies to execs that ran (not callbacks, dry runs or replays) carry a
+/// `Server-Timing` header with the time spent queued for a slot, getting
+/// the session, running the turn, tearing down, and in total:
+///
+/// ```text
+/// Server-Timing: queue;dur=0.0, session;dur=4.1, exec;dur=5210.7, teardown;dur=1.3, total;dur=5216.1
+/// ```
 pub async fn handle_exec(
     State(state): State<AppState>,
-    Json(request): Json<ExecRequest>,
-) -> GatewayResult<(StatusCode, Json<ExecResponse>)> {
-    info!(
-        "Exec request received: prompt_len={}, session_id={:?}",
-        request.prompt.len(),
-        request.session_id
-    );
-
-    // 1. Get or create conversation
-    let conversation_id = state
-        .codex_service
-        .get_or_create_conversation(request.session_id.as_deref())
+    key_id: Option<Extension<ApiKeyId>>,
+    scope: Option<Extension<ApiKeyScope>>,
+    Query(query): Query<ExecQuery>,
+    headers: HeaderMap,
+    Json(mut request): Json<ExecRequest>,
+) -> GatewayResult<Response> {
+    let event_filter = query.event_filter()?;
+    // Fingerprint the body as sent, before validation rewrites it
+    let idempotency = match idempotency_key(&headers)? {
+        Some(key) => Some((key, request_fingerprint(&request)?)),
+        None => None,
+    };
+    request.api_key_id = key_id.map(|Extension(ApiKeyId(id))| id);
+    request
+        .resolve_prompt_ref(&state.prompt_store, &state.config().exec)
         .await?;
+    request.validate(&state.config().exec)?;
+    state.prompt_allowlist.check(&request.prompt)?;
+    if let Some(Extension(scope)) = &scope {
+        check_sandbox_scope(
+            &effective_sandbox_policy(&state, request.sandbox_mode.as_deref())?,
+            scope,
+        )?;
+    }
+    if request.dry_run {
+        return Ok((StatusCode::OK, Json(dry_run_plan(&state, &request)?)).into_response());
+    }
 
-    debug!("Using conversation_id: {}", conversation_id);
-
-    // 2. Get conversation from ConversationManager
-    let conversation = {
-        let manager = state.codex_service.conversation_manager().lock().await;
-        manager
-            .get_conversation(conversation_id)
-            .await
-            .map_err(|e| GatewayError::Internal(format!("Failed to get conversation: {e}")))?
+    let Some((key, fingerprint)) = idempotency else {
+        let ((status_code, mut body), timings) = execute_exec(state, request).await?;
+        if let Some(allowed) = &event_filter {
+            filter_events(&mut body, allowed);
+        }
+        return Ok(with_server_timing(
+            (status_code, Json(body)).into_response(),
+            timings,
+        ));
     };
 
-    // 3. Prepare UserInputs from request
-    let user_inputs = prepare_user_inputs(&request)?;
-    debug!("Prepared {} user inputs", user_inputs.len());
+    // Repeats of a key wait for and replay the first request's reply; the
+    // cached reply keeps every event so each repeat can apply its own filter
+    let mut replayed = true;
+    let mut timings = None;
+    let timings_slot = &mut timings;
+    let owner = request.api_key_id.clone().unwrap_or_default();
+    let (status_code, mut body) = state
+        .idempotency
+        .slot(&owner, &key, &fingerprint)?
+        .get_or_try_init(|| {
+            replayed = false;
+            let state = state.clone();
+            async move {
+                let (reply, exec_timings) = execute_exec(state, request).await?;
+                *timings_slot = exec_timings;
+                Ok::<_, GatewayError>(reply)
+            }
+        })
+        .await?
+        .clone();
+    if let Some(allowed) = &event_filter {
+        filter_events(&mut body, allowed);
+    }
 
-    // 4. Get config for Op::UserTurn params
-    let config = state.codex_service.codex_config();
-    let cwd = request.cwd.unwrap_or_else(|| config.cwd.clone());
-    let model = request.model.unwrap_or_else(|| config.model.clone());
+    if replayed {
+        info!("Replaying exec reply for Idempotency-Key {}", key);
+        return Ok((
+            status_code,
+            [(IDEMPOTENT_REPLAYED_HEADER, "true")],
+            Json(body),
+        )
+            .into_response());
+    }
+    Ok(with_server_timing(
+        (status_code, Json(body)).into_response(),
+        timings,
+    ))
+}
 
-    // 5. Create channel for event collection
-    let (tx, mut rx) = mpsc::unbounded_channel::<ThreadEvent>();
+/// Add a `Server-Timing` header when the exec's timings are known
+fn with_server_timing(mut response: Response, timings: Option<ExecTimings>) -> Response {
+    if let Some(timings) = timings
+        && let Ok(value) = HeaderValue::from_str(&timings.server_timing())
+    {
+        response.headers_mut().insert(SERVER_TIMING_HEADER, value);
+    }
+    response
+}
 
-    // 6. Spawn background task to process events using REAL EventProcessorWithJsonOutput
-    let conversation_clone = conversation.clone();
-    tokio::spawn(async move {
-        let mut processor = EventProcessorWithJsonOutput::new(None);
+/// Resolve everything a turn would be submitted with, without running it
+///
+/// Checks the provider and images the same way a real exec does, but takes
+/// no execution slot and creates no conversation.
+fn dry_run_plan(state: &AppState, request: &ExecRequest) -> GatewayResult<Value> {
+    let config = state.codex_service.codex_config();
+    if let Some(provider) = &request.provider {
+        state.codex_service.model_provider(provider)?;
+    }
+    let user_inputs = prepare_user_inputs(request)?;
+    let sandbox_policy = effective_sandbox_policy(state, request.sandbox_mode.as_deref())?;
+    let approval_policy = resolve_approval_policy(request.approval_policy.as_deref())?;
+    let prompt_prefix = prompt_prefix(&state.config().exec, request.prompt_prefix.as_deref());
+    let turn_limits = request.turn_limits(&state.config().exec);
 
-        loop {
-            match conversation_clone.next_event().await {
+    info!(
+        "Dry-run exec validated: session_id={:?}, prompt_len={}",
+        request.session_id,
+        request.prompt.len()
+    );
+
+    Ok(json!({
+        "status": "dry_run",
+        "session_id": request.session_id,
+        "model": request.model.as_deref().unwrap_or(&config.model),
+        "provider": request.provider.as_deref().unwrap_or(&config.model_provider_id),
+        "cwd": request.cwd.as_deref().unwrap_or(&config.cwd),
+        "sandbox_policy": sandbox_policy,
+        "approval_policy": approval_policy,
+        "timeout_ms": turn_limits.timeout_ms,
+        "max_lifetime_ms": turn_limits.max_lifetime_ms,
+        "input_items": user_inputs.len(),
+        "prompt_prefix": prompt_prefix,
+        "callback_url": request.callback_url,
+    }))
+}
+
+/// Read and check the optional `Idempotency-Key` header
+fn idempotency_key(headers: &HeaderMap) -> GatewayResult<Option<String>> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+
+    let key = value.to_str().map_err(|_| {
+        GatewayError::InvalidRequest("header 'Idempotency-Key' must be visible ASCII".to_string())
+    })?;
+    if key.is_empty() || key.len() > MAX_KEY_LEN {
+        return Err(GatewayError::InvalidRequest(format!(
+            "header 'Idempotency-Key' must be 1 to {MAX_KEY_LEN} characters"
+        )));
+    }
+
+    Ok(Some(key.to_string()))
+}
+
+/// Run the exec, or start it in the background when `callback_url` is set
+///
+/// Returns the HTTP status and JSON body to send back, with the exec's
+/// timings when it ran in the foreground, or 503 with `Retry-After` when
+/// `CODEX_MAX_INFLIGHT` execs are already running or queued. The exec is
+/// counted against the daily quota only once nothing else rejects it.
+async fn execute_exec(
+    state: AppState,
+    mut request: ExecRequest,
+) -> GatewayResult<(CachedReply, Option<ExecTimings>)> {
+    let inflight = state.enter_inflight()?;
+    if let Some(callback_url) = &request.callback_url {
+        if !state.callbacks.is_configured() {
+            return Err(GatewayError::InvalidRequest(
+                "field 'callback_url' requires CODEX_WEBHOOK_SECRET to be set on the gateway"
+                    .to_string(),
+            ));
+        }
+        state.callbacks.check_url(callback_url).await?;
+    }
+    if let Some(session_id) = &request.session_id {
+        check_session_owner(&state, session_id, request.api_key_id.as_deref())?;
+    }
+    check_persist(&state, request.session_id.as_deref(), request.persist).await?;
+    admit_exec(&state, request.api_key_id.as_deref())?;
+    let Some(callback_url) = request.callback_url.take() else {
+        let response = run_exec(&state, request).await?;
+        let status_code = if matches!(
+            response.status,
+            ExecStatus::Timeout | ExecStatus::LifetimeExceeded
+        ) {
+            StatusCode::REQUEST_TIMEOUT
+        } else {
+            StatusCode::OK
+        };
+        return Ok((
+            (status_code, serde_json::to_value(&response)?),
+            Some(response.timings),
+        ));
+    };
+
+    let session_id = request
+        .session_id
+        .get_or_insert_with(|| Uuid::new_v4().to_string())
+        .clone();
+    info!(
+        "Exec accepted with callback: session_id={}, callback_url={}",
+        session_id, callback_url
+    );
+
+    let accepted = json!({
+        "status": "accepted",
+        "session_id": session_id,
+    });
+
+    tokio::spawn(async move {
+        let _inflight = inflight;
+        let payload = match run_exec(&state, request).await {
+            Ok(response) => serde_json::to_value(&response).unwrap_or_else(|e| {
+                json!({
+                    "status": "error",
+                    "session_id": session_id,
+                    "error": format!("failed to serialize exec response: {e}"),
+                })
+            }),
+            Err(err) => json!({
+                "status": "error",
+                "session_id": session_id,
+                "error": err.to_string(),
+            }),
+        };
+
+        if let Err(e) = state.callbacks.deliver(&callback_url, &payload).await {
+            error!(
+                "Failed to deliver exec callback for session {}: {e}",
+                session_id
+            );
+        }
+    });
+
+    Ok(((StatusCode::ACCEPTED, accepted), None))
+}
+
+/// POST /exec/ndjson - Stream an exec's events as newline-delimited JSON
+///
+/// Takes the same body as `POST /exec` (without `callback_url` or `dry_run`)
+/// and runs the same pipeline, but writes each event as soon as it is
+/// produced, one compact JSON object per line:
+///
+/// ```text
+/// {"event":"task_started","data":{"timeout_ms":60000,"max_lifetime_ms":3600000}}
+/// {"event":"thread.started","data":{"type":"thread.started","thread_id":"..."}}
+/// {"event":"turn.started","data":{"type":"turn.started"}}
+/// {"event":"item.completed","data":{"type":"item.completed","item":{...}}}
+/// {"event":"turn.completed","data":{"type":"turn.completed","usage":{...}}}
+/// {"event":"exec.completed","data":{"conversation_id":"...","status":"completed",...}}
+/// ```
+///
+/// The first line, `task_started`, gives the `timeout_ms` and
+/// `max_lifetime_ms` the turn actually runs under once clamped to the
+/// server's maximums (`timeout_ms` is null when the turn has no inactivity
+/// bound), so clients can set their own deadlines to match. The last line
+/// is `exec.completed` with the `/exec` response minus its `events`, or
+/// `exec.error` with an `error` message if the turn could not run. Since
+/// headers go out before the turn runs, a completed exec's `Server-Timing`
+/// phases come as a `timings` line just before `exec.completed`:
+///
+/// ```text
+/// {"event":"timings","data":{"queue_ms":0.0,"session_ms":4.1,"exec_ms":5210.7,"teardown_ms":1.3,"total_ms":5216.1}}
+/// ```
+/// Validation errors are returned as a regular JSON error before the
+/// stream starts. If the client disconnects, the turn is interrupted as for
+/// `/exec`.
+pub async fn handle_exec_ndjson(
+    State(state): State<AppState>,
+    key_id: Option<Extension<ApiKeyId>>,
+    scope: Option<Extension<ApiKeyScope>>,
+    Json(mut request): Json<ExecRequest>,
+) -> GatewayResult<Response> {
+    request.api_key_id = key_id.map(|Extension(ApiKeyId(id))| id);
+    request
+        .resolve_prompt_ref(&state.prompt_store, &state.config().exec)
+        .await?;
+    request.validate(&state.config().exec)?;
+    state.prompt_allowlist.check(&request.prompt)?;
+    if let Some(Extension(scope)) = &scope {
+        check_sandbox_scope(
+            &effective_sandbox_policy(&state, request.sandbox_mode.as_deref())?,
+            scope,
+        )?;
+    }
+    if request.callback_url.is_some() || request.dry_run {
+        return Err(GatewayError::InvalidRequest(
+            "fields 'callback_url' and 'dry_run' are not supported on /exec/ndjson".to_string(),
+        ));
+    }
+    if let Some(session_id) = &request.session_id {
+        check_session_owner(&state, session_id, request.api_key_id.as_deref())?;
+    }
+    check_persist(&state, request.session_id.as_deref(), request.persist).await?;
+    let inflight = state.enter_inflight()?;
+    admit_exec(&state, request.api_key_id.as_deref())?;
+
+    let started = ndjson_line(
+        "task_started",
+        serde_json::to_value(request.turn_limits(&state.config().exec))?,
+    );
+    let (event_tx, event_rx) = mpsc::unbounded_channel();
+    let (result_tx, result_rx) = oneshot::channel();
+    let client = event_tx.clone();
+    request.event_sink = Some(event_tx);
+    tokio::spawn(async move {
+        let _inflight = inflight;
+        tokio::select! {
+            result = run_exec(&state, request) => {
+                let _ = result_tx.send(result);
+            }
+            // Dropping the exec lets its disconnect guard interrupt the turn
+            () = client.closed() => {}
+        }
+    });
+
+    Ok((
+        [(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)],
+        Body::from_stream(
+            futures::stream::once(async move { Ok(started) })
+                .chain(ndjson_stream(event_rx, result_rx)),
+        ),
+    )
+        .into_response())
+}
+
+/// NDJSON lines for each event in `events`, then one for the exec's `result`
+fn ndjson_stream(
+    events: mpsc::UnboundedReceiver<ThreadEvent>,
+    result: oneshot::Receiver<GatewayResult<ExecResponse>>,
+) -> impl Stream<Item = Result<String, Infallible>> {
+    let events = futures::stream::unfold(events, |mut events| async move {
+        let event = events.recv().await?;
+        let data = serde_json::to_value(&event).unwrap_or(Value::Null);
+        let name = data["type"].as_str().unwrap_or("event").to_string();
+        Some((ndjson_line(&name, data), events))
+    });
+    let last = futures::stream::once(async move {
+        match result.await {
+            Ok(Ok(response)) => {
+                let mut data = serde_json::to_value(&response).unwrap_or(Value::Null);
+                if let Some(data) = data.as_object_mut() {
+                    data.remove("events");
+                }
+                let mut lines = ndjson_line("timings", response.timings.to_json());
+                lines.push_str(&ndjson_line("exec.completed", data));
+                lines
+            }
+            Ok(Err(err)) => ndjson_line("exec.error", json!({ "error": err.to_string() })),
+            Err(_) => ndjson_line(
+                "exec.error",
+                json!({ "error": "exec ended without a result" }),
+            ),
+        }
+    });
+    events.chain(last).map(Ok)
+}
+
+/// One NDJSON line: `{"event": <event>, "data": <data>}` and a newline
+fn ndjson_line(event: &str, data: Value) -> String {
+    let mut line = json!({ "event": event, "data": data }).to_string();
+    line.push('\n');
+    line
+}
+
+/// Fail with 503 while the circuit breaker is open
+///
+/// Once the cooldown has passed, the call that succeeds is the half-open
+/// probe, so take it exactly once per exec, after every other check, right
+/// before [`run_exec`] or a WebSocket turn.
+pub fn acquire_breaker(state: &AppState) -> GatewayResult<()> {
+    state.circuit_breaker.try_acquire().map_err(|retry_in| {
+        GatewayError::ServiceUnavailable(format!(
+            "agent backend is failing; circuit breaker open, retry in {}s",
+            retry_in.as_secs().max(1)
+        ))
+    })
+}
+
+/// Count an exec against `key_id`'s daily quota, then pass the circuit breaker
+///
+/// The last checks before an exec runs. A quota rejection never takes the
+/// half-open probe, and a breaker rejection gives the quota back.
+pub fn admit_exec(state: &AppState, key_id: Option<&str>) -> GatewayResult<()> {
+    if let Some(key_id) = key_id {
+        state.daily_quota.try_consume(key_id, 1)?;
+    }
+    acquire_breaker(state).inspect_err(|_| {
+        if let Some(key_id) = key_id {
+            state.daily_quota.refund(key_id, 1);
+        }
+    })
+}
+
+/// Run one exec turn to completion and build its response
+///
+/// Runs inside an `exec` span carrying the session, conversation, final
+/// status and execution time, exported over OTLP when enabled.
+///
+/// The caller must have passed [`acquire_breaker`]. The outcome is reported
+/// to the circuit breaker: turns that end in an error or failure, and
+/// internal errors before the turn runs, count as backend failures. Any
+/// other error gives back the half-open probe, if this exec held it.
+#[tracing::instrument(
+    name = "exec",
+    skip_all,
+    fields(
+        session_id = request.session_id.as_deref(),
+        conversation_id = tracing::field::Empty,
+        status = tracing::field::Empty,
+        execution_time_ms = tracing::field::Empty,
+    )
+)]
+async fn run_exec(state: &AppState, request: ExecRequest) -> GatewayResult<ExecResponse> {
+    // The turn task reports the outcome of a turn that ran, even when the
+    // client has gone and this future was dropped
+    let result = run_turn(state, request).await;
+    match &result {
+        Ok(_) => {}
+        Err(GatewayError::Internal(_) | GatewayError::Generic(_)) => {
+            state.circuit_breaker.record_failure();
+        }
+        Err(_) => state.circuit_breaker.release_probe(),
+    }
+    result
+}
+
+/// Report how a turn ended to the circuit breaker: errors and failed turns
+/// count against the backend, completed turns close the breaker, and any
+/// other end gives back the half-open probe
+pub fn record_breaker_outcome(state: &AppState, status: ExecStatus) {
+    match status {
+        ExecStatus::Completed => state.circuit_breaker.record_success(),
+        ExecStatus::Error | ExecStatus::Failed => state.circuit_breaker.record_failure(),
+        _ => state.circuit_breaker.release_probe(),
+    }
+}
+
+/// Prompt of the smoke exec run by `/healthz/deep`
+const SMOKE_PROMPT: &str = "Reply with the single word OK. Do not run any commands.";
+
+/// Run a trivial read-only turn through the whole pipeline, for `/healthz/deep`
+///
+/// Skips the circuit breaker, quotas and callbacks, keeps no rollout, stays
+/// out of metrics, the audit log and `/sessions/active`, and gives up after
+/// `timeout` including any wait for an execution slot.
+pub async fn run_smoke_exec(state: &AppState, timeout: Duration) -> Result<(), String> {
+    let timeout_ms = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+    let mut request = ExecRequest {
+        prompt: SMOKE_PROMPT.to_string(),
+        sandbox_mode: Some("read-only".to_string()),
+        timeout_ms: Some(timeout_ms),
+        max_lifetime_ms: Some(timeout_ms),
+        persist: Some(false),
+        smoke: true,
+        ..Default::default()
+    };
+    request
+        .validate(&state.config().exec)
+        .map_err(|e| e.to_string())?;
+
+    let response = tokio::time::timeout(timeout, run_turn(state, request))
+        .await
+        .map_err(|_| format!("smoke exec did not finish within {timeout_ms}ms"))?
+        .map_err(|e| e.to_string())?;
+    match response.status {
+        ExecStatus::Completed => Ok(()),
+        status => Err(match response.error {
+            Some(error) => format!("smoke exec ended with status {status}: {error}"),
+            None => format!("smoke exec ended with status {status}"),
+        }),
+    }
+}
+
+/// Run one exec turn on the session's conversation
+async fn run_turn(state: &AppState, mut request: ExecRequest) -> GatewayResult<ExecResponse> {
+    info!(
+        "Exec request received: prompt_len={}, session_id={:?}",
+        request.prompt.len(),
+        request.session_id
+    );
+
+    // Unknown providers are a client error; catch them before taking a slot
+    if let Some(provider) = &request.provider {
+        state.codex_service.model_provider(provider)?;
+    }
+
+    // 0. Wait for an execution slot; held until the turn has been collected
+    let queued_at = Instant::now();
+    let permit = match state.try_acquire_exec_permit() {
+        Some(permit) => permit,
+        None => {
+            info!(
+                "Exec request queued: all {} execution slots busy",
+                state.config().exec.max_concurrency
+            );
+            state.acquire_exec_permit().await?
+        }
+    };
+    let started_at = Instant::now();
+    let mut timings = ExecTimings {
+        queue: started_at.duration_since(queued_at),
+        ..ExecTimings::default()
+    };
+
+    // 1. Get or create conversation (with the requested provider, if any)
+    let (conversation_id, created) = state
+        .codex_service
+        .get_or_create_conversation_with_provider(
+            request.session_id.as_deref(),
+            request.provider.as_deref(),
+        )
+        .await?;
+
+    debug!("Using conversation_id: {}", conversation_id);
+    let smoke = request.smoke;
+    let active = (!smoke).then(|| {
+        state.active_execs.register(
+            request.session_id.as_deref(),
+            conversation_id,
+            &request.prompt,
+            request.api_key_id.as_deref(),
+        )
+    });
+
+    // 2. Get conversation from ConversationManager
+    let conversation = {
+        let manager = state.codex_service.conversation_manager().lock().await;
+        manager
+            .get_conversation(conversation_id)
+            .await
+            .map_err(|e| GatewayError::Internal(format!("Failed to get conversation: {e}")))?
+    };
+    timings.session = started_at.elapsed();
+    let exec_started_at = Instant::now();
+
+    let persist = request.persist(&state.config().exec);
+    let prompt_sent = request.prompt.clone();
+
+    // 3. Prepare UserInputs from request, with the configured prompt prefix
+    let prompt_prefix = prompt_prefix(&state.config().exec, request.prompt_prefix.as_deref());
+    request.prompt = apply_prompt_prefix(prompt_prefix.as_deref(), &request.prompt);
+    let user_inputs = prepare_user_inputs(&request)?;
+    debug!("Prepared {} user inputs", user_inputs.len());
+
+    // 4. Get config for Op::UserTurn params
+    let config = state.codex_service.codex_config();
+    let cwd = request.cwd.unwrap_or_else(|| config.cwd.clone());
+    let workdir = cwd.clone();
+    let model = request.model.unwrap_or_else(|| config.model.clone());
+    let sandbox_policy = effective_sandbox_policy(state, request.sandbox_mode.as_deref())?;
+    let approval_policy = resolve_approval_policy(request.approval_policy.as_deref())?;
+
+    // 5. Create channel for event collection
+    let (tx, mut rx) = mpsc::unbounded_channel::<ThreadEvent>();
+
+    // 6. Spawn background task to process events using REAL EventProcessorWithJsonOutput
+    let conversation_clone = conversation.clone();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let cancelled_flag = Arc::clone(&cancelled);
+    let finished = Arc::new(AtomicBool::new(false));
+    let finished_flag = Arc::clone(&finished);
+    let retries = Arc::new(Mutex::new(ExecRetries::default()));
+    let retries_tally = Arc::clone(&retries);
+    let stream_error: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+    let stream_error_slot = Arc::clone(&stream_error);
+    let max_line_bytes = state.config().exec.max_line_bytes;
+    let redact_output = state.config().exec.redact_output;
+    tokio::spawn(async move {
+        let mut processor = EventProcessorWithJsonOutput::new(None);
+
+        loop {
+            match conversation_clone.next_event().await {
                 Ok(event) => {
                     debug!("Processing event: {:?}", event.msg);
+                    retries_tally
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .record(&event.msg);
 
                     // Use REAL EventProcessorWithJsonOutput to convert Codex events → ThreadEvents
                     let thread_events = processor.collect_thread_events(&event);
-                    for te in thread_events {
+                    for mut te in thread_events {
+                        cap_line_lengths(&mut te, max_line_bytes);
+                        if redact_output {
+                            redaction::redact_event(&mut te);
+                        }
                         if tx.send(te).is_err() {
                             error!("Failed to send event to channel (receiver dropped)");
                             break;
                         }
                     }
 
+                    if let EventMsg::TurnAborted(aborted) = &event.msg
+                        && matches!(aborted.reason, TurnAbortReason::Interrupted)
+                    {
+                        cancelled_flag.store(true, Ordering::SeqCst);
+                    }
+
                     // Check for terminal events
                     if matches!(
                         event.msg,
@@ -234,60 +1584,209 @@ pub async fn handle_exec(
                 }
                 Err(e) => {
                     error!("Error receiving event: {e}");
+                    *stream_error_slot
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(e.to_string());
                     break;
                 }
             }
         }
+        finished_flag.store(true, Ordering::SeqCst);
     });
 
     // 7. Submit Op::UserTurn with all config parameters
-    info!("Submitting user turn with model={}, cwd={:?}", model, cwd);
-    conversation
+    info!(
+        "Submitting user turn with model={}, cwd={:?}, sandbox_policy={}, approval_policy={}",
+        model, cwd, sandbox_policy, approval_policy
+    );
+    let turn_limits = request.turn_limits(&state.config().exec);
+    let audit = AuditRecord::started(
+        request.api_key_id.as_deref(),
+        request.session_id.as_deref(),
+        &conversation_id.to_string(),
+        &prompt_sent,
+        &sandbox_policy.to_string(),
+    )
+    .with_limits(turn_limits.timeout_ms, turn_limits.max_lifetime_ms);
+    // Counted only once nothing short of the agent can fail the exec, so
+    // every start is matched by a finish below
+    if !smoke {
+        state.audit_log.record(&audit);
+        state.metrics.record_started();
+    }
+    let submitted = conversation
         .submit(Op::UserTurn {
             items: user_inputs,
             cwd,
-            approval_policy: config.approval_policy,
-            sandbox_policy: config.sandbox_policy.clone(),
+            approval_policy,
+            sandbox_policy,
             model,
             effort: config.model_reasoning_effort,
             summary: config.model_reasoning_summary,
             final_output_json_schema: request.output_schema,
         })
-        .await
-        .map_err(|e| GatewayError::Internal(format!("Failed to submit user turn: {e}")))?;
-
-    // 8. Collect all events from background task
-    let mut events = Vec::new();
-    while let Some(event) = rx.recv().await {
-        events.push(event);
+        .await;
+    if let Err(e) = submitted {
+        if !smoke {
+            state
+                .metrics
+                .record_finished(ExecOutcome::Failed, started_at.elapsed());
+            state
+                .audit_log
+                .record(&audit.completed(ExecStatus::Error.as_str(), started_at.elapsed()));
+        }
+        return Err(GatewayError::Internal(format!(
+            "Failed to submit user turn: {e}"
+        )));
     }
 
-    // 9. Determine final status
-    let status = determine_status(&events);
-    let error = if status == "error" {
-        events.iter().find_map(|e| match e {
-            ThreadEvent::Error(err) => Some(err.message.clone()),
-            _ => None,
-        })
-    } else {
-        None
-    };
+    // 8. Collect all events from background task, bounded by timeout_ms
+    //    between events and by the turn's lifetime overall, then tear the
+    //    turn down. Both run in a task of their own: if the client
+    //    disconnects, this future is dropped and the guard interrupts the
+    //    turn, while the task still records its outcome, drops the rollout
+    //    and holds the execution slot until the turn has actually ended.
+    let disconnect_guard = DisconnectGuard::new(finished, DISCONNECT_GRACE, {
+        let conversation = conversation.clone();
+        async move {
+            warn!(
+                "Client disconnected, interrupting conversation_id={}",
+                conversation_id
+            );
+            if let Err(e) = conversation.submit(Op::Interrupt).await {
+                warn!("Failed to interrupt turn after client disconnect: {e}");
+            }
+        }
+    });
+    let state = state.clone();
+    let timeout_ms = request.timeout_ms;
+    let event_sink = request.event_sink.take();
+    let session_id = request.session_id.take();
+    let key_id = request.api_key_id.take();
+    let turn = tokio::spawn(
+        async move {
+            let _held = (permit, active);
+            let (events, stopped) = collect_events(
+                &mut rx,
+                timeout_ms.map(Duration::from_millis),
+                Some(Duration::from_millis(turn_limits.max_lifetime_ms)),
+                event_sink,
+            )
+            .await;
+            if let Some(reason) = stopped {
+                warn!(
+                    "Exec stopped ({}), interrupting conversation_id={}",
+                    reason, conversation_id
+                );
+                if let Err(e) = conversation.submit(Op::Interrupt).await {
+                    warn!("Failed to interrupt stopped turn: {e}");
+                }
+            }
+            timings.exec = exec_started_at.elapsed();
+            let teardown_started_at = Instant::now();
 
-    let response = ExecResponse {
-        conversation_id: conversation_id.to_string(),
-        events: events.clone(),
-        status: status.to_string(),
-        error,
-    };
+            // 9. Determine final status (a timeout, the lifetime running
+            //    out or an interrupt from /sessions/{id}/cancel wins)
+            let status = if let Some(reason) = stopped {
+                reason
+            } else if cancelled.load(Ordering::SeqCst) {
+                ExecStatus::Cancelled
+            } else {
+                determine_status(&events)
+            };
+            if !smoke {
+                record_breaker_outcome(&state, status);
+                state
+                    .metrics
+                    .record_finished(status.outcome(), started_at.elapsed());
+                state
+                    .audit_log
+                    .record(&audit.completed(status.as_str(), started_at.elapsed()));
+            }
+            let stream_error = stream_error
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .take();
+            let error = status_error(
+                status,
+                &events,
+                timeout_ms.unwrap_or_default(),
+                turn_limits.max_lifetime_ms,
+                stream_error.as_deref(),
+            );
+            let retries = *retries
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
 
-    info!(
-        "Exec completed: conversation_id={}, status={}, events={}",
-        conversation_id,
-        status,
-        response.events.len()
+            let mut response = ExecResponse {
+                conversation_id: conversation_id.to_string(),
+                created_files: collect_created_files(&events, &workdir),
+                prompt_prefix,
+                events: events.clone(),
+                status,
+                error,
+                retries,
+                timings,
+            };
+
+            telemetry::record_exec_result(
+                &tracing::Span::current(),
+                &response.conversation_id,
+                status.as_str(),
+                started_at.elapsed(),
+            );
+            // An existing session's rollout also holds its earlier turns
+            if !persist && created {
+                match state.codex_service.discard_rollout(conversation_id).await {
+                    Ok(_) => debug!("Discarded rollout for conversation_id={}", conversation_id),
+                    Err(e) => error!(
+                        "Failed to discard rollout for conversation_id={}: {e}",
+                        conversation_id
+                    ),
+                }
+            } else if redact_output
+                && let Err(e) = state.codex_service.redact_rollout(conversation_id).await
+            {
+                error!(
+                    "Failed to redact rollout for conversation_id={}: {e}",
+                    conversation_id
+                );
+            }
+            if persist && let Some(session_id) = session_id {
+                state.exec_results.record(ExecResult {
+                    session_id,
+                    conversation_id: response.conversation_id.clone(),
+                    status: status.to_string(),
+                    execution_time_ms: u64::try_from(started_at.elapsed().as_millis())
+                        .unwrap_or(u64::MAX),
+                    finished_at: chrono::Utc::now().to_rfc3339(),
+                    final_message: final_message(&events),
+                    usage: turn_usage(&events),
+                    error: response.error.clone(),
+                    retries,
+                    created_files: response.created_files.clone(),
+                    workdir: Some(workdir),
+                    key_id,
+                });
+            }
+            info!(
+                "Exec completed: conversation_id={}, status={}, events={}",
+                conversation_id,
+                status,
+                response.events.len()
+            );
+            response.timings.teardown = teardown_started_at.elapsed();
+
+            response
+        }
+        .instrument(tracing::Span::current()),
     );
+    let response = turn
+        .await
+        .map_err(|e| GatewayError::Internal(format!("Exec turn task failed: {e}")))?;
+    disconnect_guard.disarm();
 
-    Ok((StatusCode::OK, Json(response)))
+    Ok(response)
 }
 
 /// POST /exec/resume - Resume a previous conversation
@@ -321,6 +1820,7 @@ pub async fn handle_exec_resume(
         "Resume request received: conversation_id={}, session_id={}",
         request.conversation_id, request.session_id
     );
+    validate_session_id(&request.session_id)?;
 
     // Resume the conversation
     let conversation_id = state
@@ -345,6 +1845,239 @@ pub async fn handle_exec_resume(
     Ok((StatusCode::OK, Json(response)))
 }
 
+/// Most prompts accepted in one batch
+pub const MAX_BATCH_PROMPTS: usize = 16;
+
+/// Request structure for the batch exec endpoint
+///
+/// Every prompt runs as its own turn with the shared parameters below.
+#[derive(Debug, Default, Deserialize)]
+pub struct BatchExecRequest {
+    /// Prompts to run, in order (1 to `MAX_BATCH_PROMPTS`)
+    pub prompts: Vec<String>,
+
+    /// Session shared by every prompt (generated when absent)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+
+    /// Working directory for every turn, as for `/exec`
+    #[serde(alias = "working_dir", skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+
+    /// Model override, checked against `CODEX_ALLOWED_MODELS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Model provider override, checked against `CODEX_ALLOWED_PROVIDERS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+
+    /// Sandbox mode override, as for `/exec`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox_mode: Option<String>,
+
+    /// Approval policy, as for `/exec`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<String>,
+
+    /// Timeout for each prompt's turn, in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+
+    /// Lifetime of each prompt's turn, in milliseconds, as for `/exec`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_lifetime_ms: Option<u64>,
+
+    /// Extra instructions placed before every prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_prefix: Option<String>,
+}
+
+impl BatchExecRequest {
+    /// Validate the batch and build one exec request per prompt
+    ///
+    /// Errors about a single prompt name its index (`prompts[2]: ...`).
+    pub fn into_exec_requests(self, limits: &ExecConfig) -> GatewayResult<Vec<ExecRequest>> {
+        if self.prompts.is_empty() || self.prompts.len() > MAX_BATCH_PROMPTS {
+            return Err(GatewayError::InvalidRequest(format!(
+                "field 'prompts' must contain 1 to {MAX_BATCH_PROMPTS} prompts, got {}",
+                self.prompts.len()
+            )));
+        }
+
+        let session_id = self
+            .session_id
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        self.prompts
+            .into_iter()
+            .enumerate()
+            .map(|(index, prompt)| {
+                let mut request = ExecRequest {
+                    prompt,
+                    session_id: Some(session_id.clone()),
+                    cwd: self.cwd.clone(),
+                    model: self.model.clone(),
+                    provider: self.provider.clone(),
+                    sandbox_mode: self.sandbox_mode.clone(),
+                    approval_policy: self.approval_policy.clone(),
+                    timeout_ms: self.timeout_ms,
+                    max_lifetime_ms: self.max_lifetime_ms,
+                    prompt_prefix: self.prompt_prefix.clone(),
+                    ..Default::default()
+                };
+                request.validate(limits).map_err(|e| match e {
+                    GatewayError::InvalidRequest(msg) => {
+                        GatewayError::InvalidRequest(format!("prompts[{index}]: {msg}"))
+                    }
+                    other => other,
+                })?;
+                Ok(request)
+            })
+            .collect()
+    }
+}
+
+/// POST /exec/batch - Run several prompts in order under one session
+///
+/// Each prompt is a separate turn on the same conversation, so later prompts
+/// see the earlier ones. Turns run one at a time and each takes an execution
+/// slot, so a batch never exceeds `CODEX_MAX_CONCURRENCY`. Each prompt counts
+/// against the daily quota as it starts. A cancelled turn
+/// (`/sessions/{id}/cancel`), or a prompt refused by the circuit breaker or
+/// the daily quota, stops the batch; the remaining prompts are neither run
+/// nor charged.
+///
+/// ## Example Request
+///
+/// ```json
+/// {
+///   "prompts": ["create hello.py", "add a test for it"],
+///   "session_id": "my-batch"
+/// }
+/// ```
+///
+/// ## Example Response
+///
+/// ```json
+/// {
+///   "session_id": "my-batch",
+///   "status": "completed",
+///   "completed": 2,
+///   "results": [
+///     {"prompt_index": 0, "conversation_id": "...", "status": "completed", "events": [...]},
+///     {"prompt_index": 1, "conversation_id": "...", "status": "completed", "events": [...]}
+///   ]
+/// }
+/// ```
+///
+/// `status` is "completed" only when every prompt completed. A prompt that
+/// could not run at all is reported as `{"prompt_index": n, "status": "error", "error": ...}`.
+pub async fn handle_exec_batch(
+    State(state): State<AppState>,
+    key_id: Option<Extension<ApiKeyId>>,
+    scope: Option<Extension<ApiKeyScope>>,
+    Json(request): Json<BatchExecRequest>,
+) -> GatewayResult<(StatusCode, Json<Value>)> {
+    let mut requests = request.into_exec_requests(&state.config().exec)?;
+    for request in &requests {
+        state.prompt_allowlist.check(&request.prompt)?;
+        if let Some(Extension(scope)) = &scope {
+            check_sandbox_scope(
+                &effective_sandbox_policy(&state, request.sandbox_mode.as_deref())?,
+                scope,
+            )?;
+        }
+    }
+    let key_id = key_id.map(|Extension(ApiKeyId(id))| id);
+    if let Some(session_id) = requests
+        .first()
+        .and_then(|request| request.session_id.as_ref())
+    {
+        check_session_owner(&state, session_id, key_id.as_deref())?;
+    }
+    let _inflight = state.enter_inflight()?;
+    admit_exec(&state, key_id.as_deref())?;
+    if let Some(id) = &key_id {
+        for request in &mut requests {
+            request.api_key_id = Some(id.clone());
+        }
+    }
+    let session_id = requests
+        .first()
+        .and_then(|request| request.session_id.clone())
+        .unwrap_or_default();
+    info!(
+        "Batch exec received: session_id={}, prompts={}",
+        session_id,
+        requests.len()
+    );
+
+    let mut results = Vec::with_capacity(requests.len());
+    let mut completed = 0;
+    for (index, request) in requests.into_iter().enumerate() {
+        // The first prompt was admitted before the loop
+        let admitted = if index == 0 {
+            Ok(())
+        } else {
+            admit_exec(&state, key_id.as_deref())
+        };
+        let refused = admitted.is_err();
+        let outcome = match admitted {
+            Ok(()) => run_exec(&state, request).await,
+            Err(err) => Err(err),
+        };
+        let (status, mut result) = match outcome {
+            Ok(response) => (response.status, serde_json::to_value(&response)?),
+            Err(err) => (
+                ExecStatus::Error,
+                json!({"status": ExecStatus::Error, "error": err.to_string()}),
+            ),
+        };
+        if let Value::Object(fields) = &mut result {
+            fields.insert("prompt_index".to_string(), json!(index));
+        }
+        results.push(result);
+
+        if status == ExecStatus::Completed {
+            completed += 1;
+        } else if status == ExecStatus::Cancelled {
+            info!(
+                "Batch exec cancelled at prompt {}: session_id={}",
+                index, session_id
+            );
+            break;
+        } else if refused {
+            info!(
+                "Batch exec stopped at prompt {}: session_id={}",
+                index, session_id
+            );
+            break;
+        }
+    }
+
+    let status = if completed == results.len() {
+        ExecStatus::Completed
+    } else {
+        ExecStatus::Failed
+    };
+    info!(
+        "Batch exec finished: session_id={}, completed={}/{}",
+        session_id,
+        completed,
+        results.len()
+    );
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "session_id": session_id,
+            "status": status,
+            "completed": completed,
+            "results": results,
+        })),
+    ))
+}
+
 /// Prepare UserInputs from ExecRequest
 ///
 /// Converts prompt and images into UserInput enum variants:
@@ -382,34 +2115,271 @@ fn prepare_user_inputs(request: &ExecRequest) -> GatewayResult<Vec<UserInput>> {
     Ok(inputs)
 }
 
-/// Determine final status from events
+/// Truncate lines of command output longer than `max_line_bytes`
 ///
-/// Analyzes the event stream to determine if execution was:
-/// - "completed": Normal completion with TurnCompleted event
-/// - "failed": Turn failed with TurnFailed event
-/// - "error": Error event occurred
-fn determine_status(events: &[ThreadEvent]) -> &'static str {
-    if events.iter().any(|e| matches!(e, ThreadEvent::Error(_))) {
-        "error"
-    } else if events
-        .iter()
-        .any(|e| matches!(e, ThreadEvent::TurnFailed(_)))
-    {
-        "failed"
-    } else if events
-        .iter()
-        .any(|e| matches!(e, ThreadEvent::TurnCompleted(_)))
-    {
-        "completed"
-    } else {
-        "unknown"
+/// A single pathological line (a base64 blob, minified JSON) can be large
+/// enough to break a client or a WebSocket frame. Each such line keeps its
+/// first `max_line_bytes` bytes followed by a `…[truncated N bytes]` marker.
+/// Returns how many lines were truncated.
+pub fn cap_line_lengths(event: &mut ThreadEvent, max_line_bytes: usize) -> usize {
+    let item = match event {
+        ThreadEvent::ItemStarted(ItemStartedEvent { item })
+        | ThreadEvent::ItemUpdated(ItemUpdatedEvent { item })
+        | ThreadEvent::ItemCompleted(ItemCompletedEvent { item }) => item,
+        _ => return 0,
+    };
+    let ThreadItemDetails::CommandExecution(command) = &mut item.details else {
+        return 0;
+    };
+
+    let (output, truncated) = truncate_lines(&command.aggregated_output, max_line_bytes);
+    if truncated > 0 {
+        warn!(
+            "Truncated {} lines of `{}` output longer than {} bytes",
+            truncated, command.command, max_line_bytes
+        );
+        command.aggregated_output = output.into_owned();
     }
+    truncated
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// `text` with every line cut to `max_line_bytes`, and the number of lines cut
+fn truncate_lines(text: &str, max_line_bytes: usize) -> (Cow<'_, str>, usize) {
+    if !text.split('\n').any(|line| line.len() > max_line_bytes) {
+        return (Cow::Borrowed(text), 0);
+    }
+
+    let mut truncated = 0;
+    let lines: Vec<String> = text
+        .split('\n')
+        .map(|line| {
+            if line.len() <= max_line_bytes {
+                return line.to_string();
+            }
+            truncated += 1;
+            let mut end = max_line_bytes;
+            while !line.is_char_boundary(end) {
+                end -= 1;
+            }
+            format!("{}…[truncated {} bytes]", &line[..end], line.len() - end)
+        })
+        .collect();
+    (Cow::Owned(lines.join("\n")), truncated)
+}
+
+/// Collect paths of files added or updated by successfully applied patches
+///
+/// Paths touched by several patches are reported once, in the order they
+/// were first seen. Deleted files are not included. Paths inside `cwd` are
+/// made relative to it, which is the name they can be downloaded under from
+/// `GET /sessions/{id}/artifacts/{name}`.
+fn collect_created_files(events: &[ThreadEvent], cwd: &Path) -> Vec<String> {
+    let mut files: Vec<String> = Vec::new();
+
+    for event in events {
+        let ThreadEvent::ItemCompleted(completed) = event else {
+            continue;
+        };
+        let ThreadItemDetails::FileChange(file_change) = &completed.item.details else {
+            continue;
+        };
+        if file_change.status != PatchApplyStatus::Completed {
+            continue;
+        }
+
+        for change in &file_change.changes {
+            if !matches!(change.kind, PatchChangeKind::Add | PatchChangeKind::Update) {
+                continue;
+            }
+            let name = Path::new(&change.path)
+                .strip_prefix(cwd)
+                .ok()
+                .and_then(Path::to_str)
+                .unwrap_or(&change.path);
+            if !files.iter().any(|file| file == name) {
+                files.push(name.to_string());
+            }
+        }
+    }
+
+    files
+}
+
+/// Text of the last message the agent sent
+fn final_message(events: &[ThreadEvent]) -> Option<String> {
+    events.iter().rev().find_map(|event| match event {
+        ThreadEvent::ItemCompleted(completed) => match &completed.item.details {
+            ThreadItemDetails::AgentMessage(message) => Some(message.text.clone()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// Token usage reported when the turn completed
+fn turn_usage(events: &[ThreadEvent]) -> Option<Usage> {
+    events.iter().find_map(|event| match event {
+        ThreadEvent::TurnCompleted(completed) => Some(completed.usage.clone()),
+        _ => None,
+    })
+}
+
+/// Interrupts a turn whose caller went away before it finished
+///
+/// Axum drops the handler future when the HTTP client disconnects, which
+/// would otherwise leave the agent turn running until its own timeout. An
+/// armed guard that is dropped waits `grace` and then runs `on_disconnect`,
+/// unless `finished` was set in the meantime.
+struct DisconnectGuard {
+    finished: Arc<AtomicBool>,
+    grace: Duration,
+    on_disconnect: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+}
+
+impl DisconnectGuard {
+    fn new(
+        finished: Arc<AtomicBool>,
+        grace: Duration,
+        on_disconnect: impl Future<Output = ()> + Send + 'static,
+    ) -> Self {
+        Self {
+            finished,
+            grace,
+            on_disconnect: Some(Box::pin(on_disconnect)),
+        }
+    }
+
+    /// The turn was collected; dropping the guard does nothing
+    fn disarm(mut self) {
+        self.on_disconnect = None;
+    }
+}
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        let Some(on_disconnect) = self.on_disconnect.take() else {
+            return;
+        };
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+
+        let finished = Arc::clone(&self.finished);
+        let grace = self.grace;
+        handle.spawn(async move {
+            tokio::time::sleep(grace).await;
+            if !finished.load(Ordering::SeqCst) {
+                on_disconnect.await;
+            }
+        });
+    }
+}
+
+/// Drain `rx` until the event loop closes it, `idle_timeout` passes without
+/// an event, or `lifetime` elapses
+///
+/// Returns the events received so far and, if collection was cut short, why:
+/// [`ExecStatus::Timeout`] or [`ExecStatus::LifetimeExceeded`]. A stopped
+/// turn still reports everything it produced before then. Each event is also
+/// forwarded to `sink`, when set, as it arrives.
+async fn collect_events(
+    rx: &mut mpsc::UnboundedReceiver<ThreadEvent>,
+    idle_timeout: Option<Duration>,
+    lifetime: Option<Duration>,
+    sink: Option<mpsc::UnboundedSender<ThreadEvent>>,
+) -> (Vec<ThreadEvent>, Option<ExecStatus>) {
+    let mut events = Vec::new();
+    let collect = async {
+        loop {
+            let next = match idle_timeout {
+                Some(idle_timeout) => match tokio::time::timeout(idle_timeout, rx.recv()).await {
+                    Ok(next) => next,
+                    Err(_) => return Some(ExecStatus::Timeout),
+                },
+                None => rx.recv().await,
+            };
+            let Some(event) = next else {
+                return None;
+            };
+            if let Some(sink) = &sink {
+                // A closed sink means the streaming client left; keep collecting
+                let _ = sink.send(event.clone());
+            }
+            events.push(event);
+        }
+    };
+
+    let stopped = match lifetime {
+        Some(lifetime) => tokio::time::timeout(lifetime, collect)
+            .await
+            .unwrap_or(Some(ExecStatus::LifetimeExceeded)),
+        None => collect.await,
+    };
+
+    (events, stopped)
+}
+
+/// Determine final status from events
+///
+/// Analyzes the event stream to determine if execution was:
+/// - `Completed`: Normal completion with TurnCompleted event
+/// - `Failed`: Turn failed with TurnFailed event
+/// - `Error`: Error event occurred
+fn determine_status(events: &[ThreadEvent]) -> ExecStatus {
+    if events.iter().any(|e| matches!(e, ThreadEvent::Error(_))) {
+        ExecStatus::Error
+    } else if events
+        .iter()
+        .any(|e| matches!(e, ThreadEvent::TurnFailed(_)))
+    {
+        ExecStatus::Failed
+    } else if events
+        .iter()
+        .any(|e| matches!(e, ThreadEvent::TurnCompleted(_)))
+    {
+        ExecStatus::Completed
+    } else {
+        ExecStatus::Unknown
+    }
+}
+
+/// Message explaining a status other than success or cancellation
+///
+/// `Unknown` means the agent's event stream ended before the turn finished,
+/// e.g. because the session shut down; `stream_error` is the reason the
+/// stream gave, if any.
+pub fn status_error(
+    status: ExecStatus,
+    events: &[ThreadEvent],
+    timeout_ms: u64,
+    max_lifetime_ms: u64,
+    stream_error: Option<&str>,
+) -> Option<String> {
+    match status {
+        ExecStatus::Error => events.iter().find_map(|e| match e {
+            ThreadEvent::Error(err) => Some(err.message.clone()),
+            _ => None,
+        }),
+        ExecStatus::Timeout => Some(format!(
+            "exec produced no event within timeout_ms={timeout_ms}"
+        )),
+        ExecStatus::LifetimeExceeded => Some(format!(
+            "exec did not finish within max_lifetime_ms={max_lifetime_ms}"
+        )),
+        ExecStatus::Unknown => Some(match stream_error {
+            Some(reason) => format!("agent event stream ended before the turn finished: {reason}"),
+            None => "agent event stream ended before the turn finished".to_string(),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
     use crate::config::GatewayConfig;
+    use crate::config::parse_default_sandbox_mode;
+    use crate::services::PromptAllowlist;
 
     #[tokio::test]
     async fn test_exec_basic_prompt() -> Result<(), Box<dyn std::error::Error>> {
@@ -423,9 +2393,18 @@ mod tests {
             cwd: None,
             model: None,
             sandbox_mode: None,
+            ..Default::default()
         };
 
-        let result = handle_exec(State(state), Json(request)).await;
+        let result = handle_exec(
+            State(state),
+            None,
+            None,
+            Query(ExecQuery::default()),
+            HeaderMap::new(),
+            Json(request),
+        )
+        .await;
 
         // Should succeed (or fail gracefully with proper error)
         assert!(result.is_ok() || matches!(result, Err(GatewayError::Internal(_))));
@@ -433,6 +2412,238 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_batch_shares_one_session() -> Result<(), GatewayError> {
+        let batch = BatchExecRequest {
+            prompts: vec![
+                "first".to_string(),
+                "second".to_string(),
+                "third".to_string(),
+            ],
+            timeout_ms: Some(5_000),
+            ..Default::default()
+        };
+
+        let requests = batch.into_exec_requests(&ExecConfig::default())?;
+
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2].prompt, "third");
+        assert!(requests[0].session_id.is_some());
+        assert!(
+            requests
+                .iter()
+                .all(|r| r.session_id == requests[0].session_id && r.timeout_ms == Some(5_000))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_rejects_bad_prompt_counts_and_names_index() {
+        let limits = ExecConfig::default();
+
+        let empty = BatchExecRequest::default().into_exec_requests(&limits);
+        assert!(matches!(empty, Err(GatewayError::InvalidRequest(_))));
+
+        let too_many = BatchExecRequest {
+            prompts: vec!["hi".to_string(); MAX_BATCH_PROMPTS + 1],
+            ..Default::default()
+        }
+        .into_exec_requests(&limits);
+        assert!(matches!(too_many, Err(GatewayError::InvalidRequest(_))));
+
+        let blank = BatchExecRequest {
+            prompts: vec!["ok".to_string(), " ".to_string()],
+            ..Default::default()
+        }
+        .into_exec_requests(&limits);
+        assert!(
+            matches!(blank, Err(GatewayError::InvalidRequest(ref msg)) if msg.starts_with("prompts[1]"))
+        );
+    }
+
+    #[test]
+    fn test_event_filter_keeps_only_requested_types() -> Result<(), Box<dyn std::error::Error>> {
+        let query = ExecQuery {
+            events: Some("turn.completed".to_string()),
+        };
+        let allowed = query.event_filter()?.ok_or("expected a filter")?;
+
+        let mut body = json!({
+            "status": "completed",
+            "events": [
+                {"type": "thread.started", "thread_id": "t"},
+                {"type": "turn.started"},
+                {"type": "item.completed", "item": {"id": "item_0", "type": "agent_message", "text": "hi"}},
+                {"type": "turn.completed", "usage": {"input_tokens": 1, "cached_input_tokens": 0, "output_tokens": 1}},
+            ],
+        });
+        filter_events(&mut body, &allowed);
+
+        let types: Vec<&str> = body["events"]
+            .as_array()
+            .ok_or("events missing")?
+            .iter()
+            .filter_map(|e| e["type"].as_str())
+            .collect();
+        assert_eq!(types, vec!["turn.completed"]);
+        assert_eq!(body["status"], "completed");
+
+        assert!(ExecQuery::default().event_filter()?.is_none());
+        let unknown = ExecQuery {
+            events: Some("turn.completed,stdout_line".to_string()),
+        };
+        assert!(matches!(
+            unknown.event_filter(),
+            Err(GatewayError::InvalidRequest(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_prompt_substitutes_variables() -> Result<(), GatewayError> {
+        let variables = HashMap::from([
+            ("lang".to_string(), "Rust".to_string()),
+            ("file".to_string(), "main.rs".to_string()),
+        ]);
+
+        let rendered = render_prompt(
+            "Port {{file}} to {{ lang }}; keep {{unknown}} as is",
+            &variables,
+            false,
+        )?;
+
+        assert_eq!(rendered, "Port main.rs to Rust; keep {{unknown}} as is");
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_prompt_strict_rejects_missing_variable() {
+        let mut request = ExecRequest {
+            prompt: "Summarize {{ticket}}".to_string(),
+            variables: Some(HashMap::new()),
+            strict_templating: true,
+            ..Default::default()
+        };
+
+        let err = request.validate(&ExecConfig::default()).unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidRequest(ref msg) if msg.contains("ticket")));
+        assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_render_prompt_preserves_literal_braces() -> Result<(), GatewayError> {
+        let variables = HashMap::from([("name".to_string(), "x".to_string())]);
+        let prompt = r#"fn main() { println!("{}", {{name}}); } {{ not a var }} {{"#;
+
+        let rendered = render_prompt(prompt, &variables, true)?;
+
+        assert_eq!(
+            rendered,
+            r#"fn main() { println!("{}", x); } {{ not a var }} {{"#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prompt_ref_becomes_the_prompt() -> Result<(), Box<dyn std::error::Error>> {
+        use wiremock::Mock;
+        use wiremock::MockServer;
+        use wiremock::ResponseTemplate;
+        use wiremock::matchers::path;
+
+        let server = MockServer::start().await;
+        Mock::given(path("/storage/v1/b/prompts-bucket/o/task.md"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("refactor the parser"))
+            .mount(&server)
+            .await;
+        let store = PromptStore::new(server.uri(), false);
+        let limits = ExecConfig::default();
+
+        let mut request = ExecRequest {
+            prompt_ref: Some("gs://prompts-bucket/task.md".to_string()),
+            ..Default::default()
+        };
+        request.resolve_prompt_ref(&store, &limits).await?;
+        request.validate(&limits)?;
+
+        let inputs = prepare_user_inputs(&request)?;
+        assert!(
+            matches!(inputs.last(), Some(UserInput::Text { text }) if text == "refactor the parser")
+        );
+
+        let mut both = ExecRequest {
+            prompt: "inline".to_string(),
+            prompt_ref: Some("gs://prompts-bucket/task.md".to_string()),
+            ..Default::default()
+        };
+        let result = both.resolve_prompt_ref(&store, &limits).await;
+        assert!(matches!(result, Err(GatewayError::InvalidRequest(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_persist_false_keeps_earlier_turns_of_existing_session()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+        let (conversation_id, created) = state
+            .codex_service
+            .get_or_create_conversation_with_provider(Some("persisted-session"), None)
+            .await?;
+        assert!(created);
+        let codex_home = state.codex_service.codex_config().codex_home.clone();
+        let rollout =
+            codex_core::find_conversation_path_by_id_str(&codex_home, &conversation_id.to_string())
+                .await?
+                .ok_or("rollout of the persisted session missing")?;
+
+        let request = ExecRequest {
+            prompt: "one more turn".to_string(),
+            session_id: Some("persisted-session".to_string()),
+            persist: Some(false),
+            ..Default::default()
+        };
+        let result = handle_exec(
+            State(state),
+            None,
+            None,
+            Query(ExecQuery::default()),
+            HeaderMap::new(),
+            Json(request),
+        )
+        .await;
+
+        assert!(
+            matches!(result, Err(GatewayError::InvalidRequest(ref msg)) if msg.contains("persist"))
+        );
+        assert!(rollout.exists(), "earlier turns were deleted");
+        Ok(())
+    }
+
+    #[test]
+    fn test_clients_cannot_mark_execs_as_smoke() -> Result<(), serde_json::Error> {
+        let request: ExecRequest = serde_json::from_str(r#"{"prompt": "hi", "smoke": true}"#)?;
+        assert!(!request.smoke);
+        Ok(())
+    }
+
+    #[test]
+    fn test_persist_follows_request_then_default() {
+        let keep = ExecConfig::default();
+        let discard = ExecConfig {
+            persist_default: false,
+            ..Default::default()
+        };
+        let unset = ExecRequest::default();
+        let opted_out = ExecRequest {
+            persist: Some(false),
+            ..Default::default()
+        };
+
+        assert!(unset.persist(&keep));
+        assert!(!unset.persist(&discard));
+        assert!(!opted_out.persist(&keep));
+    }
+
     #[test]
     fn test_prepare_user_inputs_text_only() {
         let request = ExecRequest {
@@ -443,6 +2654,7 @@ mod tests {
             cwd: None,
             model: None,
             sandbox_mode: None,
+            ..Default::default()
         };
 
         let inputs = prepare_user_inputs(&request).unwrap();
@@ -460,6 +2672,7 @@ mod tests {
             cwd: None,
             model: None,
             sandbox_mode: None,
+            ..Default::default()
         };
 
         let inputs = prepare_user_inputs(&request).unwrap();
@@ -468,6 +2681,967 @@ mod tests {
         assert!(matches!(inputs[1], UserInput::Text { .. }));
     }
 
+    #[test]
+    fn test_validate_rejects_empty_prompt() {
+        let mut request = ExecRequest {
+            prompt: "   \n".to_string(),
+            ..Default::default()
+        };
+
+        let err = request.validate(&ExecConfig::default()).unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidRequest(ref msg) if msg.contains("prompt")));
+    }
+
+    #[test]
+    fn test_validate_rejects_oversized_prompt() {
+        let limits = ExecConfig {
+            max_prompt_bytes: 16,
+            ..Default::default()
+        };
+        let mut request = ExecRequest {
+            prompt: "x".repeat(17),
+            ..Default::default()
+        };
+
+        let err = request.validate(&limits).unwrap_err();
+        assert!(matches!(err, GatewayError::PromptTooLarge(ref msg) if msg.contains("prompt")));
+        assert_eq!(err.code(), "prompt_too_large");
+    }
+
+    #[test]
+    fn test_validate_clamps_timeout() {
+        let limits = ExecConfig {
+            max_timeout_ms: 1_000,
+            ..Default::default()
+        };
+        let mut request = ExecRequest {
+            prompt: "hello".to_string(),
+            timeout_ms: Some(60_000),
+            ..Default::default()
+        };
+
+        request.validate(&limits).unwrap();
+        assert_eq!(request.timeout_ms, Some(1_000));
+    }
+
+    #[test]
+    fn test_task_started_reports_clamped_limits() -> Result<(), Box<dyn std::error::Error>> {
+        let limits = ExecConfig {
+            max_timeout_ms: 1_000,
+            max_lifetime_ms: 5_000,
+            ..Default::default()
+        };
+        let mut request = ExecRequest {
+            prompt: "hello".to_string(),
+            timeout_ms: Some(60_000),
+            max_lifetime_ms: Some(86_400_000),
+            ..Default::default()
+        };
+        request.validate(&limits)?;
+
+        let line: Value = serde_json::from_str(&ndjson_line(
+            "task_started",
+            serde_json::to_value(request.turn_limits(&limits))?,
+        ))?;
+        assert_eq!(line["event"], "task_started");
+        assert_eq!(line["data"]["timeout_ms"], 1_000);
+        assert_eq!(line["data"]["max_lifetime_ms"], 5_000);
+
+        // Without a requested lifetime the configured maximum still applies
+        let unbounded = ExecRequest {
+            prompt: "hello".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            unbounded.turn_limits(&limits),
+            TurnLimits {
+                timeout_ms: None,
+                max_lifetime_ms: 5_000
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_clamped_turn_limits_match_validated_request() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let limits = ExecConfig {
+            max_timeout_ms: 1_000,
+            max_lifetime_ms: 5_000,
+            ..Default::default()
+        };
+        let mut request = ExecRequest {
+            prompt: "hello".to_string(),
+            timeout_ms: Some(60_000),
+            max_lifetime_ms: Some(86_400_000),
+            ..Default::default()
+        };
+        request.validate(&limits)?;
+
+        assert_eq!(
+            TurnLimits::clamped(Some(60_000), Some(86_400_000), &limits),
+            request.turn_limits(&limits)
+        );
+        assert_eq!(
+            TurnLimits::clamped(None, None, &limits),
+            TurnLimits {
+                timeout_ms: None,
+                max_lifetime_ms: 5_000
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_output_lines_are_truncated_with_marker() -> Result<(), Box<dyn std::error::Error>>
+    {
+        use codex_exec::exec_events::*;
+
+        let blob = "QUJD".repeat(1000);
+        let mut event = ThreadEvent::ItemCompleted(ItemCompletedEvent {
+            item: ThreadItem {
+                id: "item_0".to_string(),
+                details: ThreadItemDetails::CommandExecution(CommandExecutionItem {
+                    command: "cat blob.b64".to_string(),
+                    aggregated_output: format!("start\n{blob}\nend"),
+                    exit_code: Some(0),
+                    status: CommandExecutionStatus::Completed,
+                }),
+            },
+        });
+
+        assert_eq!(cap_line_lengths(&mut event, 100), 1);
+        let ThreadEvent::ItemCompleted(ItemCompletedEvent { item }) = &event else {
+            return Err("event changed type".into());
+        };
+        let ThreadItemDetails::CommandExecution(command) = &item.details else {
+            return Err("item changed type".into());
+        };
+        let lines: Vec<&str> = command.aggregated_output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "start");
+        assert_eq!(lines[1], format!("{}…[truncated 3900 bytes]", &blob[..100]));
+        assert_eq!(lines[2], "end");
+
+        // Short lines are left alone, and cuts never split a character
+        assert_eq!(cap_line_lengths(&mut event, 1_000_000), 0);
+        assert_eq!(
+            truncate_lines("ééé", 3),
+            ("é…[truncated 4 bytes]".into(), 1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_created_files_dedups_and_skips_deletes() {
+        use codex_exec::exec_events::*;
+
+        let file_change = |id: &str, changes: Vec<(&str, PatchChangeKind)>| {
+            ThreadEvent::ItemCompleted(ItemCompletedEvent {
+                item: ThreadItem {
+                    id: id.to_string(),
+                    details: ThreadItemDetails::FileChange(FileChangeItem {
+                        changes: changes
+                            .into_iter()
+                            .map(|(path, kind)| FileUpdateChange {
+                                path: path.to_string(),
+                                kind,
+                            })
+                            .collect(),
+                        status: PatchApplyStatus::Completed,
+                    }),
+                },
+            })
+        };
+
+        let events = vec![
+            file_change(
+                "item_0",
+                vec![
+                    ("src/main.py", PatchChangeKind::Add),
+                    ("README.md", PatchChangeKind::Update),
+                ],
+            ),
+            file_change(
+                "item_1",
+                vec![
+                    ("src/main.py", PatchChangeKind::Update),
+                    ("old.txt", PatchChangeKind::Delete),
+                ],
+            ),
+        ];
+
+        assert_eq!(
+            collect_created_files(&events, Path::new("/work")),
+            vec!["src/main.py".to_string(), "README.md".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_created_files_are_named_relative_to_cwd() {
+        use codex_exec::exec_events::*;
+
+        let events = vec![ThreadEvent::ItemCompleted(ItemCompletedEvent {
+            item: ThreadItem {
+                id: "item_0".to_string(),
+                details: ThreadItemDetails::FileChange(FileChangeItem {
+                    changes: ["/work/out/chart.png", "/work/report.md", "/tmp/scratch.txt"]
+                        .into_iter()
+                        .map(|path| FileUpdateChange {
+                            path: path.to_string(),
+                            kind: PatchChangeKind::Add,
+                        })
+                        .collect(),
+                    status: PatchApplyStatus::Completed,
+                }),
+            },
+        })];
+
+        assert_eq!(
+            collect_created_files(&events, Path::new("/work")),
+            vec![
+                "out/chart.png".to_string(),
+                "report.md".to_string(),
+                "/tmp/scratch.txt".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_sandbox_policy_override() {
+        let policy =
+            resolve_sandbox_policy(Some("read-only"), None, &SandboxPolicy::DangerFullAccess)
+                .unwrap();
+        assert_eq!(policy, SandboxPolicy::ReadOnly);
+    }
+
+    #[test]
+    fn test_resolve_sandbox_policy_defaults_to_config() {
+        let configured = SandboxPolicy::new_workspace_write_policy();
+        let policy = resolve_sandbox_policy(None, None, &configured).unwrap();
+        assert_eq!(policy, configured);
+    }
+
+    #[test]
+    fn test_default_sandbox_mode_applies_without_override() -> Result<(), GatewayError> {
+        let default_mode = Some(parse_default_sandbox_mode(" read-only ")?);
+
+        let policy = resolve_sandbox_policy(None, default_mode, &SandboxPolicy::DangerFullAccess)?;
+        assert_eq!(policy, SandboxPolicy::ReadOnly);
+
+        // A request's own sandbox_mode still wins
+        let policy = resolve_sandbox_policy(
+            Some("workspace-write"),
+            default_mode,
+            &SandboxPolicy::DangerFullAccess,
+        )?;
+        assert_eq!(policy, SandboxPolicy::new_workspace_write_policy());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_default_sandbox_mode_is_config_error() {
+        let err = parse_default_sandbox_mode("full-access").unwrap_err();
+        assert!(
+            matches!(err, GatewayError::Config(ref msg) if msg.contains("CODEX_DEFAULT_SANDBOX_POLICY") && msg.contains("full-access"))
+        );
+    }
+
+    #[test]
+    fn test_validate_enforces_model_allowlist() {
+        let limits = ExecConfig {
+            allowed_models: vec!["gpt-5-mini".to_string()],
+            allowed_providers: vec!["openai".to_string()],
+            ..Default::default()
+        };
+
+        let mut allowed = ExecRequest {
+            prompt: "hello".to_string(),
+            model: Some("gpt-5-mini".to_string()),
+            provider: Some("openai".to_string()),
+            ..Default::default()
+        };
+        assert!(allowed.validate(&limits).is_ok());
+
+        let mut unknown_model = ExecRequest {
+            prompt: "hello".to_string(),
+            model: Some("gpt-5".to_string()),
+            ..Default::default()
+        };
+        let err = unknown_model.validate(&limits).unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidRequest(ref msg) if msg.contains("'model'")));
+
+        let mut unknown_provider = ExecRequest {
+            prompt: "hello".to_string(),
+            provider: Some("ollama".to_string()),
+            ..Default::default()
+        };
+        let err = unknown_provider.validate(&limits).unwrap_err();
+        assert!(matches!(err, GatewayError::InvalidRequest(ref msg) if msg.contains("'provider'")));
+    }
+
+    #[test]
+    fn test_resolve_workdir_inside_root() -> Result<(), Box<dyn std::error::Error>> {
+        let root = tempfile::tempdir()?;
+        std::fs::create_dir(root.path().join("project"))?;
+        let limits = ExecConfig {
+            workdir_root: Some(root.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let resolved = resolve_workdir(Some(Path::new("project")), &limits)?;
+
+        assert_eq!(
+            resolved,
+            Some(std::fs::canonicalize(root.path().join("project"))?)
+        );
+        assert_eq!(resolve_workdir(None, &ExecConfig::default())?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_workdir_rejects_missing_dir() -> Result<(), Box<dyn std::error::Error>> {
+        let root = tempfile::tempdir()?;
+        let limits = ExecConfig::default();
+
+        let err = resolve_workdir(Some(&root.path().join("missing")), &limits).unwrap_err();
+
+        assert!(matches!(err, GatewayError::InvalidRequest(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_workdir_rejects_traversal_outside_root()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let parent = tempfile::tempdir()?;
+        let root = parent.path().join("root");
+        std::fs::create_dir(&root)?;
+        std::fs::create_dir(parent.path().join("outside"))?;
+        let limits = ExecConfig {
+            workdir_root: Some(root),
+            ..Default::default()
+        };
+
+        let err = resolve_workdir(Some(Path::new("../outside")), &limits).unwrap_err();
+
+        assert!(matches!(err, GatewayError::Forbidden(_)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_resolves_params_without_running() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let state = AppState::new(GatewayConfig::default()).await?;
+        let request = ExecRequest {
+            prompt: "create a hello world python script".to_string(),
+            session_id: Some("dry-session".to_string()),
+            model: Some("gpt-5-mini".to_string()),
+            sandbox_mode: Some("read-only".to_string()),
+            dry_run: true,
+            ..Default::default()
+        };
+
+        let response = handle_exec(
+            State(state.clone()),
+            None,
+            None,
+            Query(ExecQuery::default()),
+            HeaderMap::new(),
+            Json(request),
+        )
+        .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        let plan: Value = serde_json::from_slice(&body)?;
+        assert_eq!(plan["status"], "dry_run");
+        assert_eq!(plan["model"], "gpt-5-mini");
+        assert_eq!(plan["approval_policy"], "never");
+        assert_eq!(plan["input_items"], 1);
+
+        // No conversation was created for the session
+        assert!(
+            !state
+                .codex_service
+                .active_conversations()
+                .lock()
+                .await
+                .contains_key("dry-session")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_prompt_prefix_is_prepended() {
+        let limits = ExecConfig {
+            prompt_prefix: Some("Never touch files outside the repo.".to_string()),
+            ..Default::default()
+        };
+        let mut request = ExecRequest {
+            prompt: "delete the build directory".to_string(),
+            ..Default::default()
+        };
+
+        let prefix = prompt_prefix(&limits, Some("Answer in English."));
+        request.prompt = apply_prompt_prefix(prefix.as_deref(), &request.prompt);
+        let inputs = prepare_user_inputs(&request).unwrap();
+
+        let UserInput::Text { text } = &inputs[0] else {
+            panic!("expected a text input");
+        };
+        assert_eq!(
+            text,
+            "Never touch files outside the repo.\n\nAnswer in English.\n\ndelete the build directory"
+        );
+
+        // Without any prefix the prompt goes out unchanged
+        assert_eq!(prompt_prefix(&ExecConfig::default(), Some("  ")), None);
+    }
+
+    #[test]
+    fn test_validate_session_id() {
+        assert!(validate_session_id("my-session_01").is_ok());
+        assert!(validate_session_id("550e8400-e29b-41d4-a716-446655440000").is_ok());
+
+        for bad in ["", "../etc/passwd", "a/b", "has space", &"x".repeat(65)] {
+            assert!(
+                matches!(
+                    validate_session_id(bad),
+                    Err(GatewayError::InvalidRequest(_))
+                ),
+                "{bad:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_allows_absent_session_id() {
+        let mut request = ExecRequest {
+            prompt: "hello".to_string(),
+            ..Default::default()
+        };
+        assert!(request.validate(&ExecConfig::default()).is_ok());
+
+        request.session_id = Some("sessions/../other".to_string());
+        assert!(request.validate(&ExecConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_resolve_approval_policy() -> Result<(), GatewayError> {
+        assert_eq!(resolve_approval_policy(None)?, AskForApproval::Never);
+        assert_eq!(
+            resolve_approval_policy(Some("on-request"))?,
+            AskForApproval::OnRequest
+        );
+        assert_eq!(
+            resolve_approval_policy(Some("unless-trusted"))?,
+            AskForApproval::UnlessTrusted
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_approval_policy() {
+        let mut request = ExecRequest {
+            prompt: "hello".to_string(),
+            approval_policy: Some("sometimes".to_string()),
+            ..Default::default()
+        };
+
+        let err = request.validate(&ExecConfig::default()).unwrap_err();
+        assert!(
+            matches!(err, GatewayError::InvalidRequest(ref msg) if msg.contains("approval_policy"))
+        );
+    }
+
+    #[test]
+    fn test_sandbox_scope_allows_listed_mode() {
+        let scope = ApiKeyScope::sandbox_modes(vec![SandboxMode::ReadOnly]);
+
+        assert!(check_sandbox_scope(&SandboxPolicy::ReadOnly, &scope).is_ok());
+        assert!(check_sandbox_scope(&SandboxPolicy::DangerFullAccess, &scope).is_err());
+        assert!(
+            check_sandbox_scope(&SandboxPolicy::DangerFullAccess, &ApiKeyScope::default()).is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exec_scope_applies_to_default_sandbox_policy()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = GatewayConfig::default();
+        config.exec.default_sandbox_mode = Some(SandboxMode::DangerFullAccess);
+        let state = AppState::new(config).await?;
+        let scope = ApiKeyScope::sandbox_modes(vec![SandboxMode::ReadOnly]);
+        // No sandbox_mode, so the turn would run with the default
+        let request = ExecRequest {
+            prompt: "delete everything".to_string(),
+            ..Default::default()
+        };
+
+        let result = handle_exec(
+            State(state),
+            None,
+            Some(Extension(scope)),
+            Query(ExecQuery::default()),
+            HeaderMap::new(),
+            Json(request),
+        )
+        .await;
+
+        assert!(matches!(result, Err(GatewayError::Forbidden(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exec_rejects_sandbox_mode_outside_scope() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let state = AppState::new(GatewayConfig::default()).await?;
+        let scope = ApiKeyScope::sandbox_modes(vec![SandboxMode::ReadOnly]);
+        let request = ExecRequest {
+            prompt: "delete everything".to_string(),
+            sandbox_mode: Some("danger-full-access".to_string()),
+            ..Default::default()
+        };
+
+        let result = handle_exec(
+            State(state),
+            None,
+            Some(Extension(scope)),
+            Query(ExecQuery::default()),
+            HeaderMap::new(),
+            Json(request),
+        )
+        .await;
+
+        assert!(matches!(result, Err(GatewayError::Forbidden(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exec_rejects_prompt_outside_allowlist() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let mut state = AppState::new(GatewayConfig::default()).await?;
+        state.prompt_allowlist = Arc::new(PromptAllowlist::parse("Run the test suite")?);
+        let request = ExecRequest {
+            prompt: "delete everything".to_string(),
+            ..Default::default()
+        };
+
+        let result = handle_exec(
+            State(state),
+            None,
+            None,
+            Query(ExecQuery::default()),
+            HeaderMap::new(),
+            Json(request),
+        )
+        .await;
+
+        assert!(matches!(result, Err(GatewayError::Forbidden(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exec_over_inflight_capacity_gets_503() -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = GatewayConfig::default();
+        config.exec.max_inflight = 2;
+        let state = AppState::new(config).await?;
+
+        // Two execs already running or queued fill the in-flight capacity
+        let first = state.enter_inflight()?;
+        let _second = state.enter_inflight()?;
+
+        for _ in 0..3 {
+            let result = handle_exec(
+                State(state.clone()),
+                None,
+                None,
+                Query(ExecQuery::default()),
+                HeaderMap::new(),
+                Json(ExecRequest {
+                    prompt: "echo hello".to_string(),
+                    ..Default::default()
+                }),
+            )
+            .await;
+
+            let response = result.unwrap_err().into_response();
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+            assert!(
+                response
+                    .headers()
+                    .contains_key(axum::http::header::RETRY_AFTER)
+            );
+        }
+
+        drop(first);
+        assert!(state.enter_inflight().is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exec_over_daily_quota_gets_429() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::services::DailyQuota;
+
+        let mut state = AppState::new(GatewayConfig::default()).await?;
+        state.daily_quota = Arc::new(DailyQuota::new(1, None));
+        state.daily_quota.try_consume("key-1", 1)?;
+
+        let result = handle_exec(
+            State(state.clone()),
+            Some(Extension(ApiKeyId("key-1".to_string()))),
+            None,
+            Query(ExecQuery::default()),
+            HeaderMap::new(),
+            Json(ExecRequest {
+                prompt: "echo hello".to_string(),
+                ..Default::default()
+            }),
+        )
+        .await;
+
+        let response = result.unwrap_err().into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(
+            response
+                .headers()
+                .contains_key(axum::http::header::RETRY_AFTER)
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        let body: Value = serde_json::from_slice(&body)?;
+        assert_eq!(body["code"], "quota_exceeded");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rejected_exec_does_not_use_quota() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::services::CircuitBreaker;
+        use crate::services::DailyQuota;
+
+        let mut state = AppState::new(GatewayConfig::default()).await?;
+        state.daily_quota = Arc::new(DailyQuota::new(1, None));
+        state.circuit_breaker = Arc::new(CircuitBreaker::new(
+            1,
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+        ));
+        state.circuit_breaker.record_failure();
+
+        let result = handle_exec(
+            State(state.clone()),
+            Some(Extension(ApiKeyId("key-1".to_string()))),
+            None,
+            Query(ExecQuery::default()),
+            HeaderMap::new(),
+            Json(ExecRequest {
+                prompt: "echo hello".to_string(),
+                ..Default::default()
+            }),
+        )
+        .await;
+
+        assert!(matches!(result, Err(GatewayError::ServiceUnavailable(_))));
+        assert_eq!(
+            state.daily_quota.remaining_at("key-1", chrono::Utc::now()),
+            1
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_quota_rejection_leaves_half_open_probe() -> Result<(), Box<dyn std::error::Error>>
+    {
+        use crate::services::CircuitBreaker;
+        use crate::services::DailyQuota;
+
+        let mut state = AppState::new(GatewayConfig::default()).await?;
+        state.daily_quota = Arc::new(DailyQuota::new(1, None));
+        state.daily_quota.try_consume("key-1", 1)?;
+        state.circuit_breaker = Arc::new(CircuitBreaker::new(
+            1,
+            Duration::from_secs(60),
+            Duration::from_millis(50),
+        ));
+        state.circuit_breaker.record_failure();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        let result = handle_exec(
+            State(state.clone()),
+            Some(Extension(ApiKeyId("key-1".to_string()))),
+            None,
+            Query(ExecQuery::default()),
+            HeaderMap::new(),
+            Json(ExecRequest {
+                prompt: "echo hello".to_string(),
+                ..Default::default()
+            }),
+        )
+        .await;
+
+        assert!(matches!(result, Err(GatewayError::QuotaExceeded { .. })));
+        assert!(
+            state.circuit_breaker.try_acquire().is_ok(),
+            "the probe is still free for the next exec"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_batch_stopped_early_is_charged_only_for_prompts_it_ran()
+    -> Result<(), Box<dyn std::error::Error>> {
+        use crate::services::DailyQuota;
+
+        let mut state = AppState::new(GatewayConfig::default()).await?;
+        state.daily_quota = Arc::new(DailyQuota::new(3, None));
+        state.daily_quota.try_consume("key-1", 1)?;
+
+        // Each prompt fails fast on the unknown provider, without a model call
+        let (status, Json(body)) = handle_exec_batch(
+            State(state.clone()),
+            Some(Extension(ApiKeyId("key-1".to_string()))),
+            None,
+            Json(BatchExecRequest {
+                prompts: vec!["one".into(), "two".into(), "three".into(), "four".into()],
+                provider: Some("no-such-provider".to_string()),
+                ..Default::default()
+            }),
+        )
+        .await?;
+
+        assert_eq!(status, StatusCode::OK);
+        let results = body["results"].as_array().ok_or("missing results")?;
+        // Two prompts fit in the quota; the third is refused and stops the batch
+        assert_eq!(results.len(), 3);
+        assert!(
+            results[2]["error"]
+                .as_str()
+                .is_some_and(|error| error.contains("daily execs"))
+        );
+        assert_eq!(
+            state.daily_quota.remaining_at("key-1", chrono::Utc::now()),
+            0
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_stream_parses_line_by_line() -> Result<(), Box<dyn std::error::Error>> {
+        use codex_exec::exec_events::*;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (result_tx, result_rx) = oneshot::channel();
+        let events = vec![
+            ThreadEvent::ThreadStarted(ThreadStartedEvent {
+                thread_id: "thread-1".to_string(),
+            }),
+            ThreadEvent::TurnStarted(TurnStartedEvent {}),
+            ThreadEvent::ItemCompleted(ItemCompletedEvent {
+                item: ThreadItem {
+                    id: "item_0".to_string(),
+                    details: ThreadItemDetails::AgentMessage(AgentMessageItem {
+                        text: "done".to_string(),
+                    }),
+                },
+            }),
+            ThreadEvent::TurnCompleted(TurnCompletedEvent {
+                usage: Usage::default(),
+            }),
+        ];
+        for event in &events {
+            tx.send(event.clone())?;
+        }
+        drop(tx);
+        let _ = result_tx.send(Ok(ExecResponse {
+            conversation_id: "conv-1".to_string(),
+            events: events.clone(),
+            status: ExecStatus::Completed,
+            created_files: Vec::new(),
+            prompt_prefix: None,
+            error: None,
+            retries: ExecRetries { model_stream: 2 },
+            timings: ExecTimings {
+                queue: Duration::from_millis(3),
+                session: Duration::from_millis(12),
+                exec: Duration::from_millis(2_500),
+                teardown: Duration::from_millis(1),
+            },
+        }));
+
+        let body = Body::from_stream(ndjson_stream(rx, result_rx));
+        let body = axum::body::to_bytes(body, usize::MAX).await?;
+        let lines = std::str::from_utf8(&body)?
+            .lines()
+            .map(serde_json::from_str::<Value>)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let names: Vec<&str> = lines.iter().filter_map(|l| l["event"].as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "thread.started",
+                "turn.started",
+                "item.completed",
+                "turn.completed",
+                "timings",
+                "exec.completed"
+            ]
+        );
+        assert_eq!(lines[2]["data"]["item"]["text"], "done");
+        assert_eq!(lines[4]["data"]["exec_ms"], 2500.0);
+        assert_eq!(lines[4]["data"]["total_ms"], 2516.0);
+        assert_eq!(lines[5]["data"]["status"], "completed");
+        assert_eq!(lines[5]["data"]["retries"]["model_stream"], 2);
+        assert!(lines[5]["data"].get("events").is_none());
+        assert!(lines[5]["data"].get("timings").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_server_timing_names_every_phase() {
+        let timings = ExecTimings {
+            queue: Duration::from_micros(300),
+            session: Duration::from_millis(4),
+            exec: Duration::from_millis(1_500),
+            teardown: Duration::from_millis(2),
+        };
+        assert_eq!(
+            timings.server_timing(),
+            "queue;dur=0.3, session;dur=4.0, exec;dur=1500.0, teardown;dur=2.0, total;dur=1506.3"
+        );
+
+        let response = with_server_timing(StatusCode::OK.into_response(), Some(timings));
+        let header = response.headers()[SERVER_TIMING_HEADER].to_str().unwrap();
+        for phase in ["queue", "session", "exec", "teardown", "total"] {
+            assert!(header.contains(&format!("{phase};dur=")), "missing {phase}");
+        }
+        let untimed = with_server_timing(StatusCode::OK.into_response(), None);
+        assert!(untimed.headers().get(SERVER_TIMING_HEADER).is_none());
+    }
+
+    #[test]
+    fn test_idempotency_key_header() {
+        let mut headers = HeaderMap::new();
+        assert!(matches!(idempotency_key(&headers), Ok(None)));
+
+        headers.insert(IDEMPOTENCY_KEY_HEADER, "retry-123".parse().unwrap());
+        assert_eq!(
+            idempotency_key(&headers).unwrap().as_deref(),
+            Some("retry-123")
+        );
+
+        headers.insert(
+            IDEMPOTENCY_KEY_HEADER,
+            "k".repeat(MAX_KEY_LEN + 1).parse().unwrap(),
+        );
+        assert!(matches!(
+            idempotency_key(&headers),
+            Err(GatewayError::InvalidRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_replays_only_the_same_body()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+        let first = || ExecRequest {
+            prompt: "first".to_string(),
+            ..Default::default()
+        };
+        let _ = state
+            .idempotency
+            .slot("key_001", "retry-1", &request_fingerprint(&first())?)?
+            .set((StatusCode::OK, json!({"status": "completed"})));
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_static("retry-1"));
+        let call = |request: ExecRequest| {
+            handle_exec(
+                State(state.clone()),
+                Some(Extension(ApiKeyId("key_001".to_string()))),
+                None,
+                Query(ExecQuery::default()),
+                headers.clone(),
+                Json(request),
+            )
+        };
+
+        let replayed = call(first()).await?;
+        assert_eq!(
+            replayed.headers().get(IDEMPOTENT_REPLAYED_HEADER),
+            Some(&HeaderValue::from_static("true"))
+        );
+
+        let other = ExecRequest {
+            prompt: "second".to_string(),
+            ..Default::default()
+        };
+        let result = call(other).await;
+        assert!(matches!(result, Err(GatewayError::IdempotencyKeyReused(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_rejects_non_http_callback_url() {
+        let limits = ExecConfig::default();
+        for callback_url in ["not a url", "ftp://example.com/hook"] {
+            let mut request = ExecRequest {
+                prompt: "hello".to_string(),
+                callback_url: Some(callback_url.to_string()),
+                ..Default::default()
+            };
+            assert!(matches!(
+                request.validate(&limits),
+                Err(GatewayError::InvalidRequest(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_callback_requires_webhook_secret() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::services::CallbackClient;
+
+        // Regardless of CODEX_WEBHOOK_SECRET in the test environment
+        let mut state = AppState::new(GatewayConfig::default()).await?;
+        state.callbacks = Arc::new(CallbackClient::new(None));
+
+        let request = ExecRequest {
+            prompt: "hello".to_string(),
+            callback_url: Some("https://example.com/hook".to_string()),
+            ..Default::default()
+        };
+        let err = handle_exec(
+            State(state),
+            None,
+            None,
+            Query(ExecQuery::default()),
+            HeaderMap::new(),
+            Json(request),
+        )
+        .await
+        .err()
+        .ok_or("callback_url without a webhook secret should be rejected")?;
+
+        assert!(
+            matches!(err, GatewayError::InvalidRequest(ref msg) if msg.contains("CODEX_WEBHOOK_SECRET"))
+        );
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.code(), "invalid_request");
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_sandbox_mode() {
+        let mut request = ExecRequest {
+            prompt: "hello".to_string(),
+            sandbox_mode: Some("root".to_string()),
+            ..Default::default()
+        };
+
+        let err = request.validate(&ExecConfig::default()).unwrap_err();
+        assert!(
+            matches!(err, GatewayError::InvalidRequest(ref msg) if msg.contains("sandbox_mode"))
+        );
+    }
+
     #[test]
     fn test_determine_status_completed() {
         use codex_exec::exec_events::*;
@@ -476,7 +3650,64 @@ mod tests {
             usage: Default::default(),
         })];
 
-        assert_eq!(determine_status(&events), "completed");
+        assert_eq!(determine_status(&events), ExecStatus::Completed);
+    }
+
+    #[test]
+    fn test_determine_status_failed() {
+        use codex_exec::exec_events::*;
+
+        let events = vec![ThreadEvent::TurnFailed(TurnFailedEvent {
+            error: ThreadErrorEvent {
+                message: "model refused".to_string(),
+            },
+        })];
+
+        assert_eq!(determine_status(&events), ExecStatus::Failed);
+        assert_eq!(determine_status(&[]), ExecStatus::Unknown);
+    }
+
+    #[test]
+    fn test_stream_ending_early_is_explained() {
+        use codex_exec::exec_events::*;
+
+        // The session went away after the turn started but before it finished
+        let events = vec![ThreadEvent::TurnStarted(TurnStartedEvent {})];
+        let status = determine_status(&events);
+        assert_eq!(status, ExecStatus::Unknown);
+        assert_eq!(
+            status_error(status, &events, 0, 1000, Some("channel closed")).as_deref(),
+            Some("agent event stream ended before the turn finished: channel closed")
+        );
+        assert_eq!(
+            status_error(status, &events, 0, 1000, None).as_deref(),
+            Some("agent event stream ended before the turn finished")
+        );
+        assert_eq!(
+            status_error(ExecStatus::Completed, &events, 0, 1000, None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_exec_status_wire_format() -> Result<(), serde_json::Error> {
+        for status in [
+            ExecStatus::Completed,
+            ExecStatus::Failed,
+            ExecStatus::Error,
+            ExecStatus::Cancelled,
+            ExecStatus::Timeout,
+            ExecStatus::LifetimeExceeded,
+            ExecStatus::Unknown,
+        ] {
+            assert_eq!(serde_json::to_value(status)?, json!(status.as_str()));
+        }
+        assert_eq!(ExecStatus::Timeout.outcome(), ExecOutcome::TimedOut);
+        assert_eq!(
+            ExecStatus::LifetimeExceeded.outcome(),
+            ExecOutcome::TimedOut
+        );
+        Ok(())
     }
 
     #[test]
@@ -487,6 +3718,127 @@ mod tests {
             message: "test error".to_string(),
         })];
 
-        assert_eq!(determine_status(&events), "error");
+        assert_eq!(determine_status(&events), ExecStatus::Error);
+    }
+
+    #[tokio::test]
+    async fn test_collect_events_keeps_partial_events_on_timeout() {
+        use codex_exec::exec_events::*;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tx.send(ThreadEvent::ThreadStarted(ThreadStartedEvent {
+            thread_id: "thread-1".to_string(),
+        }))
+        .unwrap();
+        tx.send(ThreadEvent::TurnStarted(TurnStartedEvent {}))
+            .unwrap();
+
+        // The sender stays alive, like a turn that hangs after its first events
+        let (events, stopped) =
+            collect_events(&mut rx, Some(Duration::from_millis(50)), None, None).await;
+
+        assert_eq!(stopped, Some(ExecStatus::Timeout));
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[1], ThreadEvent::TurnStarted(_)));
+        drop(tx);
+    }
+
+    #[tokio::test]
+    async fn test_collect_events_until_channel_closes() {
+        use codex_exec::exec_events::*;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tx.send(ThreadEvent::TurnStarted(TurnStartedEvent {}))
+            .unwrap();
+        drop(tx);
+
+        let (events, stopped) = collect_events(&mut rx, None, None, None).await;
+
+        assert_eq!(stopped, None);
+        assert_eq!(events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_collect_events_stops_busy_turn_at_lifetime() {
+        use codex_exec::exec_events::*;
+
+        // A turn that never goes quiet: an event every 10ms until dropped
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let producer = tokio::spawn(async move {
+            while tx
+                .send(ThreadEvent::TurnStarted(TurnStartedEvent {}))
+                .is_ok()
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+
+        let started = Instant::now();
+        let (events, stopped) = collect_events(
+            &mut rx,
+            Some(Duration::from_millis(200)),
+            Some(Duration::from_millis(100)),
+            None,
+        )
+        .await;
+
+        assert_eq!(stopped, Some(ExecStatus::LifetimeExceeded));
+        assert!(started.elapsed() < Duration::from_millis(200));
+        assert!(events.len() > 1);
+        drop(rx);
+        producer.await.unwrap();
+    }
+
+    /// A guard whose disconnect action records that it ran
+    fn disconnect_guard(finished: &Arc<AtomicBool>) -> (DisconnectGuard, Arc<AtomicBool>) {
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&interrupted);
+        let guard = DisconnectGuard::new(
+            Arc::clone(finished),
+            Duration::from_millis(20),
+            async move {
+                flag.store(true, Ordering::SeqCst);
+            },
+        );
+        (guard, interrupted)
+    }
+
+    #[tokio::test]
+    async fn test_client_disconnect_interrupts_turn() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let (guard, interrupted) = disconnect_guard(&finished);
+        let (tx, mut rx) = mpsc::unbounded_channel::<ThreadEvent>();
+
+        // The handler future is dropped mid-turn, as axum does on disconnect
+        let exec = tokio::spawn(async move {
+            let _ = collect_events(&mut rx, None, None, None).await;
+            guard.disarm();
+        });
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        exec.abort();
+        let _ = exec.await;
+
+        assert!(
+            !interrupted.load(Ordering::SeqCst),
+            "waits for the grace period"
+        );
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(interrupted.load(Ordering::SeqCst));
+        drop(tx);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_guard_spares_finished_turns() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let (guard, interrupted) = disconnect_guard(&finished);
+        guard.disarm();
+
+        let (guard, finished_interrupted) = disconnect_guard(&finished);
+        drop(guard);
+        finished.store(true, Ordering::SeqCst);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(!interrupted.load(Ordering::SeqCst));
+        assert!(!finished_interrupted.load(Ordering::SeqCst));
     }
 }
@@ -1,12 +1,20 @@
 //! Health check handler
 
 use crate::error::GatewayResult;
+use crate::handlers::exec::run_smoke_exec;
+use crate::services::provider_credentials;
 use crate::state::AppState;
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::Json;
 use serde_json::Value;
 use serde_json::json;
+use std::path::Path;
+use std::time::Duration;
+
+/// Longest the `/healthz/deep` smoke exec may take, including queueing;
+/// below the default request timeout so the probe still gets an answer
+const SMOKE_TIMEOUT: Duration = Duration::from_secs(20);
 
 /// Health check endpoint
 ///
@@ -31,6 +39,132 @@ pub async fn health_check(
     Ok((StatusCode::OK, Json(response)))
 }
 
+/// Readiness check endpoint
+///
+/// Verifies that `CODEX_HOME` (where config and session rollouts live) is an
+/// existing, writable directory. Cheap enough for frequent probes: it only
+/// inspects filesystem metadata.
+///
+/// ## Response
+///
+/// ```json
+/// {
+///   "status": "ok",
+///   "codex_home": "/home/gateway/.codex",
+///   "circuit_breaker": "closed",
+///   "providers": {"openai": "configured", "oss": "not_required"}
+/// }
+/// ```
+///
+/// Returns 503 with an `error` field when the directory is unusable.
+/// `circuit_breaker` is "closed", "open" or "half_open"; an open breaker does
+/// not fail the probe, since restarting the instance would not fix the backend.
+/// `providers` gives each model provider's credential status ("configured",
+/// "missing", "malformed" or "not_required"), also informational only.
+pub async fn readiness_check(
+    State(state): State<AppState>,
+) -> GatewayResult<(StatusCode, Json<Value>)> {
+    let codex_config = state.codex_service.codex_config();
+    let codex_home = &codex_config.codex_home;
+    let circuit_breaker = state.circuit_breaker.state();
+    let providers = provider_credentials::from_env(codex_config);
+
+    let response = match check_codex_home(codex_home) {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(json!({
+                "status": "ok",
+                "codex_home": codex_home.display().to_string(),
+                "circuit_breaker": circuit_breaker,
+                "providers": providers,
+            })),
+        ),
+        Err(error) => {
+            tracing::warn!("Readiness check failed: {}", error);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({
+                    "status": "unavailable",
+                    "codex_home": codex_home.display().to_string(),
+                    "circuit_breaker": circuit_breaker,
+                    "providers": providers,
+                    "error": error,
+                })),
+            )
+        }
+    };
+
+    Ok(response)
+}
+
+/// Deep readiness check endpoint
+///
+/// Runs a trivial read-only prompt through the whole exec pipeline, so
+/// broken credentials or a bad model config show up even though the
+/// shallow `/healthz` passes. The result is cached for a minute; probes in
+/// between get the cached result with `"cached": true`. Unlike `/healthz`,
+/// it requires an API key.
+///
+/// ## Response
+///
+/// ```json
+/// {
+///   "status": "ok",
+///   "smoke_exec": {"ok": true, "duration_ms": 2140, "checked_at": "2025-01-02T12:00:05+00:00"},
+///   "cached": false
+/// }
+/// ```
+///
+/// Returns 503 with `"status": "unavailable"` and `smoke_exec.error` when the
+/// exec did not complete within 20 seconds.
+pub async fn deep_readiness_check(
+    State(state): State<AppState>,
+) -> GatewayResult<(StatusCode, Json<Value>)> {
+    let (smoke_exec, cached) = state
+        .smoke_check
+        .check(|| run_smoke_exec(&state, SMOKE_TIMEOUT))
+        .await;
+
+    let (status_code, status) = if smoke_exec.ok {
+        (StatusCode::OK, "ok")
+    } else {
+        if !cached {
+            tracing::warn!(
+                "Deep readiness check failed: {}",
+                smoke_exec.error.as_deref().unwrap_or_default()
+            );
+        }
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+    };
+    Ok((
+        status_code,
+        Json(json!({
+            "status": status,
+            "smoke_exec": smoke_exec,
+            "cached": cached,
+        })),
+    ))
+}
+
+/// Check that `codex_home` is a directory the gateway can write sessions to
+fn check_codex_home(codex_home: &Path) -> Result<(), String> {
+    let metadata = std::fs::metadata(codex_home)
+        .map_err(|e| format!("CODEX_HOME {} is not accessible: {e}", codex_home.display()))?;
+
+    if !metadata.is_dir() {
+        return Err(format!(
+            "CODEX_HOME {} is not a directory",
+            codex_home.display()
+        ));
+    }
+
+    if metadata.permissions().readonly() {
+        return Err(format!("CODEX_HOME {} is read-only", codex_home.display()));
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,4 +183,67 @@ mod tests {
         assert_eq!(value["status"], "healthy");
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_readiness_reports_circuit_breaker() -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = GatewayConfig::default();
+        config.exec.breaker_threshold = 2;
+        let state = AppState::new(config).await?;
+
+        let (_, Json(body)) = readiness_check(State(state.clone())).await?;
+        assert_eq!(body["circuit_breaker"], "closed");
+        assert!(body["providers"]["openai"].is_string());
+
+        state.circuit_breaker.record_failure();
+        state.circuit_breaker.record_failure();
+        let (_, Json(body)) = readiness_check(State(state)).await?;
+        assert_eq!(body["circuit_breaker"], "open");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deep_readiness_reports_cached_smoke_failure()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+        state
+            .smoke_check
+            .check(|| async { Err("smoke exec ended with status failed".to_string()) })
+            .await;
+
+        let (status, Json(body)) = deep_readiness_check(State(state)).await?;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "unavailable");
+        assert_eq!(body["cached"], true);
+        assert_eq!(body["smoke_exec"]["ok"], false);
+        assert_eq!(
+            body["smoke_exec"]["error"],
+            "smoke exec ended with status failed"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_codex_home_existing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(check_codex_home(dir.path()).is_ok());
+    }
+
+    #[test]
+    fn test_check_codex_home_missing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("does-not-exist");
+
+        let err = check_codex_home(&missing).unwrap_err();
+        assert!(err.contains("not accessible"));
+    }
+
+    #[test]
+    fn test_check_codex_home_file_instead_of_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("codex-home");
+        std::fs::write(&file, b"").unwrap();
+
+        let err = check_codex_home(&file).unwrap_err();
+        assert!(err.contains("not a directory"));
+    }
 }
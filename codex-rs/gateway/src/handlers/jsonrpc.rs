@@ -12,7 +12,9 @@ use tracing::error;
 use tracing::info;
 
 use crate::error::GatewayResult;
+use crate::handlers::exec::validate_session_id;
 use crate::services::CodexService;
+use crate::services::PromptAllowlist;
 use crate::state::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -112,7 +114,7 @@ pub async fn handle_jsonrpc(
     let response = match request.method.as_str() {
         "conversation.prompt" => {
             info!("Processing conversation.prompt request");
-            process_execute(codex_service, &request).await
+            process_execute(codex_service, &state.prompt_allowlist, &request).await
         }
         "conversation.status" => {
             info!("Processing conversation.status request");
@@ -143,7 +145,11 @@ pub async fn handle_jsonrpc(
 }
 
 /// Process execute request - main AI prompt processing
-async fn process_execute(service: &CodexService, request: &JsonRpcRequest) -> JsonRpcResponse {
+async fn process_execute(
+    service: &CodexService,
+    allowlist: &PromptAllowlist,
+    request: &JsonRpcRequest,
+) -> JsonRpcResponse {
     let params = match &request.params {
         Some(p) => p,
         None => {
@@ -171,6 +177,12 @@ async fn process_execute(service: &CodexService, request: &JsonRpcRequest) -> Js
     };
 
     let session_id = params.get("session_id").and_then(|v| v.as_str());
+    if let Some(Err(e)) = session_id.map(validate_session_id) {
+        return JsonRpcResponse::invalid_params(request.id.clone(), e.to_string());
+    }
+    if let Err(e) = allowlist.check(prompt) {
+        return JsonRpcResponse::error(request.id.clone(), -32003, e.to_string(), None);
+    }
 
     match service.execute_prompt(prompt, session_id).await {
         Ok(result) => JsonRpcResponse::success(request.id.clone(), result),
@@ -318,6 +330,25 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_conversation_prompt_rejects_unsafe_session_id()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "conversation.prompt".to_string(),
+            params: Some(json!({"prompt": "hello", "session_id": "../other/session"})),
+            id: Some(json!(3)),
+        };
+
+        let (_, Json(response)) = handle_jsonrpc(State(state), Json(request)).await?;
+
+        let error = response.error.ok_or("expected invalid session_id error")?;
+        assert_eq!(error.code, -32602);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_conversation_prompt_returns_events_field()
     -> Result<(), Box<dyn std::error::Error>> {
@@ -0,0 +1,49 @@
+//! Prometheus metrics handler
+
+use crate::error::GatewayResult;
+use crate::state::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::http::header;
+
+/// Content type of the Prometheus text exposition format
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Metrics endpoint
+///
+/// Serves exec counters and the execution time histogram in the Prometheus
+/// text exposition format. Exempt from API key authentication so scrapers
+/// don't need a key.
+pub async fn handle_metrics(
+    State(state): State<AppState>,
+) -> GatewayResult<(StatusCode, [(header::HeaderName, &'static str); 1], String)> {
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)],
+        state.metrics.render(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GatewayConfig;
+    use crate::metrics::ExecOutcome;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_metrics_after_completed_exec() -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+        state.metrics.record_started();
+        state
+            .metrics
+            .record_finished(ExecOutcome::Completed, Duration::from_millis(42));
+
+        let (status, headers, body) = handle_metrics(State(state)).await?;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[0].1, PROMETHEUS_CONTENT_TYPE);
+        assert!(body.contains("codex_gateway_exec_completed_total 1\n"));
+        Ok(())
+    }
+}
@@ -1,15 +1,23 @@
 //! HTTP handlers for the Codex Gateway
 
+pub mod debug;
 pub mod exec;
 pub mod health;
 pub mod jsonrpc;
+pub mod metrics;
 pub mod oauth;
+pub mod sessions;
+pub mod version;
 pub mod webhook;
 pub mod websocket;
 
+pub use debug::*;
 pub use exec::*;
 pub use health::*;
 pub use jsonrpc::*;
+pub use metrics::*;
 pub use oauth::*;
+pub use sessions::*;
+pub use version::*;
 pub use webhook::*;
 pub use websocket::*;
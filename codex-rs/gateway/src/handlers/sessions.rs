@@ -0,0 +1,741 @@
+//! Session management handlers
+//!
+//! Endpoints that act on sessions created through `/exec`, `/jsonrpc` or `/ws`.
+//!
+//! A session whose turns ran under an API key belongs to that key: other
+//! keys get 404 for it and don't see it in the listings. Ownership is known
+//! while the session has a running turn or a recorded result in this process.
+
+use crate::error::GatewayError;
+use crate::error::GatewayResult;
+use crate::handlers::exec::validate_prompt;
+use crate::middleware::ApiKeyId;
+use crate::services::active_execs::ActiveExecSummary;
+use crate::services::codex_service::SessionsPage;
+use crate::state::AppState;
+use axum::body::Body;
+use axum::extract::Extension;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::response::Json;
+use axum::response::Response;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use serde_json::json;
+use std::path::Component;
+use tokio_util::io::ReaderStream;
+use tracing::info;
+
+/// Page size used when `limit` is not given
+const DEFAULT_LIST_LIMIT: usize = 20;
+
+/// Largest page size a client may request
+const MAX_LIST_LIMIT: usize = 100;
+
+/// API key that owns the session or conversation `id`, if known
+///
+/// The key of its running turn, or else of its last recorded result.
+fn session_owner(state: &AppState, id: &str) -> Option<String> {
+    state
+        .active_execs
+        .owner(id)
+        .or_else(|| state.exec_results.owner(id))
+}
+
+/// Whether the caller holding `key_id` may see a session owned by `owner`
+fn is_visible(owner: Option<&str>, key_id: Option<&str>) -> bool {
+    owner.is_none() || owner == key_id
+}
+
+/// Reject with 404 a session or conversation `id` that belongs to another API key
+pub fn check_session_owner(state: &AppState, id: &str, key_id: Option<&str>) -> GatewayResult<()> {
+    if is_visible(session_owner(state, id).as_deref(), key_id) {
+        Ok(())
+    } else {
+        Err(GatewayError::NotFound(format!("No session: {id}")))
+    }
+}
+
+/// API key ID of the caller, when authenticated with one
+fn caller_key(key_id: Option<Extension<ApiKeyId>>) -> Option<String> {
+    key_id.map(|Extension(ApiKeyId(id))| id)
+}
+
+/// Query parameters for `GET /sessions`
+#[derive(Debug, Default, Deserialize)]
+pub struct ListSessionsQuery {
+    /// Page size, 1 to 100 (default 20)
+    pub limit: Option<usize>,
+    /// `next_cursor` from the previous page
+    #[serde(alias = "page_token")]
+    pub cursor: Option<String>,
+}
+
+/// GET /sessions - List recorded sessions, newest first
+///
+/// Backed by the rollouts codex-core records under `CODEX_HOME/sessions`, so
+/// sessions from earlier runs of the gateway are included.
+///
+/// ## Response
+///
+/// ```json
+/// {
+///   "sessions": [
+///     {
+///       "conversation_id": "550e8400-e29b-41d4-a716-446655440000",
+///       "session_id": "my-session",
+///       "status": "active",
+///       "created_at": "2025-01-02T12:00:00.000Z",
+///       "updated_at": "2025-01-02T12:00:05.000Z"
+///     }
+///   ],
+///   "next_cursor": "2025-01-02T11-00-00|550e8400-e29b-41d4-a716-446655440001"
+/// }
+/// ```
+///
+/// `session_id` is only present while the conversation is active in this process.
+/// Sessions of other API keys are left out.
+pub async fn handle_list_sessions(
+    State(state): State<AppState>,
+    key_id: Option<Extension<ApiKeyId>>,
+    Query(query): Query<ListSessionsQuery>,
+) -> GatewayResult<Json<SessionsPage>> {
+    let key_id = caller_key(key_id);
+    let limit = match query.limit {
+        None => DEFAULT_LIST_LIMIT,
+        Some(limit) if (1..=MAX_LIST_LIMIT).contains(&limit) => limit,
+        Some(limit) => {
+            return Err(GatewayError::InvalidRequest(format!(
+                "limit must be between 1 and {MAX_LIST_LIMIT}, got {limit}"
+            )));
+        }
+    };
+
+    let mut page = state
+        .codex_service
+        .list_sessions(limit, query.cursor.as_deref())
+        .await?;
+    // A page may come back short; `next_cursor` still continues after it
+    page.sessions.retain(|session| {
+        let owner = session_owner(&state, &session.conversation_id.to_string());
+        is_visible(owner.as_deref(), key_id.as_deref())
+    });
+
+    Ok(Json(page))
+}
+
+/// GET /sessions/active - List exec turns running right now, oldest first
+///
+/// ## Response
+///
+/// ```json
+/// {
+///   "sessions": [
+///     {
+///       "session_id": "my-session",
+///       "conversation_id": "550e8400-e29b-41d4-a716-446655440000",
+///       "started_at": "2025-01-02T12:00:00+00:00",
+///       "elapsed_ms": 5230,
+///       "prompt_prefix": "create a hello world python script"
+///     }
+///   ]
+/// }
+/// ```
+///
+/// Execs of other API keys are left out.
+pub async fn handle_active_sessions(
+    State(state): State<AppState>,
+    key_id: Option<Extension<ApiKeyId>>,
+) -> GatewayResult<Json<ActiveSessions>> {
+    let key_id = caller_key(key_id);
+    let mut sessions = state.active_execs.snapshot();
+    sessions.retain(|exec| is_visible(exec.key_id.as_deref(), key_id.as_deref()));
+    Ok(Json(ActiveSessions { sessions }))
+}
+
+/// Response body of `GET /sessions/active`
+#[derive(Debug, Serialize)]
+pub struct ActiveSessions {
+    pub sessions: Vec<ActiveExecSummary>,
+}
+
+/// POST /sessions/{session_id}/cancel - Interrupt the running turn of a session
+///
+/// Submits `Op::Interrupt` to the conversation bound to the session so the
+/// agent stops instead of running until the request timeout. A pending
+/// `/exec` call for the same session returns with status `"cancelled"`.
+///
+/// ## Response
+///
+/// ```json
+/// {
+///   "status": "cancelled",
+///   "session_id": "my-session",
+///   "conversation_id": "550e8400-e29b-41d4-a716-446655440000"
+/// }
+/// ```
+///
+/// Returns 404 when the session has no active conversation.
+pub async fn handle_cancel_session(
+    State(state): State<AppState>,
+    key_id: Option<Extension<ApiKeyId>>,
+    Path(session_id): Path<String>,
+) -> GatewayResult<(StatusCode, Json<Value>)> {
+    info!("Cancel requested for session: {}", session_id);
+    check_session_owner(&state, &session_id, caller_key(key_id).as_deref())?;
+
+    let conversation_id = state
+        .codex_service
+        .interrupt_session(&session_id)
+        .await?
+        .ok_or_else(|| GatewayError::NotFound(format!("No active session: {session_id}")))?;
+
+    let response = json!({
+        "status": "cancelled",
+        "session_id": session_id,
+        "conversation_id": conversation_id.to_string(),
+    });
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Request body of `POST /sessions/{session_id}/input`
+#[derive(Debug, Deserialize)]
+pub struct SessionInput {
+    /// Text to add to the running turn, subject to `CODEX_MAX_PROMPT_BYTES`
+    pub text: String,
+}
+
+/// POST /sessions/{session_id}/input - Add user input to a running turn
+///
+/// The text is injected into the turn started by a pending `/exec` or
+/// WebSocket exec for the session, so the agent sees it before its next
+/// model request. Its effects show up in that exec's events.
+///
+/// ## Response
+///
+/// ```json
+/// {
+///   "status": "accepted",
+///   "session_id": "my-session",
+///   "conversation_id": "550e8400-e29b-41d4-a716-446655440000"
+/// }
+/// ```
+///
+/// Returns 404 when the session has no active conversation and 409 when no
+/// turn is running for it.
+pub async fn handle_session_input(
+    State(state): State<AppState>,
+    key_id: Option<Extension<ApiKeyId>>,
+    Path(session_id): Path<String>,
+    Json(input): Json<SessionInput>,
+) -> GatewayResult<(StatusCode, Json<Value>)> {
+    check_session_owner(&state, &session_id, caller_key(key_id).as_deref())?;
+    validate_prompt(&input.text, &state.config().exec)?;
+    state.prompt_allowlist.check(&input.text)?;
+
+    let has_conversation = state
+        .codex_service
+        .active_conversations()
+        .lock()
+        .await
+        .contains_key(&session_id);
+    if !has_conversation {
+        return Err(GatewayError::NotFound(format!(
+            "No active session: {session_id}"
+        )));
+    }
+    if !state.active_execs.is_running(&session_id) {
+        return Err(GatewayError::Conflict(format!(
+            "No turn is running for session: {session_id}"
+        )));
+    }
+
+    let conversation_id = state
+        .codex_service
+        .send_input(&session_id, &input.text)
+        .await?
+        .ok_or_else(|| GatewayError::NotFound(format!("No active session: {session_id}")))?;
+
+    let response = json!({
+        "status": "accepted",
+        "session_id": session_id,
+        "conversation_id": conversation_id.to_string(),
+    });
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// GET /sessions/{session_id}/result - Final result of the session's last exec
+///
+/// Returns the outcome of the most recent `/exec` turn run under the session
+/// without its event list.
+///
+/// ## Response
+///
+/// ```json
+/// {
+///   "session_id": "my-session",
+///   "conversation_id": "550e8400-e29b-41d4-a716-446655440000",
+///   "status": "completed",
+///   "execution_time_ms": 5230,
+///   "finished_at": "2025-01-02T12:00:05+00:00",
+///   "final_message": "Created hello.py",
+///   "usage": {"input_tokens": 1200, "cached_input_tokens": 0, "output_tokens": 85},
+///   "retries": {"model_stream": 0},
+///   "created_files": ["hello.py"]
+/// }
+/// ```
+///
+/// Returns 202 with `{"status": "running"}` while a turn is still running for
+/// the session and 404 when no result was recorded for it.
+pub async fn handle_session_result(
+    State(state): State<AppState>,
+    key_id: Option<Extension<ApiKeyId>>,
+    Path(session_id): Path<String>,
+) -> GatewayResult<Response> {
+    check_session_owner(&state, &session_id, caller_key(key_id).as_deref())?;
+    if state.active_execs.is_running(&session_id) {
+        let running = json!({
+            "status": "running",
+            "session_id": session_id,
+        });
+        return Ok((StatusCode::ACCEPTED, Json(running)).into_response());
+    }
+
+    let result = state
+        .exec_results
+        .get(&session_id)
+        .ok_or_else(|| GatewayError::NotFound(format!("No result for session: {session_id}")))?;
+    Ok((StatusCode::OK, Json(result)).into_response())
+}
+
+/// GET /sessions/{session_id}/artifacts/{name} - Download a file the session's last exec created
+///
+/// `name` is one of the `created_files` of the session's most recent
+/// `/exec` turn, relative to that turn's working directory, e.g.
+/// `out/chart.png`. The file is streamed with a `Content-Type` guessed from
+/// its extension, falling back to `application/octet-stream`.
+///
+/// Returns 400 for names that are absolute or contain `..`, 403 when the
+/// file resolves outside the working directory (e.g. through a symlink),
+/// and 404 when the turn did not create a file by that name or it is gone.
+pub async fn handle_session_artifact(
+    State(state): State<AppState>,
+    key_id: Option<Extension<ApiKeyId>>,
+    Path((session_id, name)): Path<(String, String)>,
+) -> GatewayResult<Response> {
+    check_session_owner(&state, &session_id, caller_key(key_id).as_deref())?;
+    let relative = std::path::Path::new(&name);
+    if name.is_empty()
+        || !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(GatewayError::InvalidRequest(format!(
+            "Invalid artifact name: {name}"
+        )));
+    }
+
+    let not_found =
+        || GatewayError::NotFound(format!("No artifact '{name}' in session: {session_id}"));
+    let result = state.exec_results.get(&session_id).ok_or_else(not_found)?;
+    let workdir = match result.workdir {
+        Some(workdir) if result.created_files.contains(&name) => workdir,
+        _ => return Err(not_found()),
+    };
+
+    let workdir = tokio::fs::canonicalize(&workdir)
+        .await
+        .map_err(|_| not_found())?;
+    let path = tokio::fs::canonicalize(workdir.join(relative))
+        .await
+        .map_err(|_| not_found())?;
+    if !path.starts_with(&workdir) {
+        return Err(GatewayError::Forbidden(format!(
+            "Artifact '{name}' is outside the session's working directory"
+        )));
+    }
+
+    let file = tokio::fs::File::open(&path)
+        .await
+        .map_err(|_| not_found())?;
+    let metadata = file.metadata().await.map_err(|_| not_found())?;
+    if !metadata.is_file() {
+        return Err(not_found());
+    }
+    let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_LENGTH, metadata.len().to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
+/// DELETE /sessions/{session_id} - Purge a session and its recorded rollout
+///
+/// The path segment may be a session ID or a conversation ID. Removes the in-memory
+/// session and deletes the conversation's rollout file from `CODEX_HOME`.
+///
+/// Returns 204 on success and 404 when nothing matched.
+pub async fn handle_delete_session(
+    State(state): State<AppState>,
+    key_id: Option<Extension<ApiKeyId>>,
+    Path(id): Path<String>,
+) -> GatewayResult<StatusCode> {
+    info!("Purge requested for session: {}", id);
+    check_session_owner(&state, &id, caller_key(key_id).as_deref())?;
+
+    if state.codex_service.purge_session(&id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(GatewayError::NotFound(format!(
+            "No session or conversation: {id}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GatewayConfig;
+    use crate::services::exec_results::ExecResult;
+    use crate::services::exec_results::ExecRetries;
+
+    #[tokio::test]
+    async fn test_list_sessions_rejects_out_of_range_limit()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+
+        let query = ListSessionsQuery {
+            limit: Some(0),
+            ..Default::default()
+        };
+        let result = handle_list_sessions(State(state), None, Query(query)).await;
+
+        assert!(matches!(result, Err(GatewayError::InvalidRequest(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_active_sessions_lists_running_execs() -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+
+        let first = state.active_execs.register(
+            Some("session-a"),
+            codex_protocol::ConversationId::new(),
+            "first",
+            None,
+        );
+        let second = state.active_execs.register(
+            Some("session-b"),
+            codex_protocol::ConversationId::new(),
+            "second",
+            None,
+        );
+
+        let Json(active) = handle_active_sessions(State(state.clone()), None).await?;
+        let ids: Vec<_> = active
+            .sessions
+            .iter()
+            .filter_map(|s| s.session_id.as_deref())
+            .collect();
+        assert_eq!(ids, vec!["session-a", "session-b"]);
+
+        drop(first);
+        drop(second);
+        let Json(active) = handle_active_sessions(State(state), None).await?;
+        assert!(active.sessions.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancel_unknown_session_returns_not_found()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+
+        let result =
+            handle_cancel_session(State(state), None, Path("missing-session".to_string())).await;
+
+        assert!(matches!(result, Err(GatewayError::NotFound(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_input_to_unknown_session_returns_not_found()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+
+        let result = handle_session_input(
+            State(state),
+            None,
+            Path("missing-session".to_string()),
+            Json(SessionInput {
+                text: "yes, continue".to_string(),
+            }),
+        )
+        .await;
+
+        assert!(matches!(result, Err(GatewayError::NotFound(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_input_without_running_turn_returns_conflict()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+        state
+            .codex_service
+            .active_conversations()
+            .lock()
+            .await
+            .insert(
+                "idle-session".to_string(),
+                codex_protocol::ConversationId::new(),
+            );
+
+        let result = handle_session_input(
+            State(state),
+            None,
+            Path("idle-session".to_string()),
+            Json(SessionInput {
+                text: "yes, continue".to_string(),
+            }),
+        )
+        .await;
+
+        assert!(matches!(result, Err(GatewayError::Conflict(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_session_result_by_state() -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+        state.exec_results.record(ExecResult {
+            session_id: "done-session".to_string(),
+            conversation_id: "conv-1".to_string(),
+            status: "completed".to_string(),
+            execution_time_ms: 1200,
+            finished_at: "2025-01-02T12:00:05+00:00".to_string(),
+            final_message: Some("Created hello.py".to_string()),
+            usage: None,
+            error: None,
+            retries: ExecRetries::default(),
+            created_files: vec!["hello.py".to_string()],
+            workdir: None,
+            key_id: None,
+        });
+        let _running = state.active_execs.register(
+            Some("busy-session"),
+            codex_protocol::ConversationId::new(),
+            "still going",
+            None,
+        );
+
+        let done =
+            handle_session_result(State(state.clone()), None, Path("done-session".to_string()))
+                .await?;
+        assert_eq!(done.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(done.into_body(), usize::MAX).await?;
+        let body: Value = serde_json::from_slice(&body)?;
+        assert_eq!(body["status"], "completed");
+        assert_eq!(body["final_message"], "Created hello.py");
+
+        let busy =
+            handle_session_result(State(state.clone()), None, Path("busy-session".to_string()))
+                .await?;
+        assert_eq!(busy.status(), StatusCode::ACCEPTED);
+
+        let missing = handle_session_result(State(state), None, Path("missing".to_string())).await;
+        assert!(matches!(missing, Err(GatewayError::NotFound(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sessions_of_another_key_are_hidden() -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+        state.exec_results.record(ExecResult {
+            session_id: "owned-session".to_string(),
+            conversation_id: "conv-1".to_string(),
+            status: "completed".to_string(),
+            execution_time_ms: 1200,
+            finished_at: "2025-01-02T12:00:05+00:00".to_string(),
+            final_message: Some("secret plan".to_string()),
+            usage: None,
+            error: None,
+            retries: ExecRetries::default(),
+            created_files: vec![],
+            workdir: None,
+            key_id: Some("key-a".to_string()),
+        });
+        let _running = state.active_execs.register(
+            Some("running-session"),
+            codex_protocol::ConversationId::new(),
+            "still going",
+            Some("key-a"),
+        );
+        let key = |id: &str| Some(Extension(ApiKeyId(id.to_string())));
+
+        let owner = handle_session_result(
+            State(state.clone()),
+            key("key-a"),
+            Path("owned-session".to_string()),
+        )
+        .await?;
+        assert_eq!(owner.status(), StatusCode::OK);
+        let Json(active) = handle_active_sessions(State(state.clone()), key("key-a")).await?;
+        assert_eq!(active.sessions.len(), 1);
+
+        let other = || key("key-b");
+        let result = handle_session_result(
+            State(state.clone()),
+            other(),
+            Path("owned-session".to_string()),
+        )
+        .await;
+        assert!(matches!(result, Err(GatewayError::NotFound(_))));
+        let artifact = handle_session_artifact(
+            State(state.clone()),
+            other(),
+            Path(("owned-session".to_string(), "hello.py".to_string())),
+        )
+        .await;
+        assert!(matches!(artifact, Err(GatewayError::NotFound(_))));
+        let delete =
+            handle_delete_session(State(state.clone()), other(), Path("conv-1".to_string())).await;
+        assert!(matches!(delete, Err(GatewayError::NotFound(_))));
+        let cancel = handle_cancel_session(
+            State(state.clone()),
+            other(),
+            Path("running-session".to_string()),
+        )
+        .await;
+        assert!(matches!(cancel, Err(GatewayError::NotFound(_))));
+        let input = handle_session_input(
+            State(state.clone()),
+            other(),
+            Path("running-session".to_string()),
+            Json(SessionInput {
+                text: "stop that".to_string(),
+            }),
+        )
+        .await;
+        assert!(matches!(input, Err(GatewayError::NotFound(_))));
+        let Json(active) = handle_active_sessions(State(state), other()).await?;
+        assert!(active.sessions.is_empty());
+        Ok(())
+    }
+
+    /// Record a finished turn under `session_id` that created `created_files` in `workdir`
+    fn record_artifacts(
+        state: &AppState,
+        session_id: &str,
+        workdir: &std::path::Path,
+        created_files: &[&str],
+    ) {
+        state.exec_results.record(ExecResult {
+            session_id: session_id.to_string(),
+            conversation_id: "conv-1".to_string(),
+            status: "completed".to_string(),
+            execution_time_ms: 900,
+            finished_at: "2025-01-02T12:00:05+00:00".to_string(),
+            final_message: None,
+            usage: None,
+            error: None,
+            retries: ExecRetries::default(),
+            created_files: created_files.iter().map(ToString::to_string).collect(),
+            workdir: Some(workdir.to_path_buf()),
+            key_id: None,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_session_artifact_is_downloaded() -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+        let workdir = tempfile::tempdir()?;
+        let png = b"\x89PNG\r\n\x1a\nnot really a chart";
+        std::fs::create_dir(workdir.path().join("out"))?;
+        std::fs::write(workdir.path().join("out/chart.png"), png)?;
+        record_artifacts(&state, "art-session", workdir.path(), &["out/chart.png"]);
+
+        let response = handle_session_artifact(
+            State(state),
+            None,
+            Path(("art-session".to_string(), "out/chart.png".to_string())),
+        )
+        .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        assert_eq!(&body[..], png);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_session_artifact_stays_in_workdir() -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+        let root = tempfile::tempdir()?;
+        let workdir = root.path().join("work");
+        std::fs::create_dir(&workdir)?;
+        std::fs::write(root.path().join("secret.txt"), "outside")?;
+        std::fs::write(workdir.join("notes.txt"), "not created by the turn")?;
+        record_artifacts(&state, "art-session", &workdir, &["../secret.txt"]);
+
+        let fetch = |name: &str| {
+            handle_session_artifact(
+                State(state.clone()),
+                None,
+                Path(("art-session".to_string(), name.to_string())),
+            )
+        };
+        assert!(matches!(
+            fetch("../secret.txt").await,
+            Err(GatewayError::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            fetch("/etc/passwd").await,
+            Err(GatewayError::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            fetch("notes.txt").await,
+            Err(GatewayError::NotFound(_))
+        ));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.path().join("secret.txt"), workdir.join("link.txt"))?;
+            record_artifacts(&state, "art-session", &workdir, &["link.txt"]);
+            assert!(matches!(
+                fetch("link.txt").await,
+                Err(GatewayError::Forbidden(_))
+            ));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_unknown_session_returns_not_found()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let state = AppState::new(GatewayConfig::default()).await?;
+
+        let result = handle_delete_session(
+            State(state),
+            None,
+            Path("00000000-0000-4000-8000-000000000000".to_string()),
+        )
+        .await;
+
+        assert!(matches!(result, Err(GatewayError::NotFound(_))));
+        Ok(())
+    }
+}
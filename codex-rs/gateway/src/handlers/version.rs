@@ -0,0 +1,53 @@
+//! Version handler
+
+use crate::error::GatewayResult;
+use axum::http::StatusCode;
+use axum::response::Json;
+use serde_json::Value;
+use serde_json::json;
+
+/// Gateway crate version (shared with codex-core through the workspace version)
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Commit the binary was built from, set by `build.rs`
+const GIT_SHA: &str = env!("CODEX_GATEWAY_GIT_SHA");
+
+/// Version endpoint
+///
+/// Reports which gateway build is live. codex-core is linked into the same
+/// binary, so `version` also identifies the agent runtime.
+///
+/// ## Response
+///
+/// ```json
+/// {
+///   "version": "0.0.0",
+///   "git_sha": "9deed74a1b2c"
+/// }
+/// ```
+///
+/// `git_sha` is `"unknown"` when the build had neither `GIT_SHA` nor a git checkout.
+pub async fn version_handler() -> GatewayResult<(StatusCode, Json<Value>)> {
+    let response = json!({
+        "version": VERSION,
+        "git_sha": GIT_SHA,
+    });
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_version_reports_wrapper_version() -> Result<(), Box<dyn std::error::Error>> {
+        let (status, Json(body)) = version_handler().await?;
+
+        assert_eq!(status, StatusCode::OK);
+        let version = body["version"].as_str().ok_or("missing version")?;
+        assert!(!version.is_empty());
+        assert!(!body["git_sha"].as_str().ok_or("missing git_sha")?.is_empty());
+        Ok(())
+    }
+}
@@ -21,9 +21,50 @@
 //! {"type": "item.completed", ...}
 //! {"type": "turn.completed", ...}
 //! ```
+//!
+//! A response whose JSON exceeds `GATEWAY_WEBSOCKET_MAX_EVENT_BYTES` (e.g. an
+//! item carrying a large diff) is split so no single message trips proxy
+//! frame limits: its JSON text is sent as ordered `response_chunk` messages
+//! sharing an `id`, followed by a `response_end`. Concatenating the chunks'
+//! `data` gives the original response.
+//!
+//! An exec that has to wait for an execution slot gets a `queue_position`
+//! message every couple of seconds (its position and, once an exec has
+//! finished, an estimated wait), then `task_started` when it gets its slot.
+//!
+//! Execs are held to the [`ApiKeyScope`] of the key that opened the
+//! connection, as for `POST /exec`, and each one counts against that key's
+//! `CODEX_DAILY_QUOTA`. Each exec writes `task_started` and
+//! `task_completed` audit records under that key and is counted in
+//! `/metrics`. Execs are rejected while the circuit breaker is open, and
+//! report their outcome to it. `persist`, `timeout_ms` and `max_lifetime_ms`
+//! work as for `POST /exec`: a turn that goes quiet or runs too long is
+//! interrupted and the client gets an `error` message.
 
 use crate::error::GatewayResult;
+use crate::handlers::exec::ExecStatus;
+use crate::handlers::exec::TurnLimits;
+use crate::handlers::exec::admit_exec;
+use crate::handlers::exec::apply_prompt_prefix;
+use crate::handlers::exec::cap_line_lengths;
+use crate::handlers::exec::check_persist;
+use crate::handlers::exec::check_sandbox_scope;
+use crate::handlers::exec::effective_sandbox_policy;
+use crate::handlers::exec::prompt_prefix;
+use crate::handlers::exec::record_breaker_outcome;
+use crate::handlers::exec::resolve_workdir;
+use crate::handlers::exec::status_error;
+use crate::handlers::exec::validate_model_selection;
+use crate::handlers::exec::validate_prompt;
+use crate::handlers::exec::validate_session_id;
+use crate::handlers::sessions::check_session_owner;
+use crate::metrics::ExecOutcome;
+use crate::middleware::ApiKeyId;
+use crate::middleware::ApiKeyScope;
+use crate::services::audit_log::AuditRecord;
+use crate::services::redaction;
 use crate::state::AppState;
+use axum::extract::Extension;
 use axum::extract::State;
 use axum::extract::WebSocketUpgrade;
 use axum::extract::ws::Message;
@@ -33,7 +74,9 @@ use codex_exec::event_processor_with_jsonl_output::EventProcessorWithJsonOutput;
 use codex_exec::exec_events::ThreadEvent;
 use codex_protocol::protocol::EventMsg;
 use codex_protocol::protocol::Op;
+use codex_protocol::protocol::TurnAbortReason;
 use codex_protocol::user_input::UserInput;
+use futures::Sink;
 use futures::SinkExt;
 use futures::StreamExt;
 use futures::stream::SplitSink;
@@ -42,12 +85,24 @@ use serde::Serialize;
 use serde_json::Value;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 use tokio::sync::Mutex;
+use tokio::sync::OwnedSemaphorePermit;
 use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::time::MissedTickBehavior;
 use tracing::debug;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
+use uuid::Uuid;
+
+/// Room left in each `response_chunk` for its type, id and index
+const CHUNK_OVERHEAD: usize = 128;
+
+/// How often a queued exec is told its queue position
+const QUEUE_POSITION_INTERVAL: Duration = Duration::from_secs(2);
 
 /// WebSocket request messages from client
 #[derive(Debug, Deserialize)]
@@ -62,10 +117,19 @@ enum WebSocketRequest {
         images: Vec<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         output_schema: Option<Value>,
-        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(alias = "working_dir", skip_serializing_if = "Option::is_none")]
         cwd: Option<PathBuf>,
         #[serde(skip_serializing_if = "Option::is_none")]
         model: Option<String>,
+        /// Keep the session rollout, as for `POST /exec`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        persist: Option<bool>,
+        /// Longest the turn may go without an event, as for `POST /exec`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timeout_ms: Option<u64>,
+        /// Longest the turn may run in total, as for `POST /exec`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_lifetime_ms: Option<u64>,
     },
     /// Interrupt current execution
     Interrupt { session_id: String },
@@ -79,12 +143,41 @@ enum WebSocketRequest {
 enum WebSocketResponse {
     /// JSONL event from exec (matches ThreadEvent from codex-exec)
     Event { event: Box<ThreadEvent> },
+    /// Incremental chunk of assistant text, sent as the model streams it
+    MessageDelta { delta: String },
+    /// Full assistant message, sent once the message is complete
+    Message { text: String },
     /// Acknowledgment of command
     Ack { message: String },
+    /// Place of a queued exec while it waits for an execution slot
+    QueuePosition {
+        /// 1-based; 1 means next in line
+        position: usize,
+        /// Rough wait from the mean execution time, absent until an exec has finished
+        #[serde(skip_serializing_if = "Option::is_none")]
+        estimated_wait_ms: Option<u64>,
+    },
+    /// A queued exec got its execution slot and is starting
+    TaskStarted,
     /// Error message
     Error { message: String },
     /// Pong response to ping
     Pong,
+    /// Part of the JSON text of a response too large for one message
+    ResponseChunk {
+        id: String,
+        index: usize,
+        data: String,
+    },
+    /// Last message of a chunked response, after all its chunks
+    ResponseEnd { id: String, chunks: usize },
+}
+
+/// API key that opened a connection, taken from the upgrade request
+#[derive(Debug, Clone)]
+struct Caller {
+    key_id: Option<String>,
+    scope: ApiKeyScope,
 }
 
 /// Handle WebSocket upgrade request
@@ -94,16 +187,22 @@ enum WebSocketResponse {
 pub async fn handle_websocket_upgrade(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    key_id: Option<Extension<ApiKeyId>>,
+    scope: Option<Extension<ApiKeyScope>>,
 ) -> GatewayResult<Response> {
     info!("WebSocket upgrade requested");
-    Ok(ws.on_upgrade(|socket| handle_websocket_connection(socket, state)))
+    let caller = Caller {
+        key_id: key_id.map(|Extension(ApiKeyId(id))| id),
+        scope: scope.map(|Extension(scope)| scope).unwrap_or_default(),
+    };
+    Ok(ws.on_upgrade(|socket| handle_websocket_connection(socket, state, caller)))
 }
 
 /// Handle WebSocket connection lifecycle
 ///
 /// Splits the WebSocket into sender and receiver, then enters the main
 /// message loop where it processes client requests and streams responses.
-async fn handle_websocket_connection(socket: WebSocket, state: AppState) {
+async fn handle_websocket_connection(socket: WebSocket, state: AppState, caller: Caller) {
     info!("WebSocket connection established");
 
     let (sender, mut receiver) = socket.split();
@@ -116,7 +215,11 @@ async fn handle_websocket_connection(socket: WebSocket, state: AppState) {
                 let text_str = text.to_string();
                 debug!("Received WebSocket text message: {}", text_str);
                 let sender_clone = Arc::clone(&sender);
-                if let Err(e) = handle_text_message(text_str, &state, sender_clone).await {
+                if let Err(e) = handle_text_message(text_str, &state, &caller, sender_clone).await {
+                    if e.downcast_ref::<ClientStalled>().is_some() {
+                        warn!("Dropping WebSocket connection: {e}");
+                        break;
+                    }
                     error!("Error handling WebSocket message: {e}");
                     let sender_clone = Arc::clone(&sender);
                     let _ = send_error(sender_clone, format!("Error: {e}")).await;
@@ -157,6 +260,7 @@ async fn handle_websocket_connection(socket: WebSocket, state: AppState) {
 async fn handle_text_message(
     text: String,
     state: &AppState,
+    caller: &Caller,
     sender: Arc<Mutex<SplitSink<WebSocket, Message>>>,
 ) -> anyhow::Result<()> {
     let request: WebSocketRequest = serde_json::from_str(&text)?;
@@ -169,7 +273,12 @@ async fn handle_text_message(
             output_schema,
             cwd,
             model,
+            persist,
+            timeout_ms,
+            max_lifetime_ms,
         } => {
+            let turn_limits =
+                TurnLimits::clamped(timeout_ms, max_lifetime_ms, &state.config().exec);
             handle_exec_request(
                 prompt,
                 session_id,
@@ -177,13 +286,16 @@ async fn handle_text_message(
                 output_schema,
                 cwd,
                 model,
+                persist,
+                turn_limits,
                 state,
+                caller,
                 sender,
             )
             .await
         }
         WebSocketRequest::Interrupt { session_id } => {
-            handle_interrupt_request(session_id, state, sender).await
+            handle_interrupt_request(session_id, state, caller, sender).await
         }
         WebSocketRequest::Ping => {
             let response = WebSocketResponse::Pong;
@@ -210,7 +322,10 @@ async fn handle_exec_request(
     output_schema: Option<Value>,
     cwd: Option<PathBuf>,
     model: Option<String>,
+    persist: Option<bool>,
+    turn_limits: TurnLimits,
     state: &AppState,
+    caller: &Caller,
     sender: Arc<Mutex<SplitSink<WebSocket, Message>>>,
 ) -> anyhow::Result<()> {
     info!(
@@ -219,18 +334,53 @@ async fn handle_exec_request(
         session_id
     );
 
+    validate_prompt(&prompt, &state.config().exec)?;
+    state.prompt_allowlist.check(&prompt)?;
+    if let Some(session_id) = &session_id {
+        validate_session_id(session_id)?;
+    }
+    validate_model_selection(model.as_deref(), None, &state.config().exec)?;
+    let cwd = resolve_workdir(cwd.as_deref(), &state.config().exec)?;
+    let sandbox_policy = effective_sandbox_policy(state, None)?;
+    check_sandbox_scope(&sandbox_policy, &caller.scope)?;
+    if let Some(session_id) = &session_id {
+        check_session_owner(state, session_id, caller.key_id.as_deref())?;
+    }
+    check_persist(state, session_id.as_deref(), persist).await?;
+    let persist = persist.unwrap_or(state.config().exec.persist_default);
+
+    // 0. Fail fast past CODEX_MAX_INFLIGHT, then wait for an execution slot,
+    // telling the client when it has to queue; the half-open probe is given
+    // back if the exec never gets to run
+    let _inflight = state.enter_inflight()?;
+    admit_exec(state, caller.key_id.as_deref())?;
+    let _permit = wait_for_permit(state, &sender)
+        .await
+        .inspect_err(|_| state.circuit_breaker.release_probe())?;
+    let started_at = Instant::now();
+
     // 1. Get or create conversation
-    let conversation_id = state
+    let (conversation_id, created) = state
         .codex_service
-        .get_or_create_conversation(session_id.as_deref())
-        .await?;
+        .get_or_create_conversation_with_provider(session_id.as_deref(), None)
+        .await
+        .inspect_err(|_| state.circuit_breaker.record_failure())?;
 
     debug!("Using conversation_id: {}", conversation_id);
+    let _active = state.active_execs.register(
+        session_id.as_deref(),
+        conversation_id,
+        &prompt,
+        caller.key_id.as_deref(),
+    );
 
     // 2. Get conversation from ConversationManager
     let conversation = {
         let manager = state.codex_service.conversation_manager().lock().await;
-        manager.get_conversation(conversation_id).await?
+        manager
+            .get_conversation(conversation_id)
+            .await
+            .inspect_err(|_| state.circuit_breaker.record_failure())?
     };
 
     // 3. Prepare UserInputs
@@ -247,33 +397,110 @@ async fn handle_exec_request(
         }
     }
 
-    // Add text prompt
-    user_inputs.push(UserInput::Text { text: prompt });
+    // Add text prompt, with the configured prompt prefix
+    let prefix = prompt_prefix(&state.config().exec, None);
+    user_inputs.push(UserInput::Text {
+        text: apply_prompt_prefix(prefix.as_deref(), &prompt),
+    });
 
     // 4. Get config for Op::UserTurn params
     let config = state.codex_service.codex_config();
     let cwd = cwd.unwrap_or_else(|| config.cwd.clone());
     let model = model.unwrap_or_else(|| config.model.clone());
 
-    // 5. Create channel for event streaming
-    let (tx, mut rx) = mpsc::unbounded_channel::<ThreadEvent>();
+    // 5. Create a bounded channel so a slow client applies backpressure to the
+    // event loop instead of letting responses pile up in memory
+    let (tx, mut rx) =
+        mpsc::channel::<WebSocketResponse>(state.config().websocket.channel_capacity);
 
-    // 6. Spawn background task to process events using REAL EventProcessorWithJsonOutput
+    // 6. Spawn background task to process events using REAL EventProcessorWithJsonOutput;
+    // it reports the turn's final status once the turn ends
     let conversation_clone = conversation.clone();
+    let max_line_bytes = state.config().exec.max_line_bytes;
+    let redact_output = state.config().exec.redact_output;
+    let (status_tx, status_rx) = oneshot::channel::<ExecStatus>();
     tokio::spawn(async move {
         let mut processor = EventProcessorWithJsonOutput::new(None);
+        let mut message_buffer = String::new();
+        let mut status = ExecStatus::Unknown;
+        let idle_timeout = turn_limits.timeout_ms.map(Duration::from_millis);
+        let deadline =
+            tokio::time::Instant::now() + Duration::from_millis(turn_limits.max_lifetime_ms);
 
-        loop {
-            match conversation_clone.next_event().await {
+        'events: loop {
+            // Stop at whichever comes first: the idle timeout or the end of the lifetime
+            let bound = match idle_timeout {
+                Some(idle_timeout) => deadline.min(tokio::time::Instant::now() + idle_timeout),
+                None => deadline,
+            };
+            let Ok(next) = tokio::time::timeout_at(bound, conversation_clone.next_event()).await
+            else {
+                status = if tokio::time::Instant::now() >= deadline {
+                    ExecStatus::LifetimeExceeded
+                } else {
+                    ExecStatus::Timeout
+                };
+                warn!("WebSocket: Exec stopped ({status}), interrupting turn");
+                if let Err(e) = conversation_clone.submit(Op::Interrupt).await {
+                    warn!("WebSocket: Failed to interrupt stopped turn: {e}");
+                }
+                if let Some(message) = status_error(
+                    status,
+                    &[],
+                    turn_limits.timeout_ms.unwrap_or_default(),
+                    turn_limits.max_lifetime_ms,
+                    None,
+                ) {
+                    let _ = tx.send(WebSocketResponse::Error { message }).await;
+                }
+                break;
+            };
+            match next {
                 Ok(event) => {
                     debug!("WebSocket: Processing event: {:?}", event.msg);
 
+                    // Deltas could split a secret, so with redaction on only
+                    // the full message is sent
+                    let message = match message_response(&event.msg, &mut message_buffer) {
+                        Some(WebSocketResponse::MessageDelta { .. }) if redact_output => None,
+                        Some(WebSocketResponse::Message { mut text }) if redact_output => {
+                            redaction::redact_in_place(&mut text);
+                            Some(WebSocketResponse::Message { text })
+                        }
+                        other => other,
+                    };
+
                     // Use REAL EventProcessorWithJsonOutput
                     let thread_events = processor.collect_thread_events(&event);
-                    for te in thread_events {
-                        if tx.send(te).is_err() {
-                            error!("WebSocket: Failed to send event to channel (receiver dropped)");
-                            break;
+                    for te in &thread_events {
+                        status = next_status(status, te);
+                    }
+                    if let EventMsg::TurnAborted(aborted) = &event.msg
+                        && matches!(aborted.reason, TurnAbortReason::Interrupted)
+                    {
+                        status = ExecStatus::Cancelled;
+                    }
+                    let responses =
+                        message
+                            .into_iter()
+                            .chain(thread_events.into_iter().map(|mut te| {
+                                cap_line_lengths(&mut te, max_line_bytes);
+                                if redact_output {
+                                    redaction::redact_event(&mut te);
+                                }
+                                WebSocketResponse::Event {
+                                    event: Box::new(te),
+                                }
+                            }));
+                    for response in responses {
+                        if tx.send(response).await.is_err() {
+                            // The client went away or stalled; stop the turn too
+                            warn!("WebSocket: Client gone, interrupting turn");
+                            if let Err(e) = conversation_clone.submit(Op::Interrupt).await {
+                                warn!("WebSocket: Failed to interrupt turn: {e}");
+                            }
+                            status = ExecStatus::Cancelled;
+                            break 'events;
                         }
                     }
 
@@ -292,6 +519,7 @@ async fn handle_exec_request(
                 }
             }
         }
+        let _ = status_tx.send(status);
     });
 
     // 7. Submit Op::UserTurn
@@ -299,68 +527,283 @@ async fn handle_exec_request(
         "WebSocket: Submitting user turn with model={}, cwd={:?}",
         model, cwd
     );
-    conversation
+    let audit = AuditRecord::started(
+        caller.key_id.as_deref(),
+        session_id.as_deref(),
+        &conversation_id.to_string(),
+        &prompt,
+        &sandbox_policy.to_string(),
+    );
+    state.audit_log.record(&audit);
+    state.metrics.record_started();
+    let submitted = conversation
         .submit(Op::UserTurn {
             items: user_inputs,
             cwd,
             approval_policy: config.approval_policy,
-            sandbox_policy: config.sandbox_policy.clone(),
+            sandbox_policy,
             model,
             effort: config.model_reasoning_effort,
             summary: config.model_reasoning_summary,
             final_output_json_schema: output_schema,
         })
-        .await?;
-
-    // 8. Stream events to client in real-time
-    while let Some(thread_event) = rx.recv().await {
-        let response = WebSocketResponse::Event {
-            event: Box::new(thread_event),
-        };
-        let json = serde_json::to_string(&response)?;
+        .await;
+    if let Err(e) = submitted {
+        state.circuit_breaker.record_failure();
+        state
+            .metrics
+            .record_finished(ExecOutcome::Failed, started_at.elapsed());
+        state
+            .audit_log
+            .record(&audit.completed(ExecStatus::Error.as_str(), started_at.elapsed()));
+        return Err(e.into());
+    }
 
-        let mut sender_lock = sender.lock().await;
-        if sender_lock.send(Message::Text(json.into())).await.is_err() {
-            warn!("WebSocket: Failed to send event to client (connection closed)");
-            break;
+    // 8. Stream events to client in real-time; a client that stops reading
+    // gets its turn interrupted by the event loop
+    let streamed = stream_to_client(
+        &mut rx,
+        &*sender,
+        state.config().timeouts.websocket_ping_interval,
+        state.config().websocket.send_timeout,
+        state.config().websocket.max_event_bytes,
+    )
+    .await;
+    let status = match &streamed {
+        Ok(()) => status_rx.await.unwrap_or(ExecStatus::Unknown),
+        Err(_) => ExecStatus::Cancelled,
+    };
+    record_breaker_outcome(state, status);
+    state
+        .metrics
+        .record_finished(status.outcome(), started_at.elapsed());
+    state
+        .audit_log
+        .record(&audit.completed(status.as_str(), started_at.elapsed()));
+    // An existing session's rollout also holds its earlier turns
+    if !persist && created {
+        if let Err(e) = state.codex_service.discard_rollout(conversation_id).await {
+            error!(
+                "WebSocket: Failed to discard rollout for conversation_id={conversation_id}: {e}"
+            );
         }
+    } else if redact_output
+        && let Err(e) = state.codex_service.redact_rollout(conversation_id).await
+    {
+        error!("WebSocket: Failed to redact rollout for conversation_id={conversation_id}: {e}");
     }
+    streamed?;
 
     info!(
-        "WebSocket: Exec completed for conversation_id={}",
-        conversation_id
+        "WebSocket: Exec completed for conversation_id={}, status={}",
+        conversation_id, status
     );
 
     Ok(())
 }
 
+/// Wait for an execution slot, telling the client its queue position
+async fn wait_for_permit(
+    state: &AppState,
+    sender: &Mutex<SplitSink<WebSocket, Message>>,
+) -> anyhow::Result<OwnedSemaphorePermit> {
+    if let Some(permit) = state.try_acquire_exec_permit() {
+        return Ok(permit);
+    }
+
+    let ticket = state.exec_queue.enter();
+    let acquire = state.acquire_exec_permit();
+    tokio::pin!(acquire);
+    let mut updates = tokio::time::interval(QUEUE_POSITION_INTERVAL);
+    let permit = loop {
+        tokio::select! {
+            permit = &mut acquire => break permit?,
+            _ = updates.tick() => {
+                let position = ticket.position();
+                let response = WebSocketResponse::QueuePosition {
+                    position,
+                    estimated_wait_ms: state
+                        .estimated_queue_wait(position)
+                        .map(|wait| u64::try_from(wait.as_millis()).unwrap_or(u64::MAX)),
+                };
+                let json = serde_json::to_string(&response)?;
+                sender.lock().await.send(Message::Text(json.into())).await?;
+            }
+        }
+    };
+    drop(ticket);
+    let json = serde_json::to_string(&WebSocketResponse::TaskStarted)?;
+    sender.lock().await.send(Message::Text(json.into())).await?;
+    Ok(permit)
+}
+
+/// Status of a turn after `event`, with the precedence `POST /exec` uses:
+/// an error beats a failed turn, which beats a completed one
+fn next_status(status: ExecStatus, event: &ThreadEvent) -> ExecStatus {
+    match (status, event) {
+        (_, ThreadEvent::Error(_)) | (ExecStatus::Error, _) => ExecStatus::Error,
+        (_, ThreadEvent::TurnFailed(_)) | (ExecStatus::Failed, _) => ExecStatus::Failed,
+        (_, ThreadEvent::TurnCompleted(_)) => ExecStatus::Completed,
+        (status, _) => status,
+    }
+}
+
+/// A client stopped reading and a write blocked for longer than the send timeout
+#[derive(Debug, thiserror::Error)]
+#[error("client stopped reading for {0:?}")]
+struct ClientStalled(Duration);
+
+/// Forward streamed responses to the client until the turn ends
+///
+/// Pings every `ping_interval` while the agent is quiet so idle-connection
+/// reapers (Cloud Run, proxies) keep the socket open. A write that takes
+/// longer than `send_timeout` fails with [`ClientStalled`]; dropping `rx`
+/// then unblocks the event loop, which interrupts the turn. Responses larger
+/// than `max_event_bytes` are sent in chunks (see [`encode_response`]).
+async fn stream_to_client<S>(
+    rx: &mut mpsc::Receiver<WebSocketResponse>,
+    sender: &Mutex<S>,
+    ping_interval: Duration,
+    send_timeout: Duration,
+    max_event_bytes: usize,
+) -> anyhow::Result<()>
+where
+    S: Sink<Message> + Unpin,
+    S::Error: std::fmt::Display,
+{
+    let mut heartbeat = tokio::time::interval(ping_interval);
+    heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    heartbeat.tick().await;
+
+    loop {
+        let messages = tokio::select! {
+            maybe_response = rx.recv() => {
+                let Some(response) = maybe_response else {
+                    return Ok(());
+                };
+                encode_response(&response, max_event_bytes)?
+                    .into_iter()
+                    .map(|text| Message::Text(text.into()))
+                    .collect()
+            }
+            _ = heartbeat.tick() => {
+                debug!("WebSocket: Sending heartbeat ping");
+                vec![Message::Ping(Default::default())]
+            }
+        };
+
+        for message in messages {
+            let send = async { sender.lock().await.send(message).await };
+            match tokio::time::timeout(send_timeout, send).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    warn!("WebSocket: Failed to send to client (connection closed): {e}");
+                    return Ok(());
+                }
+                Err(_) => return Err(ClientStalled(send_timeout).into()),
+            }
+        }
+    }
+}
+
+/// Serialize `response` into the text messages that carry it
+///
+/// Returns the JSON as is when it fits in `max_event_bytes`. Larger
+/// responses become `response_chunk` messages of at most `max_event_bytes`
+/// each (the chunk text counted as it is escaped inside `data`), then a
+/// `response_end`.
+fn encode_response(
+    response: &WebSocketResponse,
+    max_event_bytes: usize,
+) -> serde_json::Result<Vec<String>> {
+    let json = serde_json::to_string(response)?;
+    if json.len() <= max_event_bytes {
+        return Ok(vec![json]);
+    }
+
+    let budget = max_event_bytes.saturating_sub(CHUNK_OVERHEAD).max(1);
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut size = 0;
+    for (i, c) in json.char_indices() {
+        let cost = if matches!(c, '"' | '\\') {
+            2
+        } else {
+            c.len_utf8()
+        };
+        if size + cost > budget && i > start {
+            pieces.push(&json[start..i]);
+            start = i;
+            size = 0;
+        }
+        size += cost;
+    }
+    pieces.push(&json[start..]);
+
+    let id = Uuid::new_v4().to_string();
+    debug!(
+        "WebSocket: Splitting {} byte response into {} chunks",
+        json.len(),
+        pieces.len()
+    );
+    let mut messages = Vec::with_capacity(pieces.len() + 1);
+    for (index, data) in pieces.iter().enumerate() {
+        messages.push(serde_json::to_string(&WebSocketResponse::ResponseChunk {
+            id: id.clone(),
+            index,
+            data: (*data).to_string(),
+        })?);
+    }
+    messages.push(serde_json::to_string(&WebSocketResponse::ResponseEnd {
+        id,
+        chunks: pieces.len(),
+    })?);
+    Ok(messages)
+}
+
+/// Map assistant text events to streaming responses
+///
+/// Deltas are forwarded as they arrive and accumulated in `buffer`; when the
+/// agent finalizes the message the accumulated text is sent as one
+/// `message`, falling back to the event's own text if no deltas streamed.
+fn message_response(msg: &EventMsg, buffer: &mut String) -> Option<WebSocketResponse> {
+    match msg {
+        EventMsg::AgentMessageDelta(delta_event) => {
+            buffer.push_str(&delta_event.delta);
+            Some(WebSocketResponse::MessageDelta {
+                delta: delta_event.delta.clone(),
+            })
+        }
+        EventMsg::AgentMessage(message_event) => {
+            let text = if buffer.is_empty() {
+                message_event.message.clone()
+            } else {
+                std::mem::take(buffer)
+            };
+            Some(WebSocketResponse::Message { text })
+        }
+        _ => None,
+    }
+}
+
 /// Handle interrupt request via WebSocket
 ///
 /// Submits Op::Interrupt to the conversation to stop execution.
 async fn handle_interrupt_request(
     session_id: String,
     state: &AppState,
+    caller: &Caller,
     sender: Arc<Mutex<SplitSink<WebSocket, Message>>>,
 ) -> anyhow::Result<()> {
     info!("WebSocket: Interrupt requested for session: {}", session_id);
+    check_session_owner(state, &session_id, caller.key_id.as_deref())?;
 
-    // Get conversation ID from session ID
-    let conversation_id = {
-        let conversations = state.codex_service.active_conversations().lock().await;
-        conversations
-            .get(&session_id)
-            .copied()
-            .ok_or_else(|| anyhow::anyhow!("Session not found: {session_id}"))?
-    };
-
-    // Get conversation
-    let conversation = {
-        let manager = state.codex_service.conversation_manager().lock().await;
-        manager.get_conversation(conversation_id).await?
-    };
-
-    // Submit interrupt
-    conversation.submit(Op::Interrupt).await?;
+    // Submit interrupt to the conversation bound to this session
+    state
+        .codex_service
+        .interrupt_session(&session_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Session not found: {session_id}"))?;
 
     // Send acknowledgment
     let response = WebSocketResponse::Ack {
@@ -448,4 +891,189 @@ mod tests {
         assert!(json.contains("\"type\":\"ack\""));
         assert!(json.contains("\"message\":\"OK\""));
     }
+
+    #[test]
+    fn test_next_status_matches_exec_precedence() {
+        use codex_exec::exec_events::ThreadErrorEvent;
+        use codex_exec::exec_events::TurnCompletedEvent;
+        use codex_exec::exec_events::TurnFailedEvent;
+
+        let error = ThreadErrorEvent {
+            message: "boom".to_string(),
+        };
+        let completed = ThreadEvent::TurnCompleted(TurnCompletedEvent {
+            usage: Default::default(),
+        });
+        let failed = ThreadEvent::TurnFailed(TurnFailedEvent {
+            error: error.clone(),
+        });
+
+        let fold = |events: &[&ThreadEvent]| {
+            events.iter().fold(ExecStatus::Unknown, |status, event| {
+                next_status(status, event)
+            })
+        };
+        assert_eq!(fold(&[]), ExecStatus::Unknown);
+        assert_eq!(fold(&[&completed]), ExecStatus::Completed);
+        assert_eq!(fold(&[&failed, &completed]), ExecStatus::Failed);
+        assert_eq!(
+            fold(&[&ThreadEvent::Error(error), &completed]),
+            ExecStatus::Error
+        );
+    }
+
+    #[test]
+    fn test_message_response_accumulates_deltas() {
+        use codex_protocol::protocol::AgentMessageDeltaEvent;
+        use codex_protocol::protocol::AgentMessageEvent;
+
+        let mut buffer = String::new();
+        let mut streamed = String::new();
+
+        for chunk in ["Hel", "lo, ", "world"] {
+            let msg = EventMsg::AgentMessageDelta(AgentMessageDeltaEvent {
+                delta: chunk.to_string(),
+            });
+            match message_response(&msg, &mut buffer) {
+                Some(WebSocketResponse::MessageDelta { delta }) => streamed.push_str(&delta),
+                other => panic!("Expected MessageDelta, got {other:?}"),
+            }
+        }
+
+        let done = EventMsg::AgentMessage(AgentMessageEvent {
+            message: "Hello, world".to_string(),
+        });
+        match message_response(&done, &mut buffer) {
+            Some(WebSocketResponse::Message { text }) => assert_eq!(text, "Hello, world"),
+            other => panic!("Expected Message, got {other:?}"),
+        }
+
+        assert_eq!(streamed, "Hello, world");
+        assert!(buffer.is_empty());
+    }
+
+    /// Sink whose writes never complete, like a client that stopped reading
+    struct StalledSink;
+
+    impl Sink<Message> for StalledSink {
+        type Error = axum::Error;
+
+        fn poll_ready(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Pending
+        }
+
+        fn start_send(self: std::pin::Pin<&mut Self>, _item: Message) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Pending
+        }
+
+        fn poll_close(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stalled_client_is_dropped_and_producer_bounded() {
+        let (tx, mut rx) = mpsc::channel::<WebSocketResponse>(2);
+        let producer = tokio::spawn(async move {
+            let mut sent = 0usize;
+            loop {
+                let response = WebSocketResponse::MessageDelta {
+                    delta: "x".to_string(),
+                };
+                if tx.send(response).await.is_err() {
+                    return sent;
+                }
+                sent += 1;
+            }
+        });
+
+        let sender = Mutex::new(StalledSink);
+        let result = stream_to_client(
+            &mut rx,
+            &sender,
+            Duration::from_secs(60),
+            Duration::from_millis(50),
+            1024 * 1024,
+        )
+        .await;
+
+        let err = result.unwrap_err();
+        assert!(err.downcast_ref::<ClientStalled>().is_some());
+
+        // Closing the receiver unblocks the producer, which only ever got
+        // ahead of the stalled client by the channel capacity
+        drop(rx);
+        let sent = producer.await.unwrap();
+        assert!(
+            sent <= 3,
+            "producer sent {sent} responses past a stalled client"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_oversized_response_is_chunked_and_reassembles()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let max_event_bytes = 512;
+        let (tx, mut rx) = mpsc::channel::<WebSocketResponse>(4);
+        let large = WebSocketResponse::Message {
+            text: "diff --git \"a\" \\ é\n".repeat(200),
+        };
+        let original = serde_json::to_string(&large)?;
+        tx.send(large).await?;
+        tx.send(WebSocketResponse::Pong).await?;
+        drop(tx);
+
+        let (sink, sent) = futures::channel::mpsc::unbounded::<Message>();
+        stream_to_client(
+            &mut rx,
+            &Mutex::new(sink),
+            Duration::from_secs(60),
+            Duration::from_secs(1),
+            max_event_bytes,
+        )
+        .await?;
+
+        let mut messages = Vec::new();
+        for message in sent.collect::<Vec<_>>().await {
+            let Message::Text(text) = message else {
+                return Err(format!("unexpected message {message:?}").into());
+            };
+            assert!(
+                text.len() <= max_event_bytes,
+                "message of {} bytes",
+                text.len()
+            );
+            messages.push(serde_json::from_str::<Value>(&text)?);
+        }
+
+        let (pong, messages) = messages.split_last().ok_or("no messages")?;
+        assert_eq!(pong["type"], "pong");
+        let (end, chunks) = messages.split_last().ok_or("no chunks")?;
+        assert_eq!(end["type"], "response_end");
+        assert_eq!(end["chunks"], chunks.len());
+        assert!(chunks.len() > 1);
+
+        let mut reassembled = String::new();
+        for (index, chunk) in chunks.iter().enumerate() {
+            assert_eq!(chunk["type"], "response_chunk");
+            assert_eq!(chunk["id"], end["id"]);
+            assert_eq!(chunk["index"], index);
+            reassembled.push_str(chunk["data"].as_str().ok_or("data is not a string")?);
+        }
+        assert_eq!(reassembled, original);
+        Ok(())
+    }
 }
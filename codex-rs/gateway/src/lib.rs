@@ -3,14 +3,21 @@
 //! This crate provides a cloud-native gateway that acts as a complete wrapper
 //! for all Codex CLI services, maintaining 100% compatibility with the existing protocol.
 
+#[cfg(feature = "client")]
+pub mod client;
 pub mod config;
 pub mod error;
 pub mod handlers;
+pub mod listener;
+pub mod metrics;
 pub mod middleware;
 pub mod router;
 pub mod services;
 pub mod state;
+pub mod telemetry;
 
+pub use config::CorsConfig;
+pub use config::ExecConfig;
 pub use config::GatewayConfig;
 pub use config::TimeoutConfig;
 pub use config::WebSocketConfig;
@@ -0,0 +1,380 @@
+//! Listening sockets
+//!
+//! The gateway listens on TCP by default. For sidecar deployments it can
+//! listen on a Unix domain socket instead, named by `CODEX_LISTEN_UDS`.
+//!
+//! When not behind a TLS-terminating load balancer it can serve HTTPS
+//! itself: with `CODEX_TLS_CERT` and `CODEX_TLS_KEY` both set, TCP
+//! connections go through a [`TlsListener`]. On Unix, `SIGHUP` reloads the
+//! certificate and key from the same paths without dropping connections.
+
+use crate::error::GatewayError;
+use crate::error::GatewayResult;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::pki_types::CertificateDer;
+use tokio_rustls::rustls::pki_types::PrivateKeyDer;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::server::TlsStream;
+use tracing::debug;
+use tracing::info;
+use tracing::warn;
+
+/// Environment variable naming the Unix socket to listen on instead of TCP
+pub const LISTEN_UDS_ENV: &str = "CODEX_LISTEN_UDS";
+
+/// Environment variable with the PEM certificate chain for direct HTTPS
+pub const TLS_CERT_ENV: &str = "CODEX_TLS_CERT";
+
+/// Environment variable with the PEM private key for direct HTTPS
+pub const TLS_KEY_ENV: &str = "CODEX_TLS_KEY";
+
+/// How long a client gets to finish the TLS handshake
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Handshaken connections waiting for axum to pick them up
+const TLS_ACCEPT_BACKLOG: usize = 64;
+
+/// Bind a Unix domain socket at `path`
+///
+/// A socket left behind by a previous run is replaced; any other file at
+/// `path` is an error rather than being deleted.
+#[cfg(unix)]
+pub fn bind_unix(path: &Path) -> GatewayResult<UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            std::fs::remove_file(path).map_err(|e| {
+                GatewayError::ServerStart(format!(
+                    "Failed to remove stale socket {}: {e}",
+                    path.display()
+                ))
+            })?;
+        }
+        Ok(_) => {
+            return Err(GatewayError::ServerStart(format!(
+                "{} exists and is not a socket",
+                path.display()
+            )));
+        }
+        Err(_) => {}
+    }
+
+    UnixListener::bind(path).map_err(|e| {
+        GatewayError::ServerStart(format!("Failed to bind to {}: {e}", path.display()))
+    })
+}
+
+/// Certificate and key files for direct HTTPS
+#[derive(Debug, Clone)]
+pub struct TlsPaths {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl TlsPaths {
+    /// Read `CODEX_TLS_CERT` and `CODEX_TLS_KEY`
+    ///
+    /// Returns `None` when neither is set, so the gateway serves plain HTTP;
+    /// setting only one of them is a configuration error.
+    pub fn from_env() -> GatewayResult<Option<Self>> {
+        let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+        match (var(TLS_CERT_ENV), var(TLS_KEY_ENV)) {
+            (Some(cert), Some(key)) => Ok(Some(Self {
+                cert: PathBuf::from(cert),
+                key: PathBuf::from(key),
+            })),
+            (None, None) => Ok(None),
+            _ => Err(GatewayError::Config(format!(
+                "{TLS_CERT_ENV} and {TLS_KEY_ENV} must be set together"
+            ))),
+        }
+    }
+
+    /// Load the certificate chain and key into a server config offering
+    /// HTTP/2 and HTTP/1.1
+    pub fn load(&self) -> GatewayResult<ServerConfig> {
+        let certs = CertificateDer::pem_file_iter(&self.cert)
+            .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+            .map_err(|e| {
+                GatewayError::Config(format!(
+                    "Failed to read TLS certificate {}: {e}",
+                    self.cert.display()
+                ))
+            })?;
+        if certs.is_empty() {
+            return Err(GatewayError::Config(format!(
+                "No certificate found in {}",
+                self.cert.display()
+            )));
+        }
+        let key = PrivateKeyDer::from_pem_file(&self.key).map_err(|e| {
+            GatewayError::Config(format!(
+                "Failed to read TLS key {}: {e}",
+                self.key.display()
+            ))
+        })?;
+
+        let provider = Arc::new(tokio_rustls::rustls::crypto::ring::default_provider());
+        let mut config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| GatewayError::Config(format!("Unsupported TLS settings: {e}")))?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|e| GatewayError::Config(format!("Invalid TLS certificate or key: {e}")))?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(config)
+    }
+}
+
+/// TLS acceptor whose certificate can be replaced while serving
+#[derive(Clone)]
+pub struct ReloadableTlsAcceptor {
+    paths: TlsPaths,
+    current: Arc<RwLock<TlsAcceptor>>,
+}
+
+impl ReloadableTlsAcceptor {
+    /// Load the certificate and key from `paths`
+    pub fn new(paths: TlsPaths) -> GatewayResult<Self> {
+        let acceptor = TlsAcceptor::from(Arc::new(paths.load()?));
+        Ok(Self {
+            paths,
+            current: Arc::new(RwLock::new(acceptor)),
+        })
+    }
+
+    /// Load the files again; new connections use the result, and the old
+    /// certificate stays in use if loading fails
+    pub fn reload(&self) -> GatewayResult<()> {
+        let acceptor = TlsAcceptor::from(Arc::new(self.paths.load()?));
+        *self
+            .current
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = acceptor;
+        Ok(())
+    }
+
+    /// Reload whenever the process receives `SIGHUP`
+    #[cfg(unix)]
+    pub fn reload_on_sighup(&self) -> GatewayResult<()> {
+        use tokio::signal::unix::SignalKind;
+        use tokio::signal::unix::signal;
+
+        let mut hangups = signal(SignalKind::hangup())
+            .map_err(|e| GatewayError::ServerStart(format!("Failed to watch SIGHUP: {e}")))?;
+        let acceptor = self.clone();
+        tokio::spawn(async move {
+            while hangups.recv().await.is_some() {
+                match acceptor.reload() {
+                    Ok(()) => info!(
+                        "Reloaded TLS certificate from {}",
+                        acceptor.paths.cert.display()
+                    ),
+                    Err(e) => warn!("Keeping the current TLS certificate: {e}"),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        self.current
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone()
+    }
+}
+
+/// TCP listener that hands axum connections after their TLS handshake
+///
+/// Handshakes run in their own tasks, bounded by a timeout, so a slow or
+/// broken client cannot hold up other connections. Failed handshakes are
+/// logged and dropped.
+pub struct TlsListener {
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    /// Start accepting TLS connections on `listener`
+    pub fn new(listener: TcpListener, acceptor: ReloadableTlsAcceptor) -> GatewayResult<Self> {
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| GatewayError::ServerStart(format!("Failed to read local address: {e}")))?;
+        let (tx, connections) = mpsc::channel(TLS_ACCEPT_BACKLOG);
+
+        tokio::spawn(async move {
+            while !tx.is_closed() {
+                let (stream, addr) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        warn!("Failed to accept TCP connection: {e}");
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let acceptor = acceptor.acceptor();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await
+                    {
+                        Ok(Ok(stream)) => {
+                            let _ = tx.send((stream, addr)).await;
+                        }
+                        Ok(Err(e)) => debug!("TLS handshake with {addr} failed: {e}"),
+                        Err(_) => debug!("TLS handshake with {addr} timed out"),
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            connections,
+            local_addr,
+        })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The accept loop only stops once this listener is gone
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::GatewayConfig;
+    use crate::router::create_router;
+    use crate::state::AppState;
+    use tokio::io::AsyncReadExt;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::UnixStream;
+
+    #[tokio::test]
+    async fn test_serves_healthz_over_unix_socket() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("gateway.sock");
+        let state = AppState::new(GatewayConfig::default()).await?;
+        let app = create_router(state).await?;
+
+        let listener = bind_unix(&path)?;
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut stream = UnixStream::connect(&path).await?;
+        stream
+            .write_all(b"GET /healthz HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+
+        assert!(response.starts_with("HTTP/1.1 "), "{response}");
+        assert!(response.contains("\"codex_home\""), "{response}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bind_unix_refuses_to_replace_regular_file()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("not-a-socket");
+        std::fs::write(&path, b"data")?;
+
+        let result = bind_unix(&path);
+
+        assert!(matches!(result, Err(GatewayError::ServerStart(_))));
+        assert!(path.exists());
+        Ok(())
+    }
+
+    fn fixture_paths() -> TlsPaths {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls");
+        TlsPaths {
+            cert: dir.join("cert.pem"),
+            key: dir.join("key.pem"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_serves_healthz_over_tls() -> Result<(), Box<dyn std::error::Error>> {
+        use tokio_rustls::TlsConnector;
+        use tokio_rustls::rustls::ClientConfig;
+        use tokio_rustls::rustls::RootCertStore;
+        use tokio_rustls::rustls::pki_types::ServerName;
+
+        let paths = fixture_paths();
+        let state = AppState::new(GatewayConfig::default()).await?;
+        let app = create_router(state).await?;
+        let acceptor = ReloadableTlsAcceptor::new(paths.clone())?;
+        let listener = TlsListener::new(TcpListener::bind("127.0.0.1:0").await?, acceptor)?;
+        let addr = axum::serve::Listener::local_addr(&listener)?;
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        // Trust only the self-signed test certificate
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(&paths.cert)? {
+            roots.add(cert?)?;
+        }
+        let provider = Arc::new(tokio_rustls::rustls::crypto::ring::default_provider());
+        let config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let tcp = TcpStream::connect(addr).await?;
+        let mut stream = TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost")?, tcp)
+            .await?;
+
+        stream
+            .write_all(b"GET /healthz HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+
+        assert!(response.starts_with("HTTP/1.1 "), "{response}");
+        assert!(response.contains("\"codex_home\""), "{response}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_tls_paths_need_cert_and_key() -> Result<(), Box<dyn std::error::Error>> {
+        let paths = fixture_paths();
+        assert!(paths.load().is_ok());
+
+        let missing_key = TlsPaths {
+            key: paths.cert.with_file_name("missing.pem"),
+            ..paths
+        };
+        let err = missing_key
+            .load()
+            .err()
+            .ok_or("expected a missing key to fail")?;
+        assert!(matches!(err, GatewayError::Config(_)));
+        Ok(())
+    }
+}
@@ -1,19 +1,33 @@
 //! Main entry point for the Codex Gateway server
 
+use axum::Router;
 use codex_gateway::config::BodyLimitsConfig;
+use codex_gateway::config::CorsConfig;
+use codex_gateway::config::ExecConfig;
 use codex_gateway::config::GatewayConfig;
+use codex_gateway::config::debug_endpoints_from_env;
 use codex_gateway::error::GatewayError;
 use codex_gateway::error::GatewayResult;
+use codex_gateway::listener;
+use codex_gateway::listener::ReloadableTlsAcceptor;
+use codex_gateway::listener::TlsListener;
+use codex_gateway::listener::TlsPaths;
 use codex_gateway::router::create_router;
+use codex_gateway::services::codex_service::ClientInfo;
+use codex_gateway::services::provider_credentials;
 use codex_gateway::state::AppState;
+use codex_gateway::telemetry;
+use codex_gateway::telemetry::TelemetryGuard;
 use std::env;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::process;
 use tokio::net::TcpListener;
 use tokio::signal;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
+use tracing_subscriber::Registry;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
@@ -28,8 +42,9 @@ async fn main() {
 
 /// Main server execution function
 async fn run() -> GatewayResult<()> {
-    // Initialize tracing subscriber with structured logging
-    init_tracing()?;
+    // Initialize tracing subscriber with structured logging (and OTLP export
+    // when configured); the guard flushes pending spans on shutdown
+    let _telemetry = init_tracing()?;
 
     info!("Starting Codex Gateway server");
 
@@ -37,12 +52,32 @@ async fn run() -> GatewayResult<()> {
     let config = load_config()?;
     info!("Configuration loaded: {:?}", config);
 
+    // Identify this deployment to codex-core before any conversation starts
+    ClientInfo::from_env().install();
+
     // Create application state
     let state = AppState::new(config.clone()).await?;
 
+    // Log which model providers can authenticate; optionally refuse to start
+    provider_credentials::report(
+        &provider_credentials::from_env(state.codex_service.codex_config()),
+        &provider_credentials::well_known_keys_set(|key| env::var(key).ok()),
+        config.exec.require_provider_credentials,
+    )?;
+
     // Create router with all routes and middleware (now async)
     let app = create_router(state).await?;
 
+    // Listen on a Unix socket instead of TCP when CODEX_LISTEN_UDS is set
+    if let Some(path) = env::var_os(listener::LISTEN_UDS_ENV).filter(|p| !p.is_empty()) {
+        return serve_unix(app, PathBuf::from(path)).await;
+    }
+
+    // Serve HTTPS directly when CODEX_TLS_CERT and CODEX_TLS_KEY are set
+    let tls = TlsPaths::from_env()?
+        .map(ReloadableTlsAcceptor::new)
+        .transpose()?;
+
     // Parse server address from config
     let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
     info!("Server will bind to: {}", addr);
@@ -52,6 +87,19 @@ async fn run() -> GatewayResult<()> {
         .await
         .map_err(|e| GatewayError::ServerStart(format!("Failed to bind to {addr}: {e}")))?;
 
+    if let Some(acceptor) = tls {
+        #[cfg(unix)]
+        acceptor.reload_on_sighup()?;
+        info!("Server listening on {} with TLS", addr);
+        axum::serve(TlsListener::new(listener, acceptor)?, app)
+            .with_graceful_shutdown(shutdown_signal())
+            .await
+            .map_err(|e| GatewayError::ServerStart(format!("Server error: {e}")))?;
+
+        info!("Server shutdown complete");
+        return Ok(());
+    }
+
     info!("Server listening on {}", addr);
 
     // Start server with graceful shutdown
@@ -64,9 +112,43 @@ async fn run() -> GatewayResult<()> {
     Ok(())
 }
 
+/// Serve `app` on the Unix socket at `path`, removing the socket on shutdown
+#[cfg(unix)]
+async fn serve_unix(app: Router, path: PathBuf) -> GatewayResult<()> {
+    let listener = listener::bind_unix(&path)?;
+    info!("Server listening on unix socket {}", path.display());
+
+    let result = axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .map_err(|e| GatewayError::ServerStart(format!("Server error: {e}")));
+    if let Err(e) = std::fs::remove_file(&path) {
+        warn!("Failed to remove socket {}: {}", path.display(), e);
+    }
+
+    info!("Server shutdown complete");
+    result
+}
+
+/// Unix sockets are not available on this platform
+#[cfg(not(unix))]
+async fn serve_unix(_app: Router, path: PathBuf) -> GatewayResult<()> {
+    Err(GatewayError::ServerStart(format!(
+        "{} is set to {} but Unix sockets are not supported on this platform",
+        listener::LISTEN_UDS_ENV,
+        path.display()
+    )))
+}
+
 /// Initialize structured logging with tracing subscriber
-fn init_tracing() -> GatewayResult<()> {
+///
+/// Adds an OTLP exporter when `OTEL_EXPORTER_OTLP_ENDPOINT` is set and the
+/// gateway was built with the `otel` feature.
+fn init_tracing() -> GatewayResult<TelemetryGuard> {
+    let (otlp, guard) = telemetry::otlp_layer::<Registry>()?;
+
     tracing_subscriber::registry()
+        .with(otlp)
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "codex_gateway=info,tower_http=debug,axum=debug".into()),
@@ -75,7 +157,15 @@ fn init_tracing() -> GatewayResult<()> {
         .init();
 
     info!("Tracing initialized");
-    Ok(())
+    if guard.is_enabled() {
+        info!("OpenTelemetry trace export enabled");
+    } else if env::var(telemetry::OTLP_ENDPOINT_ENV).is_ok_and(|v| !v.is_empty()) {
+        warn!(
+            "{} is set but the gateway was built without the otel feature; traces are not exported",
+            telemetry::OTLP_ENDPOINT_ENV
+        );
+    }
+    Ok(guard)
 }
 
 /// Load configuration from environment variables
@@ -103,26 +193,105 @@ fn load_config() -> GatewayResult<GatewayConfig> {
         }
     }
 
+    if let Ok(interval_str) = env::var("GATEWAY_WEBSOCKET_PING_INTERVAL_SECS") {
+        match interval_str.parse::<u64>() {
+            Ok(secs) if secs > 0 => {
+                config.timeouts.websocket_ping_interval = std::time::Duration::from_secs(secs);
+            }
+            _ => warn!(
+                "Invalid GATEWAY_WEBSOCKET_PING_INTERVAL_SECS value: {}, using default",
+                interval_str
+            ),
+        }
+    }
+
+    if let Ok(capacity_str) = env::var("GATEWAY_WEBSOCKET_CHANNEL_CAPACITY") {
+        match capacity_str.parse::<usize>() {
+            Ok(capacity) if capacity > 0 => config.websocket.channel_capacity = capacity,
+            _ => warn!(
+                "Invalid GATEWAY_WEBSOCKET_CHANNEL_CAPACITY value: {}, using default",
+                capacity_str
+            ),
+        }
+    }
+
+    if let Ok(timeout_str) = env::var("GATEWAY_WEBSOCKET_SEND_TIMEOUT_SECS") {
+        match timeout_str.parse::<u64>() {
+            Ok(secs) if secs > 0 => {
+                config.websocket.send_timeout = std::time::Duration::from_secs(secs);
+            }
+            _ => warn!(
+                "Invalid GATEWAY_WEBSOCKET_SEND_TIMEOUT_SECS value: {}, using default",
+                timeout_str
+            ),
+        }
+    }
+
+    if let Ok(bytes_str) = env::var("GATEWAY_WEBSOCKET_MAX_EVENT_BYTES") {
+        match bytes_str.parse::<usize>() {
+            Ok(bytes) if bytes > 0 => config.websocket.max_event_bytes = bytes,
+            _ => warn!(
+                "Invalid GATEWAY_WEBSOCKET_MAX_EVENT_BYTES value: {}, using default",
+                bytes_str
+            ),
+        }
+    }
+
     // Body size limits are fully implemented in router middleware with endpoint-specific limits
     // Configuration is handled via BodyLimitsConfig and environment variables:
     // - GATEWAY_BODY_LIMIT_DEFAULT (default: 2MB)
     // - GATEWAY_BODY_LIMIT_JSONRPC (default: 1MB)
     // - GATEWAY_BODY_LIMIT_WEBHOOK (default: 10MB)
     // - GATEWAY_BODY_LIMIT_HEALTH (default: 1KB)
+    // - CODEX_MAX_BODY_BYTES (POST /exec, default: 1MB)
     // - GATEWAY_BODY_LIMITS_ENABLED (default: true)
 
     // Override body limits configuration from environment if available
     config.body_limits = BodyLimitsConfig::from_env();
 
     info!(
-        "Body size limits configured: default={}KB, jsonrpc={}KB, webhook={}KB, health={}KB, enabled={}",
+        "Body size limits configured: default={}KB, jsonrpc={}KB, webhook={}KB, health={}KB, exec={}KB, enabled={}",
         config.body_limits.default_limit / 1024,
         config.body_limits.jsonrpc_limit / 1024,
         config.body_limits.webhook_limit / 1024,
         config.body_limits.health_limit / 1024,
+        config.body_limits.exec_limit / 1024,
         config.body_limits.enabled
     );
 
+    // Override exec limits from environment:
+    // - CODEX_MAX_CONCURRENCY (default: 4)
+    // - CODEX_MAX_PROMPT_BYTES (default: 100000)
+    // - CODEX_MAX_TIMEOUT_MS (default: 600000)
+    // - CODEX_MAX_LIFETIME_MS (default: 3600000)
+    config.exec = ExecConfig::from_env();
+    // CODEX_DEFAULT_SANDBOX_POLICY: an invalid value stops startup
+    config.exec.default_sandbox_mode = ExecConfig::default_sandbox_mode_from_env()?;
+    if let Some(mode) = config.exec.default_sandbox_mode {
+        info!(
+            "Default sandbox mode for requests without sandbox_mode: {}",
+            mode
+        );
+    }
+    info!(
+        "Exec limits configured: max_concurrency={}, max_prompt_bytes={}, max_timeout_ms={}",
+        config.exec.max_concurrency, config.exec.max_prompt_bytes, config.exec.max_timeout_ms
+    );
+
+    // Browser origins allowed by CODEX_CORS_ALLOWED_ORIGINS (default: none, same-origin only)
+    config.cors = CorsConfig::from_env();
+    if config.cors.allowed_origins.is_empty() {
+        info!("CORS disabled: CODEX_CORS_ALLOWED_ORIGINS not set");
+    } else {
+        info!("CORS allowed origins: {:?}", config.cors.allowed_origins);
+    }
+
+    // GET /debug/config only with CODEX_ENABLE_DEBUG_ENDPOINTS=1
+    config.debug_endpoints = debug_endpoints_from_env();
+    if config.debug_endpoints {
+        warn!("Debug endpoints enabled (CODEX_ENABLE_DEBUG_ENDPOINTS)");
+    }
+
     Ok(config)
 }
 
@@ -188,4 +357,18 @@ mod tests {
             env::remove_var("REQUEST_TIMEOUT_SECS");
         }
     }
+
+    #[test]
+    fn test_load_config_websocket_ping_interval() {
+        unsafe {
+            env::set_var("GATEWAY_WEBSOCKET_PING_INTERVAL_SECS", "15");
+        }
+
+        let config = load_config().unwrap();
+        assert_eq!(config.timeouts.websocket_ping_interval.as_secs(), 15);
+
+        unsafe {
+            env::remove_var("GATEWAY_WEBSOCKET_PING_INTERVAL_SECS");
+        }
+    }
 }
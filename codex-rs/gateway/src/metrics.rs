@@ -0,0 +1,161 @@
+//! Exec metrics for the Codex Gateway
+//!
+//! Lock-free counters and a duration histogram rendered in the Prometheus
+//! text exposition format by the `/metrics` handler.
+
+use std::fmt::Write;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+/// Histogram bucket upper bounds for exec duration, in milliseconds
+const DURATION_BUCKETS_MS: [u64; 10] = [
+    100, 500, 1_000, 5_000, 10_000, 30_000, 60_000, 120_000, 300_000, 600_000,
+];
+
+/// How an exec finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecOutcome {
+    /// Turn completed normally
+    Completed,
+    /// Turn failed or the agent reported an error
+    Failed,
+    /// Turn exceeded its `timeout_ms`
+    TimedOut,
+    /// Turn was interrupted through the cancel endpoint
+    Cancelled,
+}
+
+/// Exec counters and execution time histogram
+#[derive(Debug, Default)]
+pub struct ExecMetrics {
+    started: AtomicU64,
+    completed: AtomicU64,
+    failed: AtomicU64,
+    timed_out: AtomicU64,
+    cancelled: AtomicU64,
+    /// Cumulative bucket counts, one per entry in `DURATION_BUCKETS_MS`
+    duration_buckets: [AtomicU64; DURATION_BUCKETS_MS.len()],
+    duration_sum_ms: AtomicU64,
+    duration_count: AtomicU64,
+}
+
+impl ExecMetrics {
+    /// Create an empty metrics registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that an exec was accepted
+    pub fn record_started(&self) {
+        self.started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Mean execution time of finished execs, `None` before the first one
+    pub fn average_execution_time(&self) -> Option<Duration> {
+        let count = self.duration_count.load(Ordering::Relaxed);
+        (count > 0)
+            .then(|| Duration::from_millis(self.duration_sum_ms.load(Ordering::Relaxed) / count))
+    }
+
+    /// Record the outcome and execution time of a finished exec
+    pub fn record_finished(&self, outcome: ExecOutcome, elapsed: Duration) {
+        let counter = match outcome {
+            ExecOutcome::Completed => &self.completed,
+            ExecOutcome::Failed => &self.failed,
+            ExecOutcome::TimedOut => &self.timed_out,
+            ExecOutcome::Cancelled => &self.cancelled,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        let elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+        for (bucket, upper_bound) in self.duration_buckets.iter().zip(DURATION_BUCKETS_MS) {
+            if elapsed_ms <= upper_bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.duration_sum_ms
+            .fetch_add(elapsed_ms, Ordering::Relaxed);
+        self.duration_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let counters = [
+            (
+                "codex_gateway_exec_total",
+                "Exec requests accepted",
+                &self.started,
+            ),
+            (
+                "codex_gateway_exec_completed_total",
+                "Exec requests that completed",
+                &self.completed,
+            ),
+            (
+                "codex_gateway_exec_failed_total",
+                "Exec requests that failed or errored",
+                &self.failed,
+            ),
+            (
+                "codex_gateway_exec_timed_out_total",
+                "Exec requests that exceeded timeout_ms",
+                &self.timed_out,
+            ),
+            (
+                "codex_gateway_exec_cancelled_total",
+                "Exec requests cancelled by the client",
+                &self.cancelled,
+            ),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            let _ = writeln!(out, "{name} {}", value.load(Ordering::Relaxed));
+        }
+
+        let name = "codex_gateway_exec_execution_time_ms";
+        let _ = writeln!(out, "# HELP {name} Exec execution time in milliseconds");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for (bucket, upper_bound) in self.duration_buckets.iter().zip(DURATION_BUCKETS_MS) {
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{le=\"{upper_bound}\"}} {}",
+                bucket.load(Ordering::Relaxed)
+            );
+        }
+        let count = self.duration_count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {count}");
+        let _ = writeln!(
+            out,
+            "{name}_sum {}",
+            self.duration_sum_ms.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(out, "{name}_count {count}");
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_finished_updates_counters_and_histogram() {
+        let metrics = ExecMetrics::new();
+        metrics.record_started();
+        metrics.record_finished(ExecOutcome::Completed, Duration::from_millis(750));
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("codex_gateway_exec_total 1\n"));
+        assert!(rendered.contains("codex_gateway_exec_completed_total 1\n"));
+        assert!(rendered.contains("codex_gateway_exec_failed_total 0\n"));
+        assert!(rendered.contains("codex_gateway_exec_execution_time_ms_bucket{le=\"500\"} 0\n"));
+        assert!(rendered.contains("codex_gateway_exec_execution_time_ms_bucket{le=\"1000\"} 1\n"));
+        assert!(rendered.contains("codex_gateway_exec_execution_time_ms_sum 750\n"));
+        assert!(rendered.contains("codex_gateway_exec_execution_time_ms_count 1\n"));
+    }
+}
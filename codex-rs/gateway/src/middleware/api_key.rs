@@ -1,17 +1,37 @@
 //! API Key Authentication Middleware
 //!
-//! This middleware validates API keys from the X-API-Key header
-//! and implements rate limiting per key.
+//! This middleware validates API keys from the X-API-Key header (or
+//! `Authorization: Bearer <key>`) and implements rate limiting per key. The
+//! key's [`ApiKeyId`] and [`ApiKeyScope`] are stored as request extensions so
+//! handlers can audit and enforce them.
+//!
+//! Rejections use the gateway's JSON error body; 401s also carry
+//! `WWW-Authenticate: Bearer`.
+//!
+//! With a [`JwtValidator`] configured, bearer JWTs from the identity provider
+//! replace static keys: the token's subject becomes the request's
+//! [`JwtSubject`] and, prefixed with `jwt:`, its [`ApiKeyId`].
 
+use crate::error::GatewayError;
+use crate::middleware::jwt::JwtError;
+use crate::middleware::jwt::JwtSubject;
+use crate::middleware::jwt::JwtValidator;
+use crate::middleware::rate_limit::RateLimiter;
 use axum::extract::Request;
-use axum::http::StatusCode;
+use axum::http::HeaderMap;
+use axum::http::HeaderValue;
+use axum::http::header;
 use axum::middleware::Next;
 use axum::response::IntoResponse;
 use axum::response::Response;
+use codex_protocol::config_types::SandboxMode;
 use serde::Deserialize;
 use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
 use std::collections::HashMap;
 use std::sync::Arc;
+use subtle::ConstantTimeEq;
 use tokio::sync::RwLock;
 use tracing::debug;
 use tracing::warn;
@@ -27,6 +47,94 @@ pub struct ApiKeyInfo {
     pub rate_limit: u32,
     /// Whether the key is active
     pub active: bool,
+    /// What the key may request
+    #[serde(default)]
+    pub scope: ApiKeyScope,
+}
+
+/// `key_id` of the API key that authenticated a request
+///
+/// Stored as a request extension next to the key's [`ApiKeyScope`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyId(pub String);
+
+/// Permissions attached to an API key
+///
+/// The default scope allows everything, so keys configured without scopes
+/// keep full access.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyScope {
+    /// Sandbox modes the key may request via `sandbox_mode`; `None` allows all
+    pub sandbox_modes: Option<Vec<SandboxMode>>,
+}
+
+impl ApiKeyScope {
+    /// Scope limited to the given sandbox modes
+    pub fn sandbox_modes(modes: Vec<SandboxMode>) -> Self {
+        Self {
+            sandbox_modes: Some(modes),
+        }
+    }
+
+    /// Whether the key may run with `mode`
+    pub fn allows_sandbox_mode(&self, mode: SandboxMode) -> bool {
+        match &self.sandbox_modes {
+            Some(modes) => modes.contains(&mode),
+            None => true,
+        }
+    }
+}
+
+/// One key from `CODEX_API_KEYS_JSON`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopedKey {
+    pub key: String,
+    /// Stable id used for rate limits, quotas and audit records
+    pub key_id: String,
+    pub scope: ApiKeyScope,
+}
+
+/// Value of one `CODEX_API_KEYS_JSON` entry
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ScopedKeyEntry {
+    /// `{"id": "ci-runner", "sandbox_modes": ["read-only"]}`
+    WithId {
+        id: String,
+        sandbox_modes: Vec<SandboxMode>,
+    },
+    /// `["read-only"]`; the id is derived from the key
+    Modes(Vec<SandboxMode>),
+}
+
+/// Parse `CODEX_API_KEYS_JSON`: a JSON object mapping each API key to the
+/// sandbox modes it may request
+///
+/// Entries are either `{"id": "ci-runner", "sandbox_modes": ["read-only"]}`
+/// or a bare list of modes. Bare lists get an id derived from a hash of the
+/// key, so ids never change when other keys are added or removed.
+pub fn parse_scoped_keys(json: &str) -> Result<Vec<ScopedKey>, serde_json::Error> {
+    let keys: std::collections::BTreeMap<String, ScopedKeyEntry> = serde_json::from_str(json)?;
+    Ok(keys
+        .into_iter()
+        .map(|(key, entry)| {
+            let (key_id, modes) = match entry {
+                ScopedKeyEntry::WithId { id, sandbox_modes } => (id, sandbox_modes),
+                ScopedKeyEntry::Modes(modes) => (derived_key_id(&key), modes),
+            };
+            ScopedKey {
+                key,
+                key_id,
+                scope: ApiKeyScope::sandbox_modes(modes),
+            }
+        })
+        .collect())
+}
+
+/// `key_` followed by the first 12 hex digits of the key's SHA-256
+fn derived_key_id(key: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(key.as_bytes()));
+    format!("key_{}", &digest[..12])
 }
 
 /// Simple in-memory API key store
@@ -51,9 +159,19 @@ impl ApiKeyStore {
     }
 
     /// Validate an API key
+    ///
+    /// Every stored key is compared in constant time and the scan never
+    /// exits early, so response timing does not reveal how much of a
+    /// presented key matched a real one.
     pub async fn validate_key(&self, api_key: &str) -> Option<ApiKeyInfo> {
         let keys = self.keys.read().await;
-        keys.get(api_key).cloned()
+        let mut matched = None;
+        for (stored_key, info) in keys.iter() {
+            if keys_match(stored_key, api_key) {
+                matched = Some(info.clone());
+            }
+        }
+        matched
     }
 
     /// Initialize with default keys for testing
@@ -70,6 +188,7 @@ impl ApiKeyStore {
                     user_id: "user_test".to_string(),
                     rate_limit: 100,
                     active: true,
+                    scope: ApiKeyScope::default(),
                 },
             )
             .await;
@@ -84,11 +203,35 @@ impl ApiKeyStore {
                         user_id: "gateway_internal".to_string(),
                         rate_limit: 10000, // Higher limit for internal use
                         active: true,
+                        scope: ApiKeyScope::default(),
                     },
                 )
                 .await;
         }
 
+        // Add scoped keys from CODEX_API_KEYS_JSON
+        if let Ok(json) = std::env::var("CODEX_API_KEYS_JSON") {
+            match parse_scoped_keys(&json) {
+                Ok(keys) => {
+                    for ScopedKey { key, key_id, scope } in keys {
+                        store
+                            .add_key(
+                                key,
+                                ApiKeyInfo {
+                                    key_id,
+                                    user_id: "gateway_scoped".to_string(),
+                                    rate_limit: 100,
+                                    active: true,
+                                    scope,
+                                },
+                            )
+                            .await;
+                    }
+                }
+                Err(e) => warn!("Ignoring invalid CODEX_API_KEYS_JSON: {}", e),
+            }
+        }
+
         store
     }
 }
@@ -99,23 +242,42 @@ impl Default for ApiKeyStore {
     }
 }
 
+/// Compare two API keys without short-circuiting on the first differing byte
+fn keys_match(expected: &str, provided: &str) -> bool {
+    expected.as_bytes().ct_eq(provided.as_bytes()).into()
+}
+
 /// API Key Authentication middleware
 #[derive(Debug, Clone)]
 pub struct ApiKeyAuth {
     store: ApiKeyStore,
-    /// Paths that don't require authentication
+    /// Paths that don't require authentication, matched exactly
     pub exempt_paths: Vec<String>,
+    /// Per-key request budget
+    rate_limiter: RateLimiter,
+    /// Requests per minute applied to every key instead of its own `rate_limit`
+    pub rate_limit_override: Option<u32>,
+    /// Validates bearer JWTs instead of static keys when set
+    jwt: Option<Arc<JwtValidator>>,
 }
 
+/// Requests per minute allowed per JWT subject without `CODEX_RATE_LIMIT_RPM`
+const DEFAULT_JWT_RATE_LIMIT: u32 = 100;
+
 impl ApiKeyAuth {
     /// Create a new API key auth middleware
     pub fn new(store: ApiKeyStore) -> Self {
         Self {
             store,
+            rate_limiter: RateLimiter::new(),
+            rate_limit_override: None,
+            jwt: None,
             exempt_paths: vec![
                 "/health".to_string(),
+                "/healthz".to_string(),
                 "/metrics".to_string(),
                 "/ready".to_string(),
+                "/version".to_string(),
                 "/oauth/authorize".to_string(),
                 "/oauth/token".to_string(),
             ],
@@ -123,22 +285,101 @@ impl ApiKeyAuth {
     }
 
     /// Create with default configuration
+    ///
+    /// `CODEX_RATE_LIMIT_RPM`, when set, overrides the per-key rate limit.
     pub async fn default_config() -> Self {
-        Self::new(ApiKeyStore::with_default_keys().await)
+        let mut auth = Self::new(ApiKeyStore::with_default_keys().await);
+        auth.rate_limit_override = std::env::var("CODEX_RATE_LIMIT_RPM")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok());
+        auth
+    }
+
+    /// Authenticate with JWTs checked by `validator` instead of static keys
+    pub fn with_jwt(mut self, validator: JwtValidator) -> Self {
+        self.jwt = Some(Arc::new(validator));
+        self
     }
 
     /// Check if a path is exempt from authentication
+    ///
+    /// Matches whole paths only, so an exempt `/healthz` does not open up
+    /// `/healthz/deep`, which runs a real exec.
     fn is_exempt_path(&self, path: &str) -> bool {
-        self.exempt_paths.iter().any(|p| path.starts_with(p))
+        self.exempt_paths.iter().any(|p| p == path)
+    }
+
+    /// Take one request from `key_id`'s budget, or build the 429 response
+    fn check_rate_limit(&self, key_id: &str, requests_per_minute: u32) -> Result<(), Response> {
+        let Err(retry_after) = self.rate_limiter.check(key_id, requests_per_minute) else {
+            return Ok(());
+        };
+
+        warn!(
+            "Rate limit exceeded: key_id={}, limit={}/min, retry_after={:?}",
+            key_id, requests_per_minute, retry_after
+        );
+        let retry_after_secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        Err(GatewayError::RateLimited {
+            message: format!("API key allows {requests_per_minute} requests per minute"),
+            retry_after_secs,
+        }
+        .into_response())
     }
 }
 
+/// Header carrying the API key
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// Why a request carried no usable API key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyExtractError {
+    /// Neither `X-API-Key` nor `Authorization` was sent
+    Missing,
+    /// A header was sent but its value cannot be a key
+    Malformed(&'static str),
+}
+
+/// Read the API key from `X-API-Key`, falling back to `Authorization: Bearer`
+fn extract_api_key(headers: &HeaderMap) -> Result<&str, KeyExtractError> {
+    if let Some(value) = headers.get(API_KEY_HEADER) {
+        return match value.to_str() {
+            Ok(key) if !key.trim().is_empty() => Ok(key.trim()),
+            _ => Err(KeyExtractError::Malformed(
+                "Malformed X-API-Key header: expected a non-empty ASCII API key",
+            )),
+        };
+    }
+
+    let Some(value) = headers.get(header::AUTHORIZATION) else {
+        return Err(KeyExtractError::Missing);
+    };
+    let token = value
+        .to_str()
+        .ok()
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty());
+    token.ok_or(KeyExtractError::Malformed(
+        "Malformed Authorization header: expected 'Bearer <api-key>'",
+    ))
+}
+
+/// 401 with a JSON error body and `WWW-Authenticate: Bearer`
+fn unauthorized(message: &str) -> Response {
+    let mut response = GatewayError::Auth(message.to_string()).into_response();
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
 /// Middleware function for API key authentication
 pub async fn api_key_middleware(
     auth: Arc<ApiKeyAuth>,
-    request: Request,
+    mut request: Request,
     next: Next,
-) -> Result<Response, impl IntoResponse> {
+) -> Result<Response, Response> {
     let path = request.uri().path();
 
     // Skip authentication for exempt paths
@@ -147,21 +388,24 @@ pub async fn api_key_middleware(
         return Ok(next.run(request).await);
     }
 
-    // Extract API key from X-API-Key header
-    let api_key = request
-        .headers()
-        .get("X-API-Key")
-        .and_then(|h| h.to_str().ok());
+    // JWT mode: tokens from the identity provider replace static keys
+    if let Some(jwt) = &auth.jwt {
+        return jwt_auth(&auth, jwt, request, next).await;
+    }
 
-    let api_key = match api_key {
-        Some(key) => key,
-        None => {
-            warn!("Missing X-API-Key header for path: {}", path);
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                "Missing X-API-Key header. Please provide a valid API key.",
+    // Extract API key from X-API-Key (or Authorization: Bearer)
+    let api_key = match extract_api_key(request.headers()) {
+        Ok(key) => key,
+        Err(KeyExtractError::Missing) => {
+            warn!("Missing API key for path: {}", path);
+            return Err(unauthorized(
+                "Missing API key: send it in the X-API-Key header or as 'Authorization: Bearer <api-key>'",
             ));
         }
+        Err(KeyExtractError::Malformed(message)) => {
+            warn!("Malformed API key header for path: {}", path);
+            return Err(unauthorized(message));
+        }
     };
 
     // Validate the API key
@@ -172,10 +416,12 @@ pub async fn api_key_middleware(
                 key_info.key_id, key_info.user_id, path
             );
 
-            // TODO: Implement rate limiting here
-            // For now, we just validate the key exists and is active
+            let requests_per_minute = auth.rate_limit_override.unwrap_or(key_info.rate_limit);
+            auth.check_rate_limit(&key_info.key_id, requests_per_minute)?;
 
-            // Continue with the request
+            // Continue with the request, exposing the key's id and scope to handlers
+            request.extensions_mut().insert(ApiKeyId(key_info.key_id));
+            request.extensions_mut().insert(key_info.scope);
             Ok(next.run(request).await)
         }
         Some(key_info) => {
@@ -183,18 +429,73 @@ pub async fn api_key_middleware(
                 "Inactive API key attempted: key_id={}, user_id={}",
                 key_info.key_id, key_info.user_id
             );
-            Err((StatusCode::FORBIDDEN, "API key is inactive"))
+            Err(GatewayError::Forbidden("API key is inactive".to_string()).into_response())
         }
         None => {
             warn!("Invalid API key attempted for path: {}", path);
-            Err((StatusCode::UNAUTHORIZED, "Invalid API key"))
+            Err(unauthorized("Invalid API key"))
         }
     }
 }
 
+/// Authenticate a request by its `Authorization: Bearer <jwt>` header
+async fn jwt_auth(
+    auth: &ApiKeyAuth,
+    jwt: &JwtValidator,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string);
+    let Some(token) = token else {
+        warn!("Missing bearer token for path: {}", request.uri().path());
+        return Err(unauthorized(
+            "Missing bearer token: send it as 'Authorization: Bearer <jwt>'",
+        ));
+    };
+
+    let JwtSubject(subject) = match jwt.validate(&token).await {
+        Ok(subject) => subject,
+        Err(JwtError::Jwks(e)) => {
+            warn!("JWT validation unavailable: {}", e);
+            return Err(GatewayError::ServiceUnavailable(format!(
+                "cannot validate bearer token: {e}"
+            ))
+            .into_response());
+        }
+        Err(e) => {
+            warn!("Rejected JWT for path {}: {}", request.uri().path(), e);
+            return Err(unauthorized(&format!("Invalid bearer token: {e}")));
+        }
+    };
+    debug!(
+        "JWT validated: sub={}, path={}",
+        subject,
+        request.uri().path()
+    );
+
+    let key_id = format!("jwt:{subject}");
+    auth.check_rate_limit(
+        &key_id,
+        auth.rate_limit_override.unwrap_or(DEFAULT_JWT_RATE_LIMIT),
+    )?;
+
+    request.extensions_mut().insert(JwtSubject(subject));
+    request.extensions_mut().insert(ApiKeyId(key_id));
+    request.extensions_mut().insert(ApiKeyScope::default());
+    Ok(next.run(request).await)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::http::StatusCode;
 
     #[tokio::test]
     async fn test_api_key_store() {
@@ -209,6 +510,7 @@ mod tests {
                     user_id: "user_test".to_string(),
                     rate_limit: 100,
                     active: true,
+                    scope: ApiKeyScope::default(),
                 },
             )
             .await;
@@ -223,12 +525,269 @@ mod tests {
         assert!(info.is_none());
     }
 
+    #[test]
+    fn test_keys_match_constant_time() {
+        // Same length, so both comparisons go through the full byte-wise check
+        assert!(keys_match("secret-key-0001", "secret-key-0001"));
+        assert!(!keys_match("secret-key-0001", "secret-key-0002"));
+        assert!(!keys_match("secret-key-0001", "secret-key"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_key_same_length_mismatch() {
+        let store = ApiKeyStore::new();
+        store
+            .add_key(
+                "abcd-1234".to_string(),
+                ApiKeyInfo {
+                    key_id: "key_001".to_string(),
+                    user_id: "user_test".to_string(),
+                    rate_limit: 100,
+                    active: true,
+                    scope: ApiKeyScope::default(),
+                },
+            )
+            .await;
+
+        assert!(store.validate_key("abcd-1234").await.is_some());
+        assert!(store.validate_key("abcd-1235").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_middleware_rate_limits_per_key() -> Result<(), Box<dyn std::error::Error>> {
+        use axum::Router;
+        use axum::body::Body;
+        use axum::routing::get;
+        use tower::ServiceExt;
+
+        let store = ApiKeyStore::new();
+        for (key, key_id) in [("key-a", "key_a"), ("key-b", "key_b")] {
+            store
+                .add_key(
+                    key.to_string(),
+                    ApiKeyInfo {
+                        key_id: key_id.to_string(),
+                        user_id: "user_test".to_string(),
+                        rate_limit: 2,
+                        active: true,
+                        scope: ApiKeyScope::default(),
+                    },
+                )
+                .await;
+        }
+        let auth = Arc::new(ApiKeyAuth::new(store));
+
+        let app =
+            Router::new()
+                .route("/exec", get(|| async { "ok" }))
+                .layer(axum::middleware::from_fn(move |req, next| {
+                    let auth = Arc::clone(&auth);
+                    api_key_middleware(auth, req, next)
+                }));
+
+        let call = |key: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = axum::http::Request::builder()
+                    .uri("/exec")
+                    .header("X-API-Key", key)
+                    .body(Body::empty())?;
+                Ok::<_, Box<dyn std::error::Error>>(app.oneshot(request).await?)
+            }
+        };
+
+        assert_eq!(call("key-a").await?.status(), StatusCode::OK);
+        assert_eq!(call("key-a").await?.status(), StatusCode::OK);
+
+        let limited = call("key-a").await?;
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(limited.headers().contains_key(header::RETRY_AFTER));
+        let body = axum::body::to_bytes(limited.into_body(), usize::MAX).await?;
+        let body: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!(body["code"], "rate_limited");
+
+        assert_eq!(call("key-b").await?.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_middleware_auth_failures_are_json() -> Result<(), Box<dyn std::error::Error>> {
+        use axum::Router;
+        use axum::body::Body;
+        use axum::routing::get;
+        use tower::ServiceExt;
+
+        let store = ApiKeyStore::new();
+        store
+            .add_key(
+                "good-key".to_string(),
+                ApiKeyInfo {
+                    key_id: "key_001".to_string(),
+                    user_id: "user_test".to_string(),
+                    rate_limit: 100,
+                    active: true,
+                    scope: ApiKeyScope::default(),
+                },
+            )
+            .await;
+        let auth = Arc::new(ApiKeyAuth::new(store));
+        let app =
+            Router::new()
+                .route("/exec", get(|| async { "ok" }))
+                .layer(axum::middleware::from_fn(move |req, next| {
+                    let auth = Arc::clone(&auth);
+                    api_key_middleware(auth, req, next)
+                }));
+
+        let cases: [(Option<(&str, &[u8])>, &str); 4] = [
+            (None, "Missing API key"),
+            (
+                Some(("X-API-Key", b"\xff\xfe")),
+                "Malformed X-API-Key header",
+            ),
+            (
+                Some(("Authorization", b"Basic dXNlcjpwYXNz")),
+                "Malformed Authorization header",
+            ),
+            (Some(("X-API-Key", b"wrong-key")), "Invalid API key"),
+        ];
+        for (header, expected) in cases {
+            let mut request = axum::http::Request::builder().uri("/exec");
+            if let Some((name, value)) = header {
+                request = request.header(name, HeaderValue::from_bytes(value)?);
+            }
+            let response = app.clone().oneshot(request.body(Body::empty())?).await?;
+
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(
+                response.headers().get(header::WWW_AUTHENTICATE),
+                Some(&HeaderValue::from_static("Bearer"))
+            );
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+            let body: serde_json::Value = serde_json::from_slice(&body)?;
+            assert_eq!(body["status"], 401);
+            let message = body["error"].as_str().ok_or("missing error")?;
+            assert!(message.contains(expected), "{message} lacks {expected}");
+        }
+
+        let bearer = axum::http::Request::builder()
+            .uri("/exec")
+            .header(header::AUTHORIZATION, "Bearer good-key")
+            .body(Body::empty())?;
+        assert_eq!(app.oneshot(bearer).await?.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_middleware_accepts_jwt_and_rejects_bad_tokens()
+    -> Result<(), Box<dyn std::error::Error>> {
+        use crate::middleware::jwt::tests::TEST_AUDIENCE;
+        use crate::middleware::jwt::tests::test_token;
+        use crate::middleware::jwt::tests::test_validator;
+        use axum::Extension;
+        use axum::Router;
+        use axum::body::Body;
+        use axum::routing::get;
+        use tower::ServiceExt;
+
+        let server = wiremock::MockServer::start().await;
+        let auth =
+            Arc::new(ApiKeyAuth::new(ApiKeyStore::new()).with_jwt(test_validator(&server).await));
+        let app = Router::new()
+            .route(
+                "/exec",
+                get(|Extension(JwtSubject(sub)): Extension<JwtSubject>| async move { sub }),
+            )
+            .layer(axum::middleware::from_fn(move |req, next| {
+                let auth = Arc::clone(&auth);
+                api_key_middleware(auth, req, next)
+            }));
+
+        let call = |token: String| {
+            let app = app.clone();
+            async move {
+                let request = axum::http::Request::builder()
+                    .uri("/exec")
+                    .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                    .body(Body::empty())?;
+                Ok::<_, Box<dyn std::error::Error>>(app.oneshot(request).await?)
+            }
+        };
+
+        let ok = call(test_token("user-42", TEST_AUDIENCE, 300)?).await?;
+        assert_eq!(ok.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(ok.into_body(), usize::MAX).await?;
+        assert_eq!(&body[..], b"user-42");
+
+        let expired = call(test_token("user-42", TEST_AUDIENCE, -3600)?).await?;
+        assert_eq!(expired.status(), StatusCode::UNAUTHORIZED);
+
+        let wrong_audience = call(test_token("user-42", "another-service", 300)?).await?;
+        assert_eq!(wrong_audience.status(), StatusCode::UNAUTHORIZED);
+
+        // Static keys are not accepted in JWT mode
+        assert_eq!(
+            call("good-key".to_string()).await?.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_scoped_keys() -> Result<(), Box<dyn std::error::Error>> {
+        let keys = parse_scoped_keys(
+            r#"{"ro-key": ["read-only"], "full-key": ["read-only", "danger-full-access"]}"#,
+        )?;
+
+        let find = |key: &str| keys.iter().find(|k| k.key == key).cloned();
+        let read_only = find("ro-key").ok_or("ro-key missing")?.scope;
+        assert!(read_only.allows_sandbox_mode(SandboxMode::ReadOnly));
+        assert!(!read_only.allows_sandbox_mode(SandboxMode::DangerFullAccess));
+
+        let full = find("full-key").ok_or("full-key missing")?.scope;
+        assert!(full.allows_sandbox_mode(SandboxMode::DangerFullAccess));
+
+        assert!(parse_scoped_keys(r#"{"bad-key": ["root"]}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scoped_key_ids_are_stable() -> Result<(), Box<dyn std::error::Error>> {
+        let id_of = |json: &str, key: &str| -> Result<String, Box<dyn std::error::Error>> {
+            let keys = parse_scoped_keys(json)?;
+            let found = keys
+                .into_iter()
+                .find(|k| k.key == key)
+                .ok_or("key missing")?;
+            Ok(found.key_id)
+        };
+
+        // Adding a key that sorts first does not renumber the others
+        let before = id_of(r#"{"m-key": ["read-only"]}"#, "m-key")?;
+        let after = id_of(
+            r#"{"a-key": ["read-only"], "m-key": ["read-only"]}"#,
+            "m-key",
+        )?;
+        assert_eq!(before, after);
+        assert!(before.starts_with("key_"));
+        assert!(!before.contains("m-key"));
+
+        let explicit = id_of(
+            r#"{"ci-key": {"id": "ci-runner", "sandbox_modes": ["workspace-write"]}}"#,
+            "ci-key",
+        )?;
+        assert_eq!(explicit, "ci-runner");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_exempt_paths() {
         let auth = ApiKeyAuth::default_config().await;
 
         assert!(auth.is_exempt_path("/health"));
-        assert!(auth.is_exempt_path("/health/ready"));
+        assert!(auth.is_exempt_path("/healthz"));
+        assert!(!auth.is_exempt_path("/healthz/deep"));
+        assert!(!auth.is_exempt_path("/health/ready"));
         assert!(auth.is_exempt_path("/metrics"));
         assert!(!auth.is_exempt_path("/jsonrpc"));
         assert!(!auth.is_exempt_path("/ws"));
@@ -0,0 +1,138 @@
+//! JSON bodies for errors raised outside the gateway's handlers
+//!
+//! axum's extractors, the body size limits and the request timeout answer
+//! with plain-text (or empty) error responses. This middleware rewrites any
+//! 4xx/5xx response that is not already JSON into the gateway's error body,
+//! with a `code` derived from the status, so clients can handle every error
+//! the same way. Headers such as `Retry-After` are kept.
+
+use crate::error::code_for_status;
+use crate::error::error_body;
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// Longest plain-text error body kept as the `error` message
+const MAX_MESSAGE_BYTES: usize = 4096;
+
+/// Rewrite non-JSON error responses into the gateway's JSON error body
+pub async fn json_error_middleware(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    if !(status.is_client_error() || status.is_server_error()) || is_json(&response) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let text = axum::body::to_bytes(body, MAX_MESSAGE_BYTES)
+        .await
+        .map(|bytes| String::from_utf8_lossy(&bytes).trim().to_string())
+        .unwrap_or_default();
+    let message = if text.is_empty() {
+        status.canonical_reason().unwrap_or("Error").to_string()
+    } else {
+        text
+    };
+
+    let body = error_body(status, code_for_status(status), &message).to_string();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Whether the response already carries a JSON body
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Json;
+    use axum::Router;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use serde_json::Value;
+    use tower::ServiceExt;
+    use tower_http::limit::RequestBodyLimitLayer;
+
+    async fn echo(Json(body): Json<Value>) -> Json<Value> {
+        Json(body)
+    }
+
+    async fn send(request: Request) -> Result<(StatusCode, Value), Box<dyn std::error::Error>> {
+        let app = Router::new()
+            .route("/echo", post(echo).layer(RequestBodyLimitLayer::new(16)))
+            .layer(axum::middleware::from_fn(json_error_middleware));
+        let response = app.oneshot(request).await?;
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        Ok((status, serde_json::from_slice(&body)?))
+    }
+
+    fn post_echo(content_type: &str, body: &str) -> Result<Request, axum::http::Error> {
+        axum::http::Request::builder()
+            .method("POST")
+            .uri("/echo")
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body.to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_plain_errors_get_json_codes() -> Result<(), Box<dyn std::error::Error>> {
+        let cases = [
+            (post_echo("application/json", "{")?, 400, "invalid_request"),
+            (
+                post_echo("text/plain", "{}")?,
+                415,
+                "unsupported_media_type",
+            ),
+            (
+                post_echo("application/json", &format!("[{}]", "1,".repeat(20) + "1"))?,
+                413,
+                "payload_too_large",
+            ),
+            (
+                axum::http::Request::builder()
+                    .uri("/missing")
+                    .body(Body::empty())?,
+                404,
+                "not_found",
+            ),
+            (
+                axum::http::Request::builder()
+                    .uri("/echo")
+                    .body(Body::empty())?,
+                405,
+                "method_not_allowed",
+            ),
+        ];
+
+        for (request, status, code) in cases {
+            let (actual, body) = send(request).await?;
+            assert_eq!(actual.as_u16(), status, "{code}");
+            assert_eq!(body["code"], code);
+            assert_eq!(body["status"], status);
+            assert!(body["error"].as_str().is_some_and(|e| !e.is_empty()));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_successful_responses_are_untouched() -> Result<(), Box<dyn std::error::Error>> {
+        let (status, body) = send(post_echo("application/json", r#"{"a":1}"#)?).await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!({"a": 1}));
+        Ok(())
+    }
+}
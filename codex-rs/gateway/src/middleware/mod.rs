@@ -1,5 +1,12 @@
 //! Middleware modules for the Codex Gateway
 
 pub mod api_key;
+pub mod json_errors;
+pub mod jwt;
+pub mod rate_limit;
 
 pub use api_key::ApiKeyAuth;
+pub use api_key::ApiKeyId;
+pub use api_key::ApiKeyScope;
+pub use jwt::JwtSubject;
+pub use rate_limit::RateLimiter;
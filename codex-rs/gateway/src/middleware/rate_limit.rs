@@ -0,0 +1,141 @@
+//! Per-key rate limiting
+//!
+//! Token buckets keyed by API key identifier. Each bucket holds up to one
+//! minute's worth of requests and refills continuously, so short bursts are
+//! allowed while the sustained rate stays at the configured requests/minute.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// How often idle buckets are pruned
+const PRUNE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Buckets untouched for this long are dropped (they would be full anyway)
+const BUCKET_IDLE_TTL: Duration = Duration::from_secs(600);
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    by_key: HashMap<String, TokenBucket>,
+    last_prune: Instant,
+}
+
+/// Token-bucket rate limiter keyed by API key identifier
+#[derive(Debug)]
+pub struct RateLimiter {
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    /// Create an empty rate limiter
+    pub fn new() -> Self {
+        Self {
+            buckets: Mutex::new(Buckets {
+                by_key: HashMap::new(),
+                last_prune: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take one request from `key`'s bucket
+    ///
+    /// Returns `Err(retry_after)` when the key has exhausted its budget of
+    /// `requests_per_minute`.
+    pub fn check(&self, key: &str, requests_per_minute: u32) -> Result<(), Duration> {
+        self.check_at(key, requests_per_minute, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, requests_per_minute: u32, now: Instant) -> Result<(), Duration> {
+        if requests_per_minute == 0 {
+            return Err(Duration::from_secs(60));
+        }
+
+        let capacity = f64::from(requests_per_minute);
+        let refill_per_sec = capacity / 60.0;
+
+        let mut buckets = match self.buckets.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if now.duration_since(buckets.last_prune) >= PRUNE_INTERVAL {
+            buckets
+                .by_key
+                .retain(|_, bucket| now.duration_since(bucket.last_refill) < BUCKET_IDLE_TTL);
+            buckets.last_prune = now;
+        }
+
+        let bucket = buckets
+            .by_key
+            .entry(key.to_string())
+            .or_insert(TokenBucket {
+                tokens: capacity,
+                last_refill: now,
+            });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing = 1.0 - bucket.tokens;
+            Err(Duration::from_secs_f64(missing / refill_per_sec))
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_per_key() {
+        let limiter = RateLimiter::new();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at("key_a", 3, now).is_ok());
+        }
+
+        let retry_after = limiter.check_at("key_a", 3, now).unwrap_err();
+        assert!(retry_after > Duration::ZERO);
+        assert!(retry_after <= Duration::from_secs(20));
+
+        // A different key has its own bucket
+        assert!(limiter.check_at("key_b", 3, now).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit_refills_over_time() {
+        let limiter = RateLimiter::new();
+        let now = Instant::now();
+
+        for _ in 0..60 {
+            assert!(limiter.check_at("key_a", 60, now).is_ok());
+        }
+        assert!(limiter.check_at("key_a", 60, now).is_err());
+
+        // 60 rpm refills one token per second
+        assert!(
+            limiter
+                .check_at("key_a", 60, now + Duration::from_secs(1))
+                .is_ok()
+        );
+    }
+}
@@ -1,25 +1,150 @@
 //! Router configuration for the Codex Gateway
 
+use crate::config::CorsConfig;
 use crate::error::GatewayResult;
-use crate::handlers::exec::{handle_exec, handle_exec_resume};
+use crate::handlers::debug::debug_config_handler;
+use crate::handlers::exec::NDJSON_CONTENT_TYPE;
+use crate::handlers::exec::SERVER_TIMING_HEADER;
+use crate::handlers::exec::{
+    handle_exec, handle_exec_batch, handle_exec_ndjson, handle_exec_resume,
+};
+use crate::handlers::health::deep_readiness_check;
 use crate::handlers::health::health_check;
+use crate::handlers::health::readiness_check;
 use crate::handlers::jsonrpc::handle_jsonrpc;
+use crate::handlers::metrics::handle_metrics;
 use crate::handlers::oauth::{handle_oauth_authorize, handle_oauth_token};
+use crate::handlers::sessions::handle_active_sessions;
+use crate::handlers::sessions::handle_cancel_session;
+use crate::handlers::sessions::handle_delete_session;
+use crate::handlers::sessions::handle_list_sessions;
+use crate::handlers::sessions::handle_session_artifact;
+use crate::handlers::sessions::handle_session_input;
+use crate::handlers::sessions::handle_session_result;
+use crate::handlers::version::version_handler;
 use crate::handlers::webhook::handle_webhook;
 use crate::handlers::websocket::handle_websocket_upgrade;
-use crate::middleware::api_key::api_key_middleware;
 use crate::middleware::api_key::ApiKeyAuth;
+use crate::middleware::api_key::api_key_middleware;
+use crate::middleware::json_errors::json_error_middleware;
+use crate::middleware::jwt::JwtConfig;
+use crate::middleware::jwt::JwtValidator;
+use crate::services::idempotency::IDEMPOTENCY_KEY_HEADER;
+use crate::services::idempotency::IDEMPOTENT_REPLAYED_HEADER;
 use crate::state::AppState;
-use axum::middleware;
+use crate::telemetry;
 use axum::Router;
+use axum::body::Body;
+use axum::extract::DefaultBodyLimit;
+use axum::http::HeaderName;
+use axum::http::HeaderValue;
+use axum::http::Method;
+use axum::http::Request;
+use axum::http::header;
+use axum::middleware;
+use axum::routing::delete;
 use axum::routing::get;
 use axum::routing::post;
 use std::sync::Arc;
+use tower_http::compression::CompressionLayer;
+use tower_http::compression::Predicate;
+use tower_http::compression::predicate::And;
+use tower_http::compression::predicate::DefaultPredicate;
+use tower_http::compression::predicate::NotForContentType;
+use tower_http::cors::AllowOrigin;
 use tower_http::cors::CorsLayer;
 use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::request_id::MakeRequestUuid;
+use tower_http::request_id::PropagateRequestIdLayer;
+use tower_http::request_id::SetRequestIdLayer;
 use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
+use tracing::Span;
 use tracing::info;
+use tracing::warn;
+
+/// Header carrying the per-request correlation id
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Build the tracing span wrapping a whole request
+///
+/// The correlation id is taken from `X-Request-Id` (set by the client or
+/// minted by `SetRequestIdLayer`), so every log line emitted while handling
+/// the request can be filtered by it. With OTLP export on, a `traceparent`
+/// header makes the span part of the caller's trace.
+fn request_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("-");
+
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id = %request_id,
+    );
+    // Join the caller's distributed trace when it sent `traceparent`
+    telemetry::set_remote_parent(&span, request.headers());
+    span
+}
+
+/// Build the CORS layer for browser clients
+///
+/// Returns `None` when no origins are configured, leaving the gateway
+/// same-origin only. Preflight requests are answered by this layer before
+/// API key authentication runs.
+fn cors_layer(config: &CorsConfig) -> Option<CorsLayer> {
+    if config.allowed_origins.is_empty() {
+        return None;
+    }
+
+    let allow_origin = if config.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins: Vec<HeaderValue> = config
+            .allowed_origins
+            .iter()
+            .filter_map(|origin| match HeaderValue::from_str(origin) {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    warn!("Ignoring invalid CORS origin: {}", origin);
+                    None
+                }
+            })
+            .collect();
+        AllowOrigin::list(origins)
+    };
+
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            .allow_headers([
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                HeaderName::from_static("x-api-key"),
+                HeaderName::from_static(REQUEST_ID_HEADER),
+                HeaderName::from_static(IDEMPOTENCY_KEY_HEADER),
+            ])
+            .expose_headers([
+                HeaderName::from_static(REQUEST_ID_HEADER),
+                HeaderName::from_static(IDEMPOTENT_REPLAYED_HEADER),
+                HeaderName::from_static(SERVER_TIMING_HEADER),
+            ]),
+    )
+}
+
+/// Build the response compression layer
+///
+/// NDJSON exec streams are left uncompressed: the encoder buffers output,
+/// which would hold back each line until a block fills.
+fn compression_layer() -> CompressionLayer<And<DefaultPredicate, NotForContentType>> {
+    CompressionLayer::new().compress_when(
+        DefaultPredicate::new().and(NotForContentType::const_new(NDJSON_CONTENT_TYPE)),
+    )
+}
 
 /// Create the main application router with all routes and middleware
 pub async fn create_router(state: AppState) -> GatewayResult<Router> {
@@ -31,52 +156,123 @@ pub async fn create_router(state: AppState) -> GatewayResult<Router> {
     let health_limit = state.config().body_limits.health_limit;
     let jsonrpc_limit = state.config().body_limits.jsonrpc_limit;
     let webhook_limit = state.config().body_limits.webhook_limit;
+    let exec_limit = state.config().body_limits.get_limit_for_path("/exec");
 
     // Initialize API Key authentication
-    let api_key_auth = Arc::new(ApiKeyAuth::default_config().await);
+    // (or JWT validation when CODEX_JWT_* is set)
+    let mut api_key_auth = ApiKeyAuth::default_config().await;
+    if let Some(jwt) = JwtConfig::from_env()? {
+        api_key_auth = api_key_auth.with_jwt(JwtValidator::new(jwt));
+    }
+    let api_key_auth = Arc::new(api_key_auth);
     info!("API Key authentication initialized");
 
-    // Configure CORS - allow all origins for now, can be restricted later
-    let cors = CorsLayer::permissive();
+    // Configure CORS from CODEX_CORS_ALLOWED_ORIGINS; disabled when unset
+    let cors = cors_layer(&state.config().cors);
 
     // Configure timeout from state config
     let timeout = TimeoutLayer::new(request_timeout);
 
-    // Configure request body size limits based on endpoint
+    // Body size limit for every route but the exec routes, which have their own
     let global_body_limit = RequestBodyLimitLayer::new(default_limit);
 
-    // Configure tracing middleware
-    let trace = TraceLayer::new_for_http();
+    // Configure tracing middleware with a correlation id per request
+    let trace = TraceLayer::new_for_http().make_span_with(request_span);
+    let request_id_header = HeaderName::from_static(REQUEST_ID_HEADER);
+    let set_request_id = SetRequestIdLayer::new(request_id_header.clone(), MakeRequestUuid);
+    let propagate_request_id = PropagateRequestIdLayer::new(request_id_header);
+
+    // Exec routes run for as long as their turn's max_lifetime_ms allows and
+    // take bodies up to CODEX_MAX_BODY_BYTES, so they are merged in outside
+    // the request timeout and the default body limit
+    let exec_routes = Router::new()
+        // Exec endpoint for real codex-exec mode (JSONL events)
+        .route(
+            "/exec",
+            post(handle_exec)
+                // 413 from Content-Length (or while reading) before the JSON is parsed
+                .layer(RequestBodyLimitLayer::new(exec_limit))
+                .layer(DefaultBodyLimit::max(exec_limit)),
+        )
+        // Run several prompts in order under one session
+        .route(
+            "/exec/batch",
+            post(handle_exec_batch)
+                .layer(RequestBodyLimitLayer::new(exec_limit))
+                .layer(DefaultBodyLimit::max(exec_limit)),
+        )
+        // Same as /exec, streamed as newline-delimited JSON
+        .route(
+            "/exec/ndjson",
+            post(handle_exec_ndjson)
+                .layer(RequestBodyLimitLayer::new(exec_limit))
+                .layer(DefaultBodyLimit::max(exec_limit)),
+        );
 
     // Build the router with all routes and middleware
     let app = Router::new()
         // Health check endpoint (no auth required)
         .route("/health", get(health_check))
+        // Readiness probe: fails when CODEX_HOME is unusable (no auth required)
+        .route("/healthz", get(readiness_check))
+        // Readiness probe that runs a cached smoke exec (auth required)
+        .route("/healthz/deep", get(deep_readiness_check))
+        // Build version and commit (no auth required)
+        .route("/version", get(version_handler))
+        // Prometheus metrics (no auth required)
+        .route("/metrics", get(handle_metrics))
         // OAuth endpoints (no auth required for OAuth flow)
         .route("/oauth/authorize", get(handle_oauth_authorize))
         .route("/oauth/token", post(handle_oauth_token))
         // JSON-RPC endpoint for protocol communication
         .route("/jsonrpc", post(handle_jsonrpc))
-        // Exec endpoint for real codex-exec mode (JSONL events)
-        .route("/exec", post(handle_exec))
         // Exec resume endpoint for resuming conversations
         .route("/exec/resume", post(handle_exec_resume))
+        // List recorded sessions, newest first
+        .route("/sessions", get(handle_list_sessions))
+        // Exec turns running right now
+        .route("/sessions/active", get(handle_active_sessions))
+        // Cancel the running turn of a session
+        .route("/sessions/{session_id}/cancel", post(handle_cancel_session))
+        // Add user input to the running turn of a session
+        .route("/sessions/{session_id}/input", post(handle_session_input))
+        // Final result of the session's last exec
+        .route("/sessions/{session_id}/result", get(handle_session_result))
+        // Download a file the session's last exec created
+        .route(
+            "/sessions/{session_id}/artifacts/{*name}",
+            get(handle_session_artifact),
+        )
+        // Purge a session and its recorded rollout
+        .route("/sessions/{session_id}", delete(handle_delete_session))
         // WebSocket endpoint for real-time communication
         .route("/ws", get(handle_websocket_upgrade))
         // Webhook endpoint for external integrations
         .route("/webhook", post(handle_webhook))
+        // Effective configuration (404 unless CODEX_ENABLE_DEBUG_ENDPOINTS=1)
+        .route("/debug/config", get(debug_config_handler))
+        .layer(global_body_limit) // Body size limit fallback
+        .layer(timeout) // Request timeout
+        .merge(exec_routes)
         // Apply global middleware stack in correct order
         .layer(middleware::from_fn(move |req, next| {
             let auth = Arc::clone(&api_key_auth);
             api_key_middleware(auth, req, next)
         })) // API Key authentication
-        .layer(global_body_limit) // Global body size limit fallback
+        .layer(middleware::from_fn(json_error_middleware)) // JSON body with a `code` for every error
+        .layer(compression_layer()) // gzip/br on Accept-Encoding (never SSE or NDJSON)
+        .layer(propagate_request_id) // Echo X-Request-Id on the response
         .layer(trace) // Request tracing
-        .layer(timeout) // Request timeout
-        .layer(cors) // CORS handling
+        .layer(set_request_id) // Accept or mint X-Request-Id
         // Add shared state
         .with_state(state);
 
+    // CORS handling (outermost, so preflights never hit auth)
+    let app = match cors {
+        Some(cors) => app.layer(cors),
+        None => app,
+    };
+
     info!(
         "Router created successfully with body size limits: health={}KB, jsonrpc={}KB, websocket={}KB, webhook={}KB, default={}KB",
         health_limit / 1024,
@@ -102,4 +298,254 @@ mod tests {
         // Se chegou até aqui, o router foi criado com sucesso
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_request_id_is_echoed() -> Result<(), Box<dyn std::error::Error>> {
+        use tower::ServiceExt;
+
+        let state = AppState::new(GatewayConfig::default()).await?;
+        let router = create_router(state).await?;
+
+        let request = Request::builder()
+            .uri("/health")
+            .header(REQUEST_ID_HEADER, "trace-abc-123")
+            .body(Body::empty())?;
+        let response = router.oneshot(request).await?;
+
+        assert_eq!(
+            response
+                .headers()
+                .get(REQUEST_ID_HEADER)
+                .map(|v| v.to_str()),
+            Some(Ok("trace-abc-123"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_generated() -> Result<(), Box<dyn std::error::Error>> {
+        use tower::ServiceExt;
+
+        let state = AppState::new(GatewayConfig::default()).await?;
+        let router = create_router(state).await?;
+
+        let request = Request::builder().uri("/health").body(Body::empty())?;
+        let response = router.oneshot(request).await?;
+
+        let request_id = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .ok_or("missing x-request-id")?
+            .to_str()?;
+        assert!(uuid::Uuid::parse_str(request_id).is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_responses_are_gzip_compressed() -> Result<(), Box<dyn std::error::Error>> {
+        use std::io::Read;
+        use tower::ServiceExt;
+
+        let state = AppState::new(GatewayConfig::default()).await?;
+        let router = create_router(state).await?;
+
+        let request = Request::builder()
+            .uri("/metrics")
+            .header("accept-encoding", "gzip")
+            .body(Body::empty())?;
+        let response = router.oneshot(request).await?;
+
+        assert_eq!(
+            response
+                .headers()
+                .get("content-encoding")
+                .map(|v| v.to_str()),
+            Some(Ok("gzip"))
+        );
+
+        let compressed = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        let mut body = String::new();
+        flate2::read::GzDecoder::new(&compressed[..]).read_to_string(&mut body)?;
+        assert!(body.contains("codex_gateway_exec_total"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_responses_are_not_compressed() -> Result<(), Box<dyn std::error::Error>> {
+        use axum::routing::get;
+        use tower::ServiceExt;
+
+        let line = format!(
+            "{}\n",
+            serde_json::json!({"event": "exec.event", "data": "x".repeat(256)})
+        );
+        let router = Router::new()
+            .route(
+                "/ndjson",
+                get({
+                    let line = line.clone();
+                    move || async move { ([(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)], line) }
+                }),
+            )
+            .route(
+                "/json",
+                get({
+                    let line = line.clone();
+                    move || async move { ([(header::CONTENT_TYPE, "application/json")], line) }
+                }),
+            )
+            .layer(compression_layer());
+
+        let call = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let request = Request::builder()
+                    .uri(uri)
+                    .header("accept-encoding", "gzip")
+                    .body(Body::empty())?;
+                Ok::<_, Box<dyn std::error::Error>>(router.oneshot(request).await?)
+            }
+        };
+
+        let ndjson = call("/ndjson").await?;
+        assert!(ndjson.headers().get(header::CONTENT_ENCODING).is_none());
+        let body = axum::body::to_bytes(ndjson.into_body(), usize::MAX).await?;
+        assert_eq!(&body[..], line.as_bytes());
+
+        let json = call("/json").await?;
+        assert_eq!(
+            json.headers()
+                .get(header::CONTENT_ENCODING)
+                .map(|v| v.to_str()),
+            Some(Ok("gzip"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_oversized_exec_body_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+        use tower::ServiceExt;
+
+        let mut config = GatewayConfig::default();
+        config.body_limits.exec_limit = 1024;
+        let state = AppState::new(config).await?;
+        let router = create_router(state).await?;
+
+        let body = format!(r#"{{"prompt": "{}"}}"#, "x".repeat(4096));
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/exec")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CONTENT_LENGTH, body.len())
+            .header("x-api-key", "test-key-12345")
+            .body(Body::from(body))?;
+        let response = router.oneshot(request).await?;
+
+        assert_eq!(response.status(), axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        let body: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!(body["code"], "payload_too_large");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exec_body_limit_may_exceed_default_limit()
+    -> Result<(), Box<dyn std::error::Error>> {
+        use tower::ServiceExt;
+
+        let mut config = GatewayConfig::default();
+        config.body_limits.default_limit = 2 * 1024 * 1024;
+        config.body_limits.exec_limit = 4 * 1024 * 1024;
+        let state = AppState::new(config).await?;
+        let router = create_router(state).await?;
+
+        // 3 MiB of blank prompt: past the default limit, within the exec limit
+        let body = format!(r#"{{"prompt": "{}"}}"#, " ".repeat(3 * 1024 * 1024));
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/exec")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CONTENT_LENGTH, body.len())
+            .header("x-api-key", "test-key-12345")
+            .body(Body::from(body))?;
+        let response = router.oneshot(request).await?;
+
+        // Parsed and rejected by the handler, not cut off with 413
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exec_routes_are_not_subject_to_request_timeout()
+    -> Result<(), Box<dyn std::error::Error>> {
+        use tower::ServiceExt;
+
+        let mut config = GatewayConfig::default();
+        config.timeouts.request_timeout = std::time::Duration::ZERO;
+        let state = AppState::new(config).await?;
+        let router = create_router(state).await?;
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/exec")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("x-api-key", "test-key-12345")
+            .body(Body::from(r#"{"prompt": " "}"#))?;
+        let response = router.oneshot(request).await?;
+
+        // The handler's own answer, not a 408 from the request timeout
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight_allows_configured_origin() -> Result<(), Box<dyn std::error::Error>>
+    {
+        use tower::ServiceExt;
+
+        let mut config = GatewayConfig::default();
+        config.cors.allowed_origins = vec!["https://app.example.com".to_string()];
+        let state = AppState::new(config).await?;
+        let router = create_router(state).await?;
+
+        let request = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/exec")
+            .header(header::ORIGIN, "https://app.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization")
+            .body(Body::empty())?;
+        let response = router.oneshot(request).await?;
+
+        assert!(response.status().is_success());
+        assert_eq!(
+            response
+                .headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .map(|v| v.to_str()),
+            Some(Ok("https://app.example.com"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cors_disabled_by_default() -> Result<(), Box<dyn std::error::Error>> {
+        use tower::ServiceExt;
+
+        let state = AppState::new(GatewayConfig::default()).await?;
+        let router = create_router(state).await?;
+
+        let request = Request::builder()
+            .uri("/health")
+            .header(header::ORIGIN, "https://app.example.com")
+            .body(Body::empty())?;
+        let response = router.oneshot(request).await?;
+
+        assert!(
+            !response
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,175 @@
+//! Registry of exec turns currently running
+//!
+//! `/exec` and WebSocket exec register a turn once its conversation is known
+//! and hold the returned guard until the turn finishes, so the registry
+//! always reflects what is running right now.
+
+use chrono::DateTime;
+use chrono::Utc;
+use codex_protocol::ConversationId;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+/// Number of prompt characters kept for the listing
+const PROMPT_PREFIX_CHARS: usize = 80;
+
+#[derive(Debug)]
+struct ActiveExec {
+    session_id: Option<String>,
+    conversation_id: ConversationId,
+    key_id: Option<String>,
+    started_at: DateTime<Utc>,
+    started: Instant,
+    prompt_prefix: String,
+}
+
+/// A running exec as reported by `GET /sessions/active`
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveExecSummary {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub conversation_id: String,
+    /// RFC 3339 start time
+    pub started_at: String,
+    pub elapsed_ms: u64,
+    /// First characters of the prompt
+    pub prompt_prefix: String,
+    /// API key the exec runs under; not reported
+    #[serde(skip)]
+    pub key_id: Option<String>,
+}
+
+/// Registry of exec turns currently running
+#[derive(Debug, Default)]
+pub struct ActiveExecs {
+    next_id: AtomicU64,
+    entries: Mutex<HashMap<u64, ActiveExec>>,
+}
+
+/// Keeps an exec listed as active until dropped
+#[derive(Debug)]
+pub struct ActiveExecGuard {
+    registry: Arc<ActiveExecs>,
+    id: u64,
+}
+
+impl Drop for ActiveExecGuard {
+    fn drop(&mut self) {
+        self.registry.entries().remove(&self.id);
+    }
+}
+
+impl ActiveExecs {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// List an exec run under `key_id` as active until the returned guard is dropped
+    pub fn register(
+        self: &Arc<Self>,
+        session_id: Option<&str>,
+        conversation_id: ConversationId,
+        prompt: &str,
+        key_id: Option<&str>,
+    ) -> ActiveExecGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.entries().insert(
+            id,
+            ActiveExec {
+                session_id: session_id.map(str::to_string),
+                conversation_id,
+                key_id: key_id.map(str::to_string),
+                started_at: Utc::now(),
+                started: Instant::now(),
+                prompt_prefix: prompt.chars().take(PROMPT_PREFIX_CHARS).collect(),
+            },
+        );
+
+        ActiveExecGuard {
+            registry: Arc::clone(self),
+            id,
+        }
+    }
+
+    /// Whether an exec is running for `session_id`
+    pub fn is_running(&self, session_id: &str) -> bool {
+        self.entries()
+            .values()
+            .any(|exec| exec.session_id.as_deref() == Some(session_id))
+    }
+
+    /// API key of a running exec whose session or conversation ID is `id`
+    pub fn owner(&self, id: &str) -> Option<String> {
+        self.entries()
+            .values()
+            .find(|exec| {
+                exec.session_id.as_deref() == Some(id) || exec.conversation_id.to_string() == id
+            })
+            .and_then(|exec| exec.key_id.clone())
+    }
+
+    /// Summaries of all running execs, oldest first
+    pub fn snapshot(&self) -> Vec<ActiveExecSummary> {
+        let entries = self.entries();
+        let mut active: Vec<(&u64, &ActiveExec)> = entries.iter().collect();
+        active.sort_by_key(|(id, exec)| (exec.started, **id));
+
+        active
+            .into_iter()
+            .map(|(_, exec)| ActiveExecSummary {
+                session_id: exec.session_id.clone(),
+                conversation_id: exec.conversation_id.to_string(),
+                started_at: exec.started_at.to_rfc3339(),
+                elapsed_ms: u64::try_from(exec.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+                prompt_prefix: exec.prompt_prefix.clone(),
+                key_id: exec.key_id.clone(),
+            })
+            .collect()
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<u64, ActiveExec>> {
+        match self.entries.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registered_execs_are_listed_until_dropped() {
+        let registry = Arc::new(ActiveExecs::new());
+
+        let first = registry.register(
+            Some("session-a"),
+            ConversationId::new(),
+            "first prompt",
+            Some("key-a"),
+        );
+        let second = registry.register(None, ConversationId::new(), &"y".repeat(200), None);
+
+        let active = registry.snapshot();
+        assert_eq!(active.len(), 2);
+        assert!(registry.is_running("session-a"));
+        assert!(!registry.is_running("session-b"));
+        assert_eq!(registry.owner("session-a").as_deref(), Some("key-a"));
+        assert_eq!(active[0].session_id.as_deref(), Some("session-a"));
+        assert_eq!(active[0].prompt_prefix, "first prompt");
+        assert_eq!(active[1].prompt_prefix.len(), PROMPT_PREFIX_CHARS);
+
+        drop(first);
+        assert_eq!(registry.snapshot().len(), 1);
+        drop(second);
+        assert!(registry.snapshot().is_empty());
+    }
+}
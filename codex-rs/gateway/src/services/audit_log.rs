@@ -0,0 +1,228 @@
+//! Audit trail of exec turns
+//!
+//! Every turn emits a `task_started` and a `task_completed` record: who ran
+//! it (a hash of the API key's `key_id`), the session and conversation, a
+//! SHA-256 of the prompt, the sandbox policy, the effective timeouts,
+//! timestamps and the final status. Records are JSON lines written to
+//! stdout, or appended to the file named by `CODEX_AUDIT_LOG`. The trail is
+//! separate from session rollouts, so `persist: false` does not remove it.
+
+use crate::error::GatewayError;
+use crate::error::GatewayResult;
+use chrono::Utc;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::error;
+
+/// Environment variable naming the audit log file; unset writes to stdout
+pub const AUDIT_LOG_ENV: &str = "CODEX_AUDIT_LOG";
+
+/// Hex SHA-256 of `value`
+pub fn sha256_hex(value: &str) -> String {
+    format!("{:x}", Sha256::digest(value.as_bytes()))
+}
+
+/// Which end of the turn a record describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEvent {
+    TaskStarted,
+    TaskCompleted,
+}
+
+/// One line of the audit trail
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    pub event: AuditEvent,
+    /// SHA-256 of the API key's `key_id`; absent when auth is disabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub conversation_id: String,
+    /// SHA-256 of the prompt as sent by the client
+    pub prompt_sha256: String,
+    pub sandbox_policy: String,
+    /// Inactivity bound after clamping to `CODEX_MAX_TIMEOUT_MS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// Lifetime bound after clamping to `CODEX_MAX_LIFETIME_MS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_lifetime_ms: Option<u64>,
+    /// RFC 3339 time the turn started
+    pub started_at: String,
+    /// RFC 3339 time the turn finished (`task_completed` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+    /// Final status of the turn (`task_completed` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_time_ms: Option<u64>,
+}
+
+impl AuditRecord {
+    /// `task_started` record for a turn about to be submitted
+    pub fn started(
+        api_key_id: Option<&str>,
+        session_id: Option<&str>,
+        conversation_id: &str,
+        prompt: &str,
+        sandbox_policy: &str,
+    ) -> Self {
+        Self {
+            event: AuditEvent::TaskStarted,
+            api_key_hash: api_key_id.map(sha256_hex),
+            session_id: session_id.map(str::to_string),
+            conversation_id: conversation_id.to_string(),
+            prompt_sha256: sha256_hex(prompt),
+            sandbox_policy: sandbox_policy.to_string(),
+            timeout_ms: None,
+            max_lifetime_ms: None,
+            started_at: Utc::now().to_rfc3339(),
+            finished_at: None,
+            status: None,
+            execution_time_ms: None,
+        }
+    }
+
+    /// Record the time bounds the turn runs under
+    pub fn with_limits(mut self, timeout_ms: Option<u64>, max_lifetime_ms: u64) -> Self {
+        self.timeout_ms = timeout_ms;
+        self.max_lifetime_ms = Some(max_lifetime_ms);
+        self
+    }
+
+    /// `task_completed` record for the same turn
+    pub fn completed(&self, status: &str, elapsed: Duration) -> Self {
+        Self {
+            event: AuditEvent::TaskCompleted,
+            finished_at: Some(Utc::now().to_rfc3339()),
+            status: Some(status.to_string()),
+            execution_time_ms: Some(u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)),
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug)]
+enum AuditSink {
+    Stdout,
+    File(Mutex<File>),
+}
+
+/// Writes audit records as JSON lines
+#[derive(Debug)]
+pub struct AuditLog {
+    sink: AuditSink,
+}
+
+impl AuditLog {
+    /// Audit log on stdout
+    pub fn stdout() -> Self {
+        Self {
+            sink: AuditSink::Stdout,
+        }
+    }
+
+    /// Audit log appended to `path`, which is created if missing
+    pub fn open(path: &Path) -> GatewayResult<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                GatewayError::Config(format!("failed to open audit log {}: {e}", path.display()))
+            })?;
+        Ok(Self {
+            sink: AuditSink::File(Mutex::new(file)),
+        })
+    }
+
+    /// Audit log on the file named by `CODEX_AUDIT_LOG`, or stdout
+    pub fn from_env() -> GatewayResult<Self> {
+        match std::env::var_os(AUDIT_LOG_ENV).filter(|v| !v.is_empty()) {
+            Some(path) => Self::open(Path::new(&path)),
+            None => Ok(Self::stdout()),
+        }
+    }
+
+    /// Write `record` as one JSON line
+    ///
+    /// Failures are logged; they never fail the exec.
+    pub fn record(&self, record: &AuditRecord) {
+        let line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Failed to serialize audit record: {e}");
+                return;
+            }
+        };
+
+        match &self.sink {
+            AuditSink::Stdout => println!("{line}"),
+            AuditSink::File(file) => {
+                let mut file = match file.lock() {
+                    Ok(guard) => guard,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+                if let Err(e) = writeln!(file, "{line}").and_then(|()| file.flush()) {
+                    error!("Failed to write audit record: {e}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_started_and_completed_records_are_hashed() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("audit.jsonl");
+        let log = AuditLog::open(&path)?;
+
+        let started = AuditRecord::started(
+            Some("key_001"),
+            Some("session-1"),
+            "conv-1",
+            "delete the tests",
+            "read-only",
+        )
+        .with_limits(Some(600_000), 3_600_000);
+        log.record(&started);
+        log.record(&started.completed("completed", Duration::from_millis(42)));
+
+        let lines = std::fs::read_to_string(&path)?
+            .lines()
+            .map(serde_json::from_str::<Value>)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"], "task_started");
+        assert_eq!(lines[0]["timeout_ms"], 600_000);
+        assert_eq!(lines[0]["max_lifetime_ms"], 3_600_000);
+        assert_eq!(lines[1]["event"], "task_completed");
+        assert_eq!(lines[1]["status"], "completed");
+        assert_eq!(lines[1]["execution_time_ms"], 42);
+
+        for line in &lines {
+            assert_eq!(line["api_key_hash"], sha256_hex("key_001"));
+            assert_eq!(line["prompt_sha256"], sha256_hex("delete the tests"));
+            assert_eq!(line["session_id"], "session-1");
+            assert_eq!(line["sandbox_policy"], "read-only");
+            let raw = line.to_string();
+            assert!(!raw.contains("key_001") && !raw.contains("delete the tests"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,457 @@
+//! Signed completion callbacks
+//!
+//! When an exec request carries a `callback_url`, the terminal payload is
+//! POSTed there once the turn finishes. The body is signed with
+//! HMAC-SHA256 using `CODEX_WEBHOOK_SECRET` and the signature is sent as
+//! `X-Signature: sha256=<hex>`, so receivers can verify the call came from
+//! this gateway.
+//!
+//! Callbacks only go to public addresses: a host resolving to a loopback,
+//! private, link-local (cloud metadata) or other internal address is refused,
+//! both when the exec is accepted and again before delivery, and redirects
+//! are not followed. `CODEX_CALLBACK_ALLOWED_HOSTS` (comma-separated) limits
+//! callbacks to the listed hosts instead, which are trusted wherever they
+//! resolve.
+
+use crate::error::GatewayError;
+use crate::error::GatewayResult;
+use axum::http::header;
+use hmac::Hmac;
+use hmac::Mac;
+use serde_json::Value;
+use sha2::Sha256;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tracing::info;
+use tracing::warn;
+use url::Host;
+use url::Url;
+
+/// Header carrying the HMAC-SHA256 signature of the callback body
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Environment variable listing the only hosts callbacks may be sent to
+pub const CALLBACK_ALLOWED_HOSTS_ENV: &str = "CODEX_CALLBACK_ALLOWED_HOSTS";
+
+/// Total delivery attempts, including the first one
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Delay before the first retry; doubled after every failed attempt
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Per-attempt HTTP timeout
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Compute the `X-Signature` value (`sha256=<hex>`) for `body`
+pub fn sign_payload(secret: &str, body: &[u8]) -> GatewayResult<String> {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .map_err(|e| GatewayError::Internal(format!("invalid webhook secret: {e}")))?;
+    mac.update(body);
+    Ok(format!("sha256={:x}", mac.finalize().into_bytes()))
+}
+
+/// Whether `ip` is a globally routable unicast address
+///
+/// Rejects loopback, private, shared (CGNAT), link-local, documentation,
+/// benchmarking, multicast, reserved and unspecified ranges, and IPv6
+/// addresses that embed one of them.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_v4(mapped);
+            }
+            let segments = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()
+                // 2001:db8::/32 documentation, 64:ff9b::/96 NAT64
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8)
+                || (segments[0] == 0x0064 && segments[1] == 0xff9b))
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || a == 0
+        // 100.64.0.0/10 shared address space
+        || (a == 100 && (b & 0xc0) == 64)
+        // 192.0.0.0/24 protocol assignments
+        || (a == 192 && b == 0 && c == 0)
+        // 198.18.0.0/15 benchmarking
+        || (a == 198 && (b & 0xfe) == 18)
+        // 240.0.0.0/4 reserved
+        || a >= 240)
+}
+
+/// `field 'callback_url' ...` validation error
+fn invalid_url(reason: impl std::fmt::Display) -> GatewayError {
+    GatewayError::InvalidRequest(format!("field 'callback_url' {reason}"))
+}
+
+/// Addresses a callback host was checked against, so delivery connects to
+/// exactly those and a second DNS answer cannot point it elsewhere
+#[derive(Debug)]
+struct PinnedHost {
+    domain: String,
+    addrs: Vec<SocketAddr>,
+}
+
+/// HTTP client that delivers signed completion callbacks
+#[derive(Clone)]
+pub struct CallbackClient {
+    secret: Option<String>,
+    /// Hosts callbacks may go to; `None` allows any host with public addresses
+    allowed_hosts: Option<Vec<String>>,
+    initial_backoff: Duration,
+}
+
+impl std::fmt::Debug for CallbackClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallbackClient")
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("allowed_hosts", &self.allowed_hosts)
+            .finish()
+    }
+}
+
+impl CallbackClient {
+    /// Create a client signing with `secret`; callbacks are refused without one
+    pub fn new(secret: Option<String>) -> Self {
+        Self {
+            secret,
+            allowed_hosts: None,
+            initial_backoff: INITIAL_BACKOFF,
+        }
+    }
+
+    /// Only send callbacks to `hosts`, trusting them wherever they resolve
+    pub fn with_allowed_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.allowed_hosts = Some(
+            hosts
+                .into_iter()
+                .map(|host| host.trim().to_ascii_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect(),
+        );
+        self
+    }
+
+    /// Create a client from `CODEX_WEBHOOK_SECRET` and
+    /// `CODEX_CALLBACK_ALLOWED_HOSTS`
+    pub fn from_env() -> Self {
+        let secret = std::env::var("CODEX_WEBHOOK_SECRET")
+            .ok()
+            .filter(|secret| !secret.is_empty());
+        let client = Self::new(secret);
+        match std::env::var(CALLBACK_ALLOWED_HOSTS_ENV) {
+            Ok(hosts) => client.with_allowed_hosts(hosts.split(',').map(str::to_string).collect()),
+            Err(_) => client,
+        }
+    }
+
+    /// Check that `url` may receive callbacks
+    ///
+    /// With an allowlist the host must be on it; otherwise every address the
+    /// host resolves to must be public (see [`is_public_address`]).
+    pub async fn check_url(&self, url: &str) -> GatewayResult<()> {
+        self.resolve(url).await.map(|_| ())
+    }
+
+    /// Check `url` and return the addresses to pin its host to, if any
+    async fn resolve(&self, url: &str) -> GatewayResult<Option<PinnedHost>> {
+        let parsed =
+            Url::parse(url).map_err(|e| invalid_url(format!("is not a valid URL: {e}")))?;
+        let host = parsed
+            .host()
+            .ok_or_else(|| invalid_url("has no host"))?
+            .to_owned();
+        let port = parsed
+            .port_or_known_default()
+            .ok_or_else(|| invalid_url("has no port"))?;
+
+        if let Some(allowed) = &self.allowed_hosts {
+            let name = host.to_string().to_ascii_lowercase();
+            return if allowed.contains(&name) {
+                Ok(None)
+            } else {
+                Err(invalid_url(format!(
+                    "host '{name}' is not in {CALLBACK_ALLOWED_HOSTS_ENV}"
+                )))
+            };
+        }
+
+        let (pinned, addrs) = match host {
+            Host::Ipv4(ip) => (None, vec![SocketAddr::new(ip.into(), port)]),
+            Host::Ipv6(ip) => (None, vec![SocketAddr::new(ip.into(), port)]),
+            Host::Domain(domain) => {
+                let addrs: Vec<SocketAddr> = tokio::net::lookup_host((domain.as_str(), port))
+                    .await
+                    .map_err(|e| invalid_url(format!("host '{domain}' cannot be resolved: {e}")))?
+                    .collect();
+                (Some(domain), addrs)
+            }
+        };
+        if addrs.is_empty() {
+            return Err(invalid_url("host resolves to no address"));
+        }
+        if let Some(addr) = addrs.iter().find(|addr| !is_public_address(addr.ip())) {
+            return Err(invalid_url(format!(
+                "resolves to non-public address {}",
+                addr.ip()
+            )));
+        }
+        Ok(pinned.map(|domain| PinnedHost { domain, addrs }))
+    }
+
+    /// HTTP client for one delivery: no redirects, pinned to `pinned`
+    fn http_client(pinned: Option<&PinnedHost>) -> GatewayResult<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none());
+        if let Some(pinned) = pinned {
+            builder = builder.resolve_to_addrs(&pinned.domain, &pinned.addrs);
+        }
+        builder
+            .build()
+            .map_err(|e| GatewayError::Internal(format!("failed to build callback client: {e}")))
+    }
+
+    /// Whether a signing secret is configured
+    pub fn is_configured(&self) -> bool {
+        self.secret.is_some()
+    }
+
+    /// POST `payload` to `url`, retrying with exponential backoff
+    ///
+    /// Connection errors and 5xx responses are retried up to `MAX_ATTEMPTS`
+    /// times in total; any other non-2xx response, redirects included, fails
+    /// immediately. The host is checked again first, as in [`Self::check_url`].
+    pub async fn deliver(&self, url: &str, payload: &Value) -> GatewayResult<()> {
+        let secret = self
+            .secret
+            .as_deref()
+            .ok_or_else(|| GatewayError::Config("CODEX_WEBHOOK_SECRET is not set".to_string()))?;
+        let body = serde_json::to_vec(payload)?;
+        let signature = sign_payload(secret, &body)?;
+        let pinned = self.resolve(url).await?;
+        let http = Self::http_client(pinned.as_ref())?;
+
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1;
+        loop {
+            let result = http
+                .post(url)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, &signature)
+                .body(body.clone())
+                .send()
+                .await;
+
+            let failure = match result {
+                Ok(response) if response.status().is_success() => {
+                    info!("Callback delivered to {} (attempt {})", url, attempt);
+                    return Ok(());
+                }
+                Ok(response) if response.status().is_server_error() => {
+                    format!("status {}", response.status())
+                }
+                Ok(response) => {
+                    return Err(GatewayError::Internal(format!(
+                        "callback to {url} rejected with status {}",
+                        response.status()
+                    )));
+                }
+                Err(e) => e.to_string(),
+            };
+
+            if attempt >= MAX_ATTEMPTS {
+                return Err(GatewayError::Internal(format!(
+                    "callback to {url} failed after {attempt} attempts: {failure}"
+                )));
+            }
+
+            warn!(
+                "Callback to {} failed (attempt {}): {}; retrying in {:?}",
+                url, attempt, failure, backoff
+            );
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::method;
+    use wiremock::matchers::path;
+
+    /// Client allowed to call the local mock server
+    fn test_client() -> CallbackClient {
+        let mut client = CallbackClient::new(Some("test-secret".to_string()))
+            .with_allowed_hosts(vec!["127.0.0.1".to_string()]);
+        client.initial_backoff = Duration::from_millis(1);
+        client
+    }
+
+    #[tokio::test]
+    async fn test_deliver_signs_body() -> Result<(), Box<dyn std::error::Error>> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let payload = json!({"status": "completed", "conversation_id": "abc"});
+        test_client()
+            .deliver(&format!("{}/hook", server.uri()), &payload)
+            .await?;
+
+        let requests = server.received_requests().await.ok_or("no requests")?;
+        let request = requests.first().ok_or("callback not delivered")?;
+        let signature = request
+            .headers
+            .get(SIGNATURE_HEADER)
+            .ok_or("missing signature")?
+            .to_str()?;
+        assert_eq!(signature, sign_payload("test-secret", &request.body)?);
+        assert_eq!(serde_json::from_slice::<Value>(&request.body)?, payload);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deliver_retries_server_errors() -> Result<(), Box<dyn std::error::Error>> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        test_client()
+            .deliver(&format!("{}/hook", server.uri()), &json!({}))
+            .await?;
+
+        let requests = server.received_requests().await.ok_or("no requests")?;
+        assert_eq!(requests.len(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deliver_does_not_retry_client_errors() -> Result<(), Box<dyn std::error::Error>> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&server)
+            .await;
+
+        let result = test_client()
+            .deliver(&format!("{}/hook", server.uri()), &json!({}))
+            .await;
+
+        assert!(result.is_err());
+        let requests = server.received_requests().await.ok_or("no requests")?;
+        assert_eq!(requests.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_public_address() -> Result<(), std::net::AddrParseError> {
+        for public in ["8.8.8.8", "2001:4860:4860::8888"] {
+            assert!(
+                is_public_address(public.parse()?),
+                "{public} should be public"
+            );
+        }
+        for internal in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fc00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(
+                !is_public_address(internal.parse()?),
+                "{internal} should not be public"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_url_rejects_internal_hosts() {
+        let client = CallbackClient::new(Some("test-secret".to_string()));
+        for url in [
+            "http://127.0.0.1:8080/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.0.0.5/hook",
+            "http://[::1]/hook",
+            "http://localhost/hook",
+        ] {
+            assert!(
+                matches!(
+                    client.check_url(url).await,
+                    Err(GatewayError::InvalidRequest(_))
+                ),
+                "{url} should be rejected"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_allowed_hosts_replace_address_check() -> Result<(), GatewayError> {
+        let client = CallbackClient::new(Some("test-secret".to_string()))
+            .with_allowed_hosts(vec!["Hooks.internal".to_string(), "127.0.0.1".to_string()]);
+
+        client.check_url("https://hooks.internal/done").await?;
+        client.check_url("http://127.0.0.1:9000/done").await?;
+        assert!(matches!(
+            client.check_url("https://example.com/done").await,
+            Err(GatewayError::InvalidRequest(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_payload_known_vector() -> Result<(), GatewayError> {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?")?,
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        Ok(())
+    }
+}
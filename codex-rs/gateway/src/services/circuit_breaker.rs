@@ -0,0 +1,226 @@
+//! Circuit breaker for the agent backend
+//!
+//! When exec turns keep failing (provider down, broken auth, ...) every new
+//! request would wait for an execution slot only to fail the same way. After
+//! `threshold` failures within `window` the breaker opens and execs are
+//! rejected with 503 right away. Once `cooldown` has passed a single probe
+//! turn is let through (half-open): a success closes the breaker, a failure
+//! opens it again.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Breaker state as reported on `/healthz`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Execs run normally
+    Closed,
+    /// Execs are rejected until the cooldown passes
+    Open,
+    /// One probe exec is running to test recovery
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum Inner {
+    Closed { failures: VecDeque<Instant> },
+    Open { since: Instant },
+    HalfOpen { probe_started: Instant },
+}
+
+/// Failure-rate circuit breaker
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: usize,
+    window: Duration,
+    cooldown: Duration,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    /// Open after `threshold` failures within `window`, for `cooldown`
+    ///
+    /// A `threshold` of 0 disables the breaker.
+    pub fn new(threshold: usize, window: Duration, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            cooldown,
+            inner: Mutex::new(Inner::Closed {
+                failures: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Current state, without letting a probe through
+    pub fn state(&self) -> BreakerState {
+        match &*self.inner() {
+            Inner::Closed { .. } => BreakerState::Closed,
+            Inner::Open { since } if since.elapsed() >= self.cooldown => BreakerState::HalfOpen,
+            Inner::Open { .. } => BreakerState::Open,
+            Inner::HalfOpen { .. } => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Whether an exec may start now
+    ///
+    /// Returns how long until the next probe when the breaker rejects it.
+    /// The first call after the cooldown becomes the half-open probe; a probe
+    /// that never reports back is replaced after another cooldown.
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut inner = self.inner();
+        match &*inner {
+            Inner::Closed { .. } => Ok(()),
+            Inner::Open { since }
+            | Inner::HalfOpen {
+                probe_started: since,
+            } => {
+                let elapsed = since.elapsed();
+                if elapsed < self.cooldown {
+                    return Err(self.cooldown - elapsed);
+                }
+                *inner = Inner::HalfOpen {
+                    probe_started: Instant::now(),
+                };
+                Ok(())
+            }
+        }
+    }
+
+    /// Record an exec that reached the agent and completed
+    pub fn record_success(&self) {
+        *self.inner() = Inner::Closed {
+            failures: VecDeque::new(),
+        };
+    }
+
+    /// Give back the half-open probe of an exec that ended without an outcome
+    ///
+    /// For execs rejected before reaching the agent, or stopped for reasons
+    /// that say nothing about the backend, so the next exec can probe
+    /// without waiting out another cooldown.
+    pub fn release_probe(&self) {
+        let mut inner = self.inner();
+        if let Inner::HalfOpen { probe_started } = &*inner {
+            let since = Instant::now()
+                .checked_sub(self.cooldown)
+                .unwrap_or(*probe_started);
+            *inner = Inner::Open { since };
+        }
+    }
+
+    /// Record an exec that failed because of the backend
+    pub fn record_failure(&self) {
+        if self.threshold == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut inner = self.inner();
+        match &mut *inner {
+            Inner::Closed { failures } => {
+                failures.push_back(now);
+                while failures
+                    .front()
+                    .is_some_and(|first| now.duration_since(*first) > self.window)
+                {
+                    failures.pop_front();
+                }
+                if failures.len() >= self.threshold {
+                    tracing::warn!(
+                        "Circuit breaker opened after {} failures within {:?}",
+                        failures.len(),
+                        self.window
+                    );
+                    *inner = Inner::Open { since: now };
+                }
+            }
+            Inner::HalfOpen { .. } => {
+                tracing::warn!("Circuit breaker probe failed; reopening");
+                *inner = Inner::Open { since: now };
+            }
+            Inner::Open { .. } => {}
+        }
+    }
+
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_threshold_and_rejects_fast() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60), Duration::from_secs(60));
+
+        breaker.record_failure();
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(breaker.try_acquire().is_ok());
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+        let retry_in = breaker.try_acquire().unwrap_err();
+        assert!(retry_in <= Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_half_open_probe_success_closes() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60), Duration::ZERO);
+
+        breaker.record_failure();
+        assert!(breaker.try_acquire().is_ok());
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_probe_failure_reopens() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60), Duration::from_millis(20));
+
+        breaker.record_failure();
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(breaker.try_acquire().is_ok());
+        assert!(breaker.try_acquire().is_err(), "only one probe at a time");
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+    }
+
+    #[test]
+    fn test_released_probe_lets_next_exec_probe() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60), Duration::from_millis(20));
+
+        breaker.record_failure();
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(breaker.try_acquire().is_ok());
+        assert!(breaker.try_acquire().is_err());
+
+        breaker.release_probe();
+        assert!(breaker.try_acquire().is_ok(), "next exec takes the probe");
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+    }
+
+    #[test]
+    fn test_zero_threshold_never_opens() {
+        let breaker = CircuitBreaker::new(0, Duration::from_secs(60), Duration::from_secs(60));
+
+        for _ in 0..10 {
+            breaker.record_failure();
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
@@ -6,18 +6,23 @@
 
 use crate::error::GatewayError;
 use crate::error::GatewayResult;
+use crate::services::redaction;
 use chrono::Utc;
 use codex_core::ConversationManager;
+use codex_core::ModelProviderInfo;
+use codex_core::RolloutRecorder;
 use codex_core::auth::AuthManager;
 use codex_core::config::Config as CodexConfig;
 use codex_core::config::ConfigOverrides;
 use codex_core::find_conversation_path_by_id_str;
+use codex_core::parse_cursor;
 use codex_protocol::ConversationId;
 use codex_protocol::models::ContentItem;
 use codex_protocol::models::ResponseItem;
 use codex_protocol::protocol::EventMsg;
 use codex_protocol::protocol::Op;
 use codex_protocol::protocol::SessionConfiguredEvent;
+use codex_protocol::protocol::SessionMetaLine;
 use codex_protocol::protocol::SessionSource;
 use codex_protocol::user_input::UserInput;
 use serde::Serialize;
@@ -25,7 +30,12 @@ use serde_json::Value;
 use serde_json::json;
 use serde_json::to_value;
 use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::Path;
 use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncSeekExt;
+use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
 use tracing::debug;
 use tracing::info;
@@ -54,6 +64,57 @@ pub struct CodexService {
     request_counter: Arc<Mutex<u64>>,
 }
 
+/// Environment variable naming this deployment to the model provider
+pub const CLIENT_NAME_ENV: &str = "CODEX_CLIENT_NAME";
+
+/// Client name used when `CODEX_CLIENT_NAME` is unset
+pub const DEFAULT_CLIENT_NAME: &str = "codex_gateway";
+
+/// How the gateway identifies itself to codex-core
+///
+/// `name` becomes the codex-core originator, sent as the `originator` header
+/// and User-Agent prefix on model requests and recorded in every rollout, so
+/// each deployment can be told apart in provider-side analytics.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClientInfo {
+    pub name: String,
+    pub version: String,
+}
+
+impl ClientInfo {
+    /// Client info with `name` (or the default) and the gateway crate version
+    pub fn new(name: Option<&str>) -> Self {
+        let name = name
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(DEFAULT_CLIENT_NAME);
+        Self {
+            name: name.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Client info named by `CODEX_CLIENT_NAME`
+    pub fn from_env() -> Self {
+        Self::new(std::env::var(CLIENT_NAME_ENV).ok().as_deref())
+    }
+
+    /// Make `name` the process-wide codex-core originator
+    ///
+    /// Must run before the first conversation is created; later calls and
+    /// names that are not valid header values are logged and ignored.
+    pub fn install(&self) {
+        match codex_core::default_client::set_default_originator(self.name.clone()) {
+            Ok(()) => info!(
+                "Codex client identity: {}/{}",
+                codex_core::default_client::originator().value,
+                self.version
+            ),
+            Err(e) => warn!("Could not set Codex client name {}: {:?}", self.name, e),
+        }
+    }
+}
+
 /// Status básico de uma sessão ativa exposto via JSON-RPC
 #[derive(Debug, Clone, Serialize)]
 pub struct SessionStatus {
@@ -62,6 +123,29 @@ pub struct SessionStatus {
     pub metadata: Option<SessionConfiguredEvent>,
 }
 
+/// Summary of a recorded session returned by `GET /sessions`
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub conversation_id: ConversationId,
+    /// Gateway session ID, when the conversation is still active in this process
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// `"active"` or `"stored"`
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+/// One page of [`SessionSummary`] entries
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionsPage {
+    pub sessions: Vec<SessionSummary>,
+    /// Opaque token for the next page, `None` when this is the last one
+    pub next_cursor: Option<String>,
+}
+
 impl std::fmt::Debug for CodexService {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("CodexService")
@@ -291,6 +375,29 @@ impl CodexService {
         &self,
         session_id: Option<&str>,
     ) -> GatewayResult<ConversationId> {
+        self.get_or_create_conversation_with_provider(session_id, None)
+            .await
+            .map(|(conversation_id, _)| conversation_id)
+    }
+
+    /// Whether `session_id` already has a conversation
+    pub async fn has_session(&self, session_id: &str) -> bool {
+        self.active_conversations
+            .lock()
+            .await
+            .contains_key(session_id)
+    }
+
+    /// Get or create a conversation, using `provider` for new conversations
+    ///
+    /// Returns the conversation and whether this call created it. The model
+    /// provider is fixed when a conversation is created, so `provider` is
+    /// ignored when the session already has one.
+    pub async fn get_or_create_conversation_with_provider(
+        &self,
+        session_id: Option<&str>,
+        provider: Option<&str>,
+    ) -> GatewayResult<(ConversationId, bool)> {
         let mut conversations = self.active_conversations.lock().await;
 
         match session_id {
@@ -300,35 +407,59 @@ impl CodexService {
                         "Found existing conversation for session {}: {}",
                         sid, conversation_id
                     );
-                    Ok(*conversation_id)
+                    if let Some(provider) = provider {
+                        debug!(
+                            "Ignoring provider {} for existing session {}",
+                            provider, sid
+                        );
+                    }
+                    Ok((*conversation_id, false))
                 } else {
                     // Create new conversation via ConversationManager
                     warn!(
                         "No existing conversation found for session {}, creating new one",
                         sid
                     );
-                    let conversation_id = self.create_new_conversation().await?;
+                    let conversation_id = self.create_new_conversation(provider).await?;
                     conversations.insert(sid.to_string(), conversation_id);
                     info!(
                         "Created new conversation for session {}: {}",
                         sid, conversation_id
                     );
-                    Ok(conversation_id)
+                    Ok((conversation_id, true))
                 }
             }
             None => {
                 // Create ephemeral conversation for session-less requests
                 warn!("Creating ephemeral conversation for session-less request");
-                let conversation_id = self.create_new_conversation().await?;
+                let conversation_id = self.create_new_conversation(provider).await?;
                 debug!("Created ephemeral conversation: {}", conversation_id);
-                Ok(conversation_id)
+                Ok((conversation_id, true))
             }
         }
     }
 
+    /// Look up a model provider configured in Codex (built-in or `config.toml`)
+    pub fn model_provider(&self, provider_id: &str) -> GatewayResult<ModelProviderInfo> {
+        self.codex_config
+            .model_providers
+            .get(provider_id)
+            .cloned()
+            .ok_or_else(|| {
+                GatewayError::InvalidRequest(format!("unknown model provider: {provider_id}"))
+            })
+    }
+
     /// Create a new conversation using ConversationManager from codex-core
-    async fn create_new_conversation(&self) -> GatewayResult<ConversationId> {
-        let config = (*self.codex_config).clone();
+    async fn create_new_conversation(
+        &self,
+        provider: Option<&str>,
+    ) -> GatewayResult<ConversationId> {
+        let mut config = (*self.codex_config).clone();
+        if let Some(provider_id) = provider {
+            config.model_provider = self.model_provider(provider_id)?;
+            config.model_provider_id = provider_id.to_string();
+        }
         let new_conversation = {
             let manager = self.conversation_manager.lock().await;
             manager.new_conversation(config).await.map_err(|e| {
@@ -400,6 +531,161 @@ impl CodexService {
         }
     }
 
+    /// Delete everything kept for a session
+    ///
+    /// Drops the in-memory session mapping and conversation and removes the
+    /// rollout file codex-core recorded under `CODEX_HOME/sessions`. `id` may
+    /// be a session ID known to this process or a conversation ID, so rollouts
+    /// can still be purged after a restart. Returns `false` when nothing matched.
+    pub async fn purge_session(&self, id: &str) -> GatewayResult<bool> {
+        let mapped = self.cancel_session(id).await?;
+        if let Some(conversation_id) = mapped {
+            self.conversation_manager
+                .lock()
+                .await
+                .remove_conversation(&conversation_id)
+                .await;
+        }
+
+        let conversation_id_str = mapped
+            .map(|conversation_id| conversation_id.to_string())
+            .unwrap_or_else(|| id.to_string());
+        let removed_rollout =
+            remove_rollout(&self.codex_config.codex_home, &conversation_id_str).await?;
+
+        Ok(mapped.is_some() || removed_rollout)
+    }
+
+    /// Delete the rollout of a conversation while keeping it active
+    ///
+    /// Used for `persist: false` execs on conversations they created; an
+    /// existing session's rollout also holds its earlier turns. The recorder
+    /// keeps writing to the unlinked file, so later turns of the conversation
+    /// are not stored either.
+    pub async fn discard_rollout(&self, conversation_id: ConversationId) -> GatewayResult<bool> {
+        remove_rollout(&self.codex_config.codex_home, &conversation_id.to_string()).await
+    }
+
+    /// Rewrite the rollout of a conversation with its secrets redacted
+    ///
+    /// Used for `CODEX_REDACT_OUTPUT` once a turn has ended. Returns `false`
+    /// when there is no rollout or it holds no secrets.
+    pub async fn redact_rollout(&self, conversation_id: ConversationId) -> GatewayResult<bool> {
+        redact_rollout_file(&self.codex_config.codex_home, &conversation_id.to_string()).await
+    }
+
+    /// Interrupt the turn currently running for a session
+    ///
+    /// Returns `None` when the session has no active conversation.
+    pub async fn interrupt_session(
+        &self,
+        session_id: &str,
+    ) -> GatewayResult<Option<ConversationId>> {
+        let conversation_id = {
+            let conversations = self.active_conversations.lock().await;
+            match conversations.get(session_id) {
+                Some(id) => *id,
+                None => return Ok(None),
+            }
+        };
+
+        let conversation = {
+            let manager = self.conversation_manager.lock().await;
+            manager
+                .get_conversation(conversation_id)
+                .await
+                .map_err(|e| GatewayError::Internal(format!("failed to get conversation: {e}")))?
+        };
+
+        conversation
+            .submit(Op::Interrupt)
+            .await
+            .map_err(|e| GatewayError::Internal(format!("failed to submit interrupt: {e}")))?;
+
+        info!(
+            "Interrupt submitted: session_id={}, conversation_id={}",
+            session_id, conversation_id
+        );
+        Ok(Some(conversation_id))
+    }
+
+    /// Feed user input into the turn currently running for a session
+    ///
+    /// codex-core injects the input into the running turn. Callers must
+    /// check that a turn is running: with none, this would start a new turn
+    /// nobody is collecting events for. Returns `None` when the session has
+    /// no active conversation.
+    pub async fn send_input(
+        &self,
+        session_id: &str,
+        text: &str,
+    ) -> GatewayResult<Option<ConversationId>> {
+        let conversation_id = {
+            let conversations = self.active_conversations.lock().await;
+            match conversations.get(session_id) {
+                Some(id) => *id,
+                None => return Ok(None),
+            }
+        };
+
+        let conversation = {
+            let manager = self.conversation_manager.lock().await;
+            manager
+                .get_conversation(conversation_id)
+                .await
+                .map_err(|e| GatewayError::Internal(format!("failed to get conversation: {e}")))?
+        };
+
+        conversation
+            .submit(Op::UserInput {
+                items: vec![UserInput::Text {
+                    text: text.to_string(),
+                }],
+            })
+            .await
+            .map_err(|e| GatewayError::Internal(format!("failed to submit input: {e}")))?;
+
+        info!(
+            "Input submitted: session_id={}, conversation_id={}, len={}",
+            session_id,
+            conversation_id,
+            text.len()
+        );
+        Ok(Some(conversation_id))
+    }
+
+    /// List recorded sessions, newest first
+    ///
+    /// Reads the rollouts codex-core recorded under `CODEX_HOME/sessions` and
+    /// marks the ones still bound to a session ID in this process as active.
+    /// `cursor` is the `next_cursor` returned by the previous page.
+    pub async fn list_sessions(
+        &self,
+        limit: usize,
+        cursor: Option<&str>,
+    ) -> GatewayResult<SessionsPage> {
+        let mut page = list_recorded_sessions(
+            &self.codex_config.codex_home,
+            limit,
+            cursor,
+            &self.codex_config.model_provider_id,
+        )
+        .await?;
+
+        let conversations = self.active_conversations.lock().await;
+        for summary in &mut page.sessions {
+            if let Some((session_id, _)) = conversations
+                .iter()
+                .find(|(_, conversation_id)| **conversation_id == summary.conversation_id)
+            {
+                summary.session_id = Some(session_id.clone());
+                summary.status = "active";
+            }
+        }
+
+        Ok(page)
+    }
+
     /// Get public accessor to conversation manager
     pub fn conversation_manager(&self) -> &Arc<Mutex<ConversationManager>> {
         &self.conversation_manager
@@ -495,3 +781,286 @@ impl CodexService {
         Ok(conversation_id)
     }
 }
+
+/// Sessions started by the gateway are recorded with this source
+const GATEWAY_SESSION_SOURCES: &[SessionSource] = &[SessionSource::Exec];
+
+/// Delete the rollout file of `conversation_id` under `codex_home`
+///
+/// Returns whether a file was found and removed.
+async fn remove_rollout(codex_home: &Path, conversation_id: &str) -> GatewayResult<bool> {
+    let rollout_path = find_conversation_path_by_id_str(codex_home, conversation_id)
+        .await
+        .map_err(|e| GatewayError::Internal(format!("Failed to find conversation path: {e}")))?;
+
+    let Some(path) = rollout_path else {
+        return Ok(false);
+    };
+    tokio::fs::remove_file(&path).await.map_err(|e| {
+        GatewayError::Internal(format!("Failed to delete rollout {}: {e}", path.display()))
+    })?;
+    info!(
+        "Deleted rollout for conversation {}: {:?}",
+        conversation_id, path
+    );
+    Ok(true)
+}
+
+/// Redact the secrets in the rollout of `conversation_id` under `codex_home`
+///
+/// The file is rewritten in place rather than replaced: the recorder keeps
+/// it open in append mode, so later turns still land at its end.
+async fn redact_rollout_file(codex_home: &Path, conversation_id: &str) -> GatewayResult<bool> {
+    let rollout_path = find_conversation_path_by_id_str(codex_home, conversation_id)
+        .await
+        .map_err(|e| GatewayError::Internal(format!("Failed to find conversation path: {e}")))?;
+
+    let Some(path) = rollout_path else {
+        return Ok(false);
+    };
+    let failed = |e: std::io::Error| {
+        GatewayError::Internal(format!("Failed to redact rollout {}: {e}", path.display()))
+    };
+    let mut file = tokio::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .await
+        .map_err(failed)?;
+    let mut jsonl = String::new();
+    file.read_to_string(&mut jsonl).await.map_err(failed)?;
+    let Some(redacted) = redaction::redact_jsonl(&jsonl) else {
+        return Ok(false);
+    };
+    file.set_len(0).await.map_err(failed)?;
+    file.seek(SeekFrom::Start(0)).await.map_err(failed)?;
+    file.write_all(redacted.as_bytes()).await.map_err(failed)?;
+    file.flush().await.map_err(failed)?;
+    info!(
+        "Redacted rollout for conversation {}: {:?}",
+        conversation_id, path
+    );
+    Ok(true)
+}
+
+/// Read one page of rollout summaries from `codex_home`, newest first
+async fn list_recorded_sessions(
+    codex_home: &Path,
+    limit: usize,
+    cursor: Option<&str>,
+    default_provider: &str,
+) -> GatewayResult<SessionsPage> {
+    let cursor = cursor
+        .map(|token| {
+            parse_cursor(token)
+                .ok_or_else(|| GatewayError::InvalidRequest(format!("invalid cursor: {token}")))
+        })
+        .transpose()?;
+
+    let page = RolloutRecorder::list_conversations(
+        codex_home,
+        limit,
+        cursor.as_ref(),
+        GATEWAY_SESSION_SOURCES,
+        None,
+        default_provider,
+    )
+    .await
+    .map_err(|e| GatewayError::Internal(format!("failed to list sessions: {e}")))?;
+
+    let sessions =
+        page.items
+            .into_iter()
+            .filter_map(|item| {
+                let meta = item.head.first().and_then(|first| {
+                    serde_json::from_value::<SessionMetaLine>(first.clone()).ok()
+                })?;
+                Some(SessionSummary {
+                    conversation_id: meta.meta.id,
+                    session_id: None,
+                    status: "stored",
+                    created_at: item.created_at,
+                    updated_at: item.updated_at,
+                })
+            })
+            .collect();
+
+    let next_cursor = page
+        .next_cursor
+        .and_then(|cursor| serde_json::to_value(cursor).ok())
+        .and_then(|value| value.as_str().map(str::to_string));
+
+    Ok(SessionsPage {
+        sessions,
+        next_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn write_rollout(codex_home: &Path, ts: &str, id: &str) -> std::io::Result<()> {
+        let dir = codex_home
+            .join("sessions")
+            .join("2025")
+            .join("01")
+            .join("02");
+        std::fs::create_dir_all(&dir)?;
+        let mut file = std::fs::File::create(dir.join(format!("rollout-{ts}-{id}.jsonl")))?;
+        let meta = json!({
+            "timestamp": ts,
+            "type": "session_meta",
+            "payload": {
+                "id": id,
+                "timestamp": ts,
+                "instructions": null,
+                "cwd": ".",
+                "originator": "codex_gateway",
+                "cli_version": "0.0.0",
+                "source": "exec",
+            },
+        });
+        let user_message = json!({
+            "timestamp": ts,
+            "type": "event_msg",
+            "payload": { "type": "user_message", "message": "hello", "kind": "plain" },
+        });
+        writeln!(file, "{meta}")?;
+        writeln!(file, "{user_message}")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_info_uses_configured_name_and_crate_version() -> Result<(), serde_json::Error> {
+        let info = ClientInfo::new(Some("acme-prod"));
+        assert_eq!(
+            serde_json::to_value(&info)?,
+            json!({"name": "acme-prod", "version": env!("CARGO_PKG_VERSION")})
+        );
+
+        assert_eq!(ClientInfo::new(None).name, DEFAULT_CLIENT_NAME);
+        assert_eq!(ClientInfo::new(Some("  ")).name, DEFAULT_CLIENT_NAME);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_recorded_sessions_newest_first_with_cursor()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let temp = TempDir::new()?;
+        let ids = [
+            "00000000-0000-4000-8000-000000000001",
+            "00000000-0000-4000-8000-000000000002",
+            "00000000-0000-4000-8000-000000000003",
+        ];
+        write_rollout(temp.path(), "2025-01-02T10-00-00", ids[0])?;
+        write_rollout(temp.path(), "2025-01-02T11-00-00", ids[1])?;
+        write_rollout(temp.path(), "2025-01-02T12-00-00", ids[2])?;
+
+        let first = list_recorded_sessions(temp.path(), 2, None, "openai").await?;
+        let first_ids: Vec<String> = first
+            .sessions
+            .iter()
+            .map(|s| s.conversation_id.to_string())
+            .collect();
+        assert_eq!(first_ids, vec![ids[2], ids[1]]);
+        assert!(first.sessions.iter().all(|s| s.status == "stored"));
+
+        let cursor = first.next_cursor.ok_or("expected a next cursor")?;
+        let second = list_recorded_sessions(temp.path(), 2, Some(&cursor), "openai").await?;
+        let second_ids: Vec<String> = second
+            .sessions
+            .iter()
+            .map(|s| s.conversation_id.to_string())
+            .collect();
+        assert_eq!(second_ids, vec![ids[0]]);
+        assert!(second.next_cursor.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_recorded_sessions_rejects_bad_cursor()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let temp = TempDir::new()?;
+
+        let result = list_recorded_sessions(temp.path(), 10, Some("garbage"), "openai").await;
+
+        assert!(matches!(result, Err(GatewayError::InvalidRequest(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_redact_rollout_rewrites_secrets_in_place()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let temp = TempDir::new()?;
+        let id = "00000000-0000-4000-8000-000000000003";
+        write_rollout(temp.path(), "2025-01-02T12-00-00", id)?;
+        assert!(!redact_rollout_file(temp.path(), id).await?);
+
+        let path = find_conversation_path_by_id_str(temp.path(), id)
+            .await?
+            .ok_or("rollout should exist")?;
+        // Held open in append mode, as codex-core's recorder does
+        let mut recorder = std::fs::OpenOptions::new().append(true).open(&path)?;
+        let leak = json!({
+            "type": "event_msg",
+            "payload": { "type": "agent_message", "message": "OPENAI_API_KEY=sk-proj-abcdefghijklmnop1234" },
+        });
+        writeln!(recorder, "{leak}")?;
+
+        assert!(redact_rollout_file(temp.path(), id).await?);
+        writeln!(recorder, "{}", json!({"type": "turn_context"}))?;
+
+        let jsonl = std::fs::read_to_string(&path)?;
+        assert!(!jsonl.contains("sk-proj-"));
+        let lines: Vec<Value> = jsonl
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[2]["payload"]["message"],
+            "OPENAI_API_KEY=***REDACTED***"
+        );
+        assert_eq!(lines[3]["type"], "turn_context");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remove_rollout_deletes_only_that_conversation()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let temp = TempDir::new()?;
+        let kept = "00000000-0000-4000-8000-000000000001";
+        let discarded = "00000000-0000-4000-8000-000000000002";
+        write_rollout(temp.path(), "2025-01-02T10-00-00", kept)?;
+        write_rollout(temp.path(), "2025-01-02T11-00-00", discarded)?;
+
+        assert!(remove_rollout(temp.path(), discarded).await?);
+        assert!(!remove_rollout(temp.path(), discarded).await?);
+
+        let remaining = list_recorded_sessions(temp.path(), 10, None, "openai").await?;
+        let ids: Vec<String> = remaining
+            .sessions
+            .iter()
+            .map(|s| s.conversation_id.to_string())
+            .collect();
+        assert_eq!(ids, vec![kept]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_model_provider_rejects_unknown_provider() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let service = CodexService::new().await?;
+
+        assert!(service.model_provider("openai").is_ok());
+        assert!(matches!(
+            service.model_provider("no-such-provider"),
+            Err(GatewayError::InvalidRequest(_))
+        ));
+        Ok(())
+    }
+}
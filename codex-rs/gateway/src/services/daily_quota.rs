@@ -0,0 +1,229 @@
+//! Daily exec quota per API key
+//!
+//! Execs are billed per run, so on top of the per-minute rate limit each
+//! key may start at most `CODEX_DAILY_QUOTA` execs in any rolling 24 hours.
+//! Past that, execs get 429 with `"code": "quota_exceeded"` and a
+//! `Retry-After` pointing at when the oldest run in the window ages out.
+//!
+//! Run times are saved under `CODEX_HOME`, next to the session rollouts, so
+//! restarting the gateway does not reset anyone's quota.
+
+use crate::error::GatewayError;
+use crate::error::GatewayResult;
+use chrono::DateTime;
+use chrono::TimeDelta;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use tracing::warn;
+
+/// Length of the rolling quota window
+pub const QUOTA_WINDOW: TimeDelta = TimeDelta::hours(24);
+
+/// Start times of each key's runs within the window, oldest first
+type Runs = HashMap<String, VecDeque<DateTime<Utc>>>;
+
+/// Rolling 24-hour exec quota per API key
+#[derive(Debug, Default)]
+pub struct DailyQuota {
+    /// Runs allowed per key per window; 0 disables the quota
+    limit: usize,
+    /// File the runs are saved to; kept in memory only when `None`
+    path: Option<PathBuf>,
+    runs: Mutex<Runs>,
+}
+
+impl DailyQuota {
+    /// Allow `limit` runs per key per window, saving them to `path`
+    ///
+    /// Runs saved by a previous process are loaded from `path`; an
+    /// unreadable file is logged and treated as empty.
+    pub fn new(limit: usize, path: Option<PathBuf>) -> Self {
+        let runs = match &path {
+            Some(path) if limit > 0 => load(path),
+            _ => Runs::new(),
+        };
+        Self {
+            limit,
+            path,
+            runs: Mutex::new(runs),
+        }
+    }
+
+    /// Whether a limit is configured
+    pub fn is_enabled(&self) -> bool {
+        self.limit > 0
+    }
+
+    /// Count `runs` execs against `key_id`, or reject all of them with
+    /// [`GatewayError::QuotaExceeded`] if they don't fit in today's quota
+    pub fn try_consume(&self, key_id: &str, runs: usize) -> GatewayResult<()> {
+        self.try_consume_at(key_id, runs, Utc::now())
+    }
+
+    /// [`DailyQuota::try_consume`] as of `now`
+    pub fn try_consume_at(
+        &self,
+        key_id: &str,
+        runs: usize,
+        now: DateTime<Utc>,
+    ) -> GatewayResult<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let mut all_runs = self.runs();
+        prune(&mut all_runs, now);
+        let key_runs = all_runs.entry(key_id.to_string()).or_default();
+        if key_runs.len() + runs > self.limit {
+            let oldest = key_runs.front().copied().unwrap_or(now);
+            let retry_after = (oldest + QUOTA_WINDOW - now).num_seconds().max(1);
+            return Err(GatewayError::QuotaExceeded {
+                message: format!(
+                    "API key '{key_id}' has used {} of its {} daily execs",
+                    key_runs.len(),
+                    self.limit
+                ),
+                retry_after_secs: u64::try_from(retry_after).unwrap_or(1),
+            });
+        }
+
+        key_runs.extend(std::iter::repeat_n(now, runs));
+        self.save(&all_runs);
+        Ok(())
+    }
+
+    /// Take back the latest `runs` execs counted against `key_id`, for execs
+    /// that were rejected right after being counted
+    pub fn refund(&self, key_id: &str, runs: usize) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut all_runs = self.runs();
+        if let Some(key_runs) = all_runs.get_mut(key_id) {
+            key_runs.truncate(key_runs.len().saturating_sub(runs));
+            if key_runs.is_empty() {
+                all_runs.remove(key_id);
+            }
+        }
+        self.save(&all_runs);
+    }
+
+    /// Execs `key_id` may still start in the window ending at `now`
+    pub fn remaining_at(&self, key_id: &str, now: DateTime<Utc>) -> usize {
+        let mut all_runs = self.runs();
+        prune(&mut all_runs, now);
+        let used = all_runs.get(key_id).map_or(0, VecDeque::len);
+        self.limit.saturating_sub(used)
+    }
+
+    /// Write `runs` to the quota file, replacing it atomically
+    fn save(&self, runs: &Runs) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = serde_json::to_vec(runs)
+            .map_err(std::io::Error::other)
+            .and_then(|json| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let tmp = path.with_extension("json.tmp");
+                std::fs::write(&tmp, json)?;
+                std::fs::rename(&tmp, path)
+            });
+        if let Err(e) = result {
+            warn!("Failed to save daily quota to {}: {e}", path.display());
+        }
+    }
+
+    fn runs(&self) -> MutexGuard<'_, Runs> {
+        self.runs
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Drop runs that left the window ending at `now`, and keys with none left
+fn prune(runs: &mut Runs, now: DateTime<Utc>) {
+    let cutoff = now - QUOTA_WINDOW;
+    runs.retain(|_, key_runs| {
+        while key_runs.front().is_some_and(|started| *started <= cutoff) {
+            key_runs.pop_front();
+        }
+        !key_runs.is_empty()
+    });
+}
+
+/// Runs saved at `path`, or none if it is missing or unreadable
+fn load(path: &Path) -> Runs {
+    match std::fs::read(path) {
+        Ok(json) => serde_json::from_slice(&json).unwrap_or_else(|e| {
+            warn!(
+                "Ignoring unreadable daily quota file {}: {e}",
+                path.display()
+            );
+            Runs::new()
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Runs::new(),
+        Err(e) => {
+            warn!("Failed to read daily quota file {}: {e}", path.display());
+            Runs::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_resets_after_window() -> Result<(), Box<dyn std::error::Error>> {
+        let quota = DailyQuota::new(2, None);
+        let start = DateTime::parse_from_rfc3339("2026-10-15T09:00:00Z")?.with_timezone(&Utc);
+
+        quota.try_consume_at("key-a", 1, start)?;
+        quota.try_consume_at("key-a", 1, start + TimeDelta::hours(6))?;
+        let err = quota
+            .try_consume_at("key-a", 1, start + TimeDelta::hours(23))
+            .err()
+            .ok_or("third exec within 24h should be rejected")?;
+        assert!(matches!(
+            err,
+            GatewayError::QuotaExceeded {
+                retry_after_secs: 3600,
+                ..
+            }
+        ));
+        // Other keys have their own quota
+        quota.try_consume_at("key-b", 1, start + TimeDelta::hours(23))?;
+
+        // Once the first run ages out there is room for one more, not two
+        let later = start + QUOTA_WINDOW + TimeDelta::seconds(1);
+        assert_eq!(quota.remaining_at("key-a", later), 1);
+        quota.try_consume_at("key-a", 1, later)?;
+        assert!(quota.try_consume_at("key-a", 1, later).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quota_survives_restart() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("gateway").join("daily_quota.json");
+        let now = Utc::now();
+
+        let quota = DailyQuota::new(3, Some(path.clone()));
+        quota.try_consume_at("key-a", 2, now)?;
+        drop(quota);
+
+        let restarted = DailyQuota::new(3, Some(path));
+        assert_eq!(restarted.remaining_at("key-a", now), 1);
+        assert!(restarted.try_consume_at("key-a", 2, now).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+//! Queue of execs waiting for an execution slot
+//!
+//! The exec semaphore already hands out permits in FIFO order; this queue
+//! only mirrors that order so a waiter can be told its position. Each waiter
+//! holds a [`QueueTicket`] while it waits and drops it once it has a permit
+//! (or gives up), so positions stay accurate when clients disconnect.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// Execs waiting for an execution slot, oldest first
+#[derive(Debug, Default)]
+pub struct ExecQueue {
+    next_ticket: AtomicU64,
+    waiting: Mutex<VecDeque<u64>>,
+}
+
+/// Place of one exec in the [`ExecQueue`], released when dropped
+#[derive(Debug)]
+pub struct QueueTicket {
+    queue: Arc<ExecQueue>,
+    ticket: u64,
+}
+
+impl ExecQueue {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Join the back of the queue
+    pub fn enter(self: &Arc<Self>) -> QueueTicket {
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+        self.waiting().push_back(ticket);
+        QueueTicket {
+            queue: Arc::clone(self),
+            ticket,
+        }
+    }
+
+    /// Number of execs waiting
+    pub fn len(&self) -> usize {
+        self.waiting().len()
+    }
+
+    /// Whether no exec is waiting
+    pub fn is_empty(&self) -> bool {
+        self.waiting().is_empty()
+    }
+
+    fn waiting(&self) -> MutexGuard<'_, VecDeque<u64>> {
+        self.waiting
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl QueueTicket {
+    /// 1-based position in the queue; 1 means next in line
+    pub fn position(&self) -> usize {
+        self.queue
+            .waiting()
+            .iter()
+            .position(|ticket| *ticket == self.ticket)
+            .map_or(1, |index| index + 1)
+    }
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        self.queue.waiting().retain(|ticket| *ticket != self.ticket);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_decrease_as_waiters_leave() {
+        let queue = Arc::new(ExecQueue::new());
+        let first = queue.enter();
+        let second = queue.enter();
+        let third = queue.enter();
+        assert_eq!(
+            (first.position(), second.position(), third.position()),
+            (1, 2, 3)
+        );
+
+        drop(first);
+        assert_eq!((second.position(), third.position()), (1, 2));
+
+        // A waiter that gives up frees its place as well
+        let fourth = queue.enter();
+        drop(third);
+        assert_eq!((second.position(), fourth.position()), (1, 2));
+
+        drop(second);
+        assert_eq!(fourth.position(), 1);
+        drop(fourth);
+        assert!(queue.is_empty());
+    }
+}
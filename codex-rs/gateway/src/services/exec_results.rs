@@ -0,0 +1,214 @@
+//! Final results of finished exec turns
+//!
+//! `/exec` and `/exec/batch` record the outcome of every turn that ran under
+//! a `session_id`, so `GET /sessions/{id}/result` can return it without the
+//! client keeping the full event list. Secrets in the final message and
+//! error are redacted before the result is kept.
+
+use crate::services::redaction;
+use codex_exec::exec_events::Usage;
+use codex_protocol::protocol::EventMsg;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// Most sessions whose last result is kept; the oldest are dropped first
+const MAX_RESULTS: usize = 1000;
+
+/// Compact outcome of a session's most recent turn
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecResult {
+    pub session_id: String,
+    pub conversation_id: String,
+    /// Final status of the turn ("completed", "failed", ...)
+    pub status: String,
+    pub execution_time_ms: u64,
+    /// RFC 3339 time the turn finished
+    pub finished_at: String,
+    /// Last message the agent sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub final_message: Option<String>,
+    /// Token usage reported by `turn.completed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Retries made while the turn ran
+    pub retries: ExecRetries,
+    /// Files the turn created or modified, relative to `workdir` when inside it
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub created_files: Vec<String>,
+    /// Working directory of the turn; artifacts are only served from here
+    #[serde(skip)]
+    pub workdir: Option<PathBuf>,
+    /// API key the turn ran under; other keys cannot see the session
+    #[serde(skip)]
+    pub key_id: Option<String>,
+}
+
+/// Retries made during one turn, by category
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecRetries {
+    /// Times codex-core reconnected a dropped or failed model response stream
+    pub model_stream: u32,
+}
+
+impl ExecRetries {
+    /// Count `msg` if it reports a retry
+    pub fn record(&mut self, msg: &EventMsg) {
+        if let EventMsg::StreamError(_) = msg {
+            self.model_stream += 1;
+        }
+    }
+}
+
+/// Last exec result per session
+#[derive(Debug, Default)]
+pub struct ExecResults {
+    next_seq: AtomicU64,
+    entries: Mutex<HashMap<String, (u64, ExecResult)>>,
+}
+
+impl ExecResults {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `result` as the latest one for its session
+    pub fn record(&self, mut result: ExecResult) {
+        for text in [&mut result.final_message, &mut result.error]
+            .into_iter()
+            .flatten()
+        {
+            redaction::redact_in_place(text);
+        }
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+
+        let mut entries = self.entries();
+        entries.insert(result.session_id.clone(), (seq, result));
+        if entries.len() > MAX_RESULTS
+            && let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, (seq, _))| *seq)
+                .map(|(session_id, _)| session_id.clone())
+        {
+            entries.remove(&oldest);
+        }
+    }
+
+    /// Latest result recorded for `session_id`
+    pub fn get(&self, session_id: &str) -> Option<ExecResult> {
+        self.entries()
+            .get(session_id)
+            .map(|(_, result)| result.clone())
+    }
+
+    /// API key of the latest result whose session or conversation ID is `id`
+    pub fn owner(&self, id: &str) -> Option<String> {
+        self.entries()
+            .values()
+            .find(|(_, result)| result.session_id == id || result.conversation_id == id)
+            .and_then(|(_, result)| result.key_id.clone())
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<String, (u64, ExecResult)>> {
+        match self.entries.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codex_protocol::protocol::StreamErrorEvent;
+    use codex_protocol::protocol::TaskCompleteEvent;
+
+    fn result(session_id: &str, status: &str) -> ExecResult {
+        ExecResult {
+            session_id: session_id.to_string(),
+            conversation_id: "conv".to_string(),
+            status: status.to_string(),
+            execution_time_ms: 10,
+            finished_at: "2025-01-02T12:00:00+00:00".to_string(),
+            final_message: None,
+            usage: None,
+            error: None,
+            retries: ExecRetries::default(),
+            created_files: Vec::new(),
+            workdir: None,
+            key_id: None,
+        }
+    }
+
+    #[test]
+    fn test_latest_result_wins_and_oldest_is_evicted() {
+        let results = ExecResults::new();
+
+        results.record(result("session-a", "failed"));
+        results.record(result("session-a", "completed"));
+        assert_eq!(
+            results.get("session-a").map(|r| r.status),
+            Some("completed".to_string())
+        );
+
+        for i in 0..MAX_RESULTS {
+            results.record(result(&format!("other-{i}"), "completed"));
+        }
+        assert!(results.get("session-a").is_none());
+        assert!(results.get("other-0").is_some());
+    }
+
+    #[test]
+    fn test_retries_count_stream_reconnects() -> Result<(), Box<dyn std::error::Error>> {
+        let mut retries = ExecRetries::default();
+        for attempt in 1..=2 {
+            retries.record(&EventMsg::StreamError(StreamErrorEvent {
+                message: format!("Reconnecting... {attempt}/5"),
+            }));
+        }
+        retries.record(&EventMsg::TaskComplete(TaskCompleteEvent {
+            last_agent_message: None,
+        }));
+        assert_eq!(retries.model_stream, 2);
+
+        let results = ExecResults::new();
+        results.record(ExecResult {
+            retries,
+            ..result("session-a", "completed")
+        });
+        let recorded = serde_json::to_value(results.get("session-a"))?;
+        assert_eq!(recorded["retries"]["model_stream"], 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recorded_result_is_redacted() {
+        let results = ExecResults::new();
+
+        results.record(ExecResult {
+            final_message: Some(
+                "Set OPENAI_API_KEY=sk-proj-abcdefghijklmnop1234 and rerun".to_string(),
+            ),
+            error: Some("401 for Bearer abcdefghijklmnopqrstuvwxyz".to_string()),
+            ..result("session-a", "completed")
+        });
+
+        let recorded = results.get("session-a");
+        assert_eq!(
+            recorded.as_ref().and_then(|r| r.final_message.as_deref()),
+            Some("Set OPENAI_API_KEY=***REDACTED*** and rerun")
+        );
+        assert_eq!(
+            recorded.as_ref().and_then(|r| r.error.as_deref()),
+            Some("401 for ***REDACTED***")
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! Idempotency-Key support for `/exec`
+//!
+//! The first request carrying a key runs normally and its reply is cached;
+//! repeats within the TTL, including ones that arrive while the first is
+//! still running, wait for and replay that reply instead of starting
+//! another turn.
+//!
+//! Keys belong to the API key that sent them, so two callers picking the
+//! same value never see each other's replies. A repeat must send the same
+//! body as the first request; a different body is rejected rather than
+//! answered with the first request's reply.
+
+use crate::error::GatewayError;
+use crate::error::GatewayResult;
+use axum::http::StatusCode;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Digest;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::OnceCell;
+
+/// Request header carrying the client's idempotency key
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header set when a cached reply is replayed
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Longest accepted idempotency key
+pub const MAX_KEY_LEN: usize = 255;
+
+/// Status and JSON body of a finished exec
+pub type CachedReply = (StatusCode, Value);
+
+/// Slot shared by every request with the same key
+pub type ReplySlot = Arc<OnceCell<CachedReply>>;
+
+/// When a key was first used, the fingerprint of its request, and its slot
+type Entry = (Instant, String, ReplySlot);
+
+/// (API key id, idempotency key) → reply cache with expiry
+#[derive(Debug)]
+pub struct IdempotencyCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<(String, String), Entry>>,
+}
+
+impl IdempotencyCache {
+    /// Create a cache keeping replies for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the reply slot for `owner`'s `key`, creating it on first use
+    ///
+    /// `fingerprint` identifies the request body (see [`request_fingerprint`]).
+    /// A key already used with another body fails with
+    /// [`GatewayError::IdempotencyKeyReused`], unless its first request
+    /// failed and nothing is waiting on it, in which case the key starts over.
+    /// Expired keys are pruned on every call, so a key can be reused once
+    /// its TTL has passed.
+    pub fn slot(&self, owner: &str, key: &str, fingerprint: &str) -> GatewayResult<ReplySlot> {
+        let now = Instant::now();
+        let mut entries = match self.entries.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        entries.retain(|_, (created, _, _)| now.duration_since(*created) < self.ttl);
+        let id = (owner.to_string(), key.to_string());
+        if let Some((_, used_with, slot)) = entries.get(&id) {
+            if used_with == fingerprint {
+                return Ok(Arc::clone(slot));
+            }
+            let abandoned = slot.get().is_none() && Arc::strong_count(slot) == 1;
+            if !abandoned {
+                return Err(GatewayError::IdempotencyKeyReused(format!(
+                    "Idempotency-Key '{key}' was already used with a different request body"
+                )));
+            }
+        }
+
+        let slot = Arc::new(OnceCell::new());
+        entries.insert(id, (now, fingerprint.to_string(), Arc::clone(&slot)));
+        Ok(slot)
+    }
+}
+
+/// SHA-256 of `request` serialized with its object keys sorted
+///
+/// Sorting makes the fingerprint independent of map iteration order, so
+/// the same body always gives the same fingerprint.
+pub fn request_fingerprint(request: &impl Serialize) -> GatewayResult<String> {
+    let canonical = sort_keys(serde_json::to_value(request)?).to_string();
+    Ok(format!("{:x}", Sha256::digest(canonical.as_bytes())))
+}
+
+/// `value` with the keys of every object in sorted order
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut fields: Vec<(String, Value)> = map.into_iter().collect();
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name, sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sort_keys).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    #[tokio::test]
+    async fn test_same_key_runs_once() -> Result<(), Box<dyn std::error::Error>> {
+        let cache = Arc::new(IdempotencyCache::new(Duration::from_secs(60)));
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let request = |cache: Arc<IdempotencyCache>, runs: Arc<AtomicUsize>| async move {
+            cache
+                .slot("key_001", "key-1", "body-1")?
+                .get_or_try_init(|| async {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    Ok::<_, GatewayError>((StatusCode::OK, json!({"status": "completed"})))
+                })
+                .await
+                .cloned()
+        };
+
+        let (first, second) = tokio::join!(
+            request(Arc::clone(&cache), Arc::clone(&runs)),
+            request(Arc::clone(&cache), Arc::clone(&runs)),
+        );
+
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(first?, second?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_key_gets_a_fresh_slot() -> Result<(), GatewayError> {
+        let cache = IdempotencyCache::new(Duration::ZERO);
+
+        let first = cache.slot("key_001", "key-1", "body-1")?;
+        let _ = first.set((StatusCode::OK, json!({})));
+        let second = cache.slot("key_001", "key-1", "body-1")?;
+
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert!(second.get().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_keys_are_per_api_key_and_body() -> Result<(), GatewayError> {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+
+        let first = cache.slot("key_001", "key-1", "body-1")?;
+        let _ = first.set((StatusCode::OK, json!({"final": "secret"})));
+
+        // Another API key with the same header value gets its own slot
+        let other = cache.slot("key_002", "key-1", "body-1")?;
+        assert!(!Arc::ptr_eq(&first, &other));
+        assert!(other.get().is_none());
+
+        // The same API key reusing the header value with another body is rejected
+        let reused = cache.slot("key_001", "key-1", "body-2");
+        assert!(matches!(reused, Err(GatewayError::IdempotencyKeyReused(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_key_can_be_reused_with_another_body() -> Result<(), GatewayError> {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+
+        // The first request failed, so its slot never got a reply
+        drop(cache.slot("key_001", "key-1", "body-1")?);
+
+        let retry = cache.slot("key_001", "key-1", "body-2")?;
+        assert!(retry.get().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_key_order() -> Result<(), GatewayError> {
+        let a = json!({"prompt": "hi", "variables": {"a": "1", "b": "2"}});
+        let b = json!({"variables": {"b": "2", "a": "1"}, "prompt": "hi"});
+
+        assert_eq!(request_fingerprint(&a)?, request_fingerprint(&b)?);
+        assert_ne!(
+            request_fingerprint(&a)?,
+            request_fingerprint(&json!({"prompt": "bye"}))?
+        );
+        Ok(())
+    }
+}
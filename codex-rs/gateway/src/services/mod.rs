@@ -1,3 +1,27 @@
+pub mod active_execs;
+pub mod audit_log;
+pub mod callback;
+pub mod circuit_breaker;
 pub mod codex_service;
+pub mod daily_quota;
+pub mod exec_queue;
+pub mod exec_results;
+pub mod idempotency;
+pub mod prompt_allowlist;
+pub mod prompt_store;
+pub mod provider_credentials;
+pub mod redaction;
+pub mod smoke_check;
 
+pub use active_execs::ActiveExecs;
+pub use audit_log::AuditLog;
+pub use callback::CallbackClient;
+pub use circuit_breaker::CircuitBreaker;
 pub use codex_service::CodexService;
+pub use daily_quota::DailyQuota;
+pub use exec_queue::ExecQueue;
+pub use exec_results::ExecResults;
+pub use idempotency::IdempotencyCache;
+pub use prompt_allowlist::PromptAllowlist;
+pub use prompt_store::PromptStore;
+pub use smoke_check::SmokeCheck;
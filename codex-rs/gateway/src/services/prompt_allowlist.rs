@@ -0,0 +1,160 @@
+//! Allowlist of vetted prompts
+//!
+//! Locked-down deployments may only run a fixed set of prompts or prompt
+//! templates. `CODEX_PROMPT_ALLOWLIST` names a file with one regular
+//! expression per line (blank lines and `#` comments are skipped); a prompt
+//! must match one of them in full, or the exec is rejected with 403 before
+//! any agent turn starts. Without the variable every prompt is allowed.
+
+use crate::error::GatewayError;
+use crate::error::GatewayResult;
+use regex_lite::Regex;
+use std::path::Path;
+use tracing::info;
+
+/// Environment variable naming the allowlist file
+pub const PROMPT_ALLOWLIST_ENV: &str = "CODEX_PROMPT_ALLOWLIST";
+
+/// Prompt patterns a request must match
+#[derive(Debug, Default)]
+pub struct PromptAllowlist {
+    /// `None` allows every prompt
+    patterns: Option<Vec<Regex>>,
+}
+
+impl PromptAllowlist {
+    /// Allowlist that accepts every prompt
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Parse allowlist `contents`, one pattern per line
+    ///
+    /// An invalid pattern, or a file with no patterns at all, is a
+    /// configuration error rather than a silently open or closed gateway.
+    pub fn parse(contents: &str) -> GatewayResult<Self> {
+        let mut patterns = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let pattern = Regex::new(&format!("^(?:{line})$")).map_err(|e| {
+                GatewayError::Config(format!(
+                    "invalid prompt allowlist pattern on line {}: {e}",
+                    index + 1
+                ))
+            })?;
+            patterns.push(pattern);
+        }
+
+        if patterns.is_empty() {
+            return Err(GatewayError::Config(
+                "prompt allowlist contains no patterns".to_string(),
+            ));
+        }
+        Ok(Self {
+            patterns: Some(patterns),
+        })
+    }
+
+    /// Read the allowlist file at `path`
+    pub fn load(path: &Path) -> GatewayResult<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            GatewayError::Config(format!(
+                "failed to read prompt allowlist {}: {e}",
+                path.display()
+            ))
+        })?;
+        let allowlist = Self::parse(&contents)?;
+        info!(
+            "Prompt allowlist loaded from {} ({} patterns)",
+            path.display(),
+            allowlist.patterns.as_ref().map_or(0, Vec::len)
+        );
+        Ok(allowlist)
+    }
+
+    /// Allowlist from `CODEX_PROMPT_ALLOWLIST`, or one allowing every prompt
+    pub fn from_env() -> GatewayResult<Self> {
+        match std::env::var_os(PROMPT_ALLOWLIST_ENV).filter(|v| !v.is_empty()) {
+            Some(path) => Self::load(Path::new(&path)),
+            None => Ok(Self::allow_all()),
+        }
+    }
+
+    /// Reject `prompt` with 403 unless it matches an allowed pattern
+    pub fn check(&self, prompt: &str) -> GatewayResult<()> {
+        let Some(patterns) = &self.patterns else {
+            return Ok(());
+        };
+        if patterns.iter().any(|pattern| pattern.is_match(prompt)) {
+            return Ok(());
+        }
+        Err(GatewayError::Forbidden(
+            "prompt is not in this gateway's prompt allowlist".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    const ALLOWLIST: &str = "\
+# Vetted prompts
+Run the test suite
+
+Summarize the diff in [a-z0-9./_-]+
+";
+
+    #[test]
+    fn test_allowed_prompt_passes() -> Result<(), GatewayError> {
+        let allowlist = PromptAllowlist::parse(ALLOWLIST)?;
+
+        allowlist.check("Run the test suite")?;
+        allowlist.check("Summarize the diff in src/main.rs")?;
+        PromptAllowlist::allow_all().check("anything at all")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_disallowed_prompt_is_forbidden() -> Result<(), GatewayError> {
+        let allowlist = PromptAllowlist::parse(ALLOWLIST)?;
+
+        for prompt in [
+            "Delete the repository",
+            "Run the test suite and then push to main",
+            "Summarize the diff in src/main.rs; rm -rf /",
+        ] {
+            let err = allowlist.check(prompt).unwrap_err();
+            assert_eq!(err.into_response().status(), StatusCode::FORBIDDEN);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_allowlist_fails_to_load() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("allowlist.txt");
+
+        std::fs::write(&path, "Run the test suite\nSummarize (unclosed\n")?;
+        let err = PromptAllowlist::load(&path).unwrap_err();
+        assert!(matches!(err, GatewayError::Config(ref msg) if msg.contains("line 2")));
+
+        std::fs::write(&path, "# only comments\n\n")?;
+        assert!(matches!(
+            PromptAllowlist::load(&path),
+            Err(GatewayError::Config(_))
+        ));
+
+        let missing = dir.path().join("missing.txt");
+        assert!(matches!(
+            PromptAllowlist::load(&missing),
+            Err(GatewayError::Config(_))
+        ));
+        Ok(())
+    }
+}
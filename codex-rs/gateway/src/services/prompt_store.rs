@@ -0,0 +1,272 @@
+//! Prompts stored in Google Cloud Storage
+//!
+//! `/exec` accepts `prompt_ref: "gs://bucket/object"` instead of an inline
+//! prompt, so large prompts do not have to fit the request body. Objects are
+//! read through the GCS JSON API with the instance's service account token
+//! from the metadata server. When `STORAGE_EMULATOR_HOST` is set, requests go
+//! to that emulator instead, without authentication.
+
+use crate::error::GatewayError;
+use crate::error::GatewayResult;
+use axum::http::StatusCode;
+use axum::http::header;
+use serde::Deserialize;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::Mutex;
+use tracing::debug;
+use url::Url;
+
+/// Public GCS JSON API endpoint
+const GCS_ENDPOINT: &str = "https://storage.googleapis.com";
+
+/// Metadata server URL returning the default service account's access token
+const METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+/// Per-request HTTP timeout
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Tokens are refreshed this long before they expire
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Longest object name GCS accepts, in bytes
+const MAX_OBJECT_NAME_LEN: usize = 1024;
+
+/// Bucket and object named by a `gs://` reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcsRef {
+    pub bucket: String,
+    pub object: String,
+}
+
+/// Parse and check a `gs://bucket/object` reference
+pub fn parse_gcs_ref(reference: &str) -> GatewayResult<GcsRef> {
+    let invalid = |reason: &str| {
+        GatewayError::InvalidRequest(format!(
+            "field 'prompt_ref' must be gs://bucket/object ({reason}), got '{reference}'"
+        ))
+    };
+
+    let rest = reference
+        .strip_prefix("gs://")
+        .ok_or_else(|| invalid("missing gs:// scheme"))?;
+    let (bucket, object) = rest
+        .split_once('/')
+        .ok_or_else(|| invalid("missing object name"))?;
+
+    let bucket_ok = (3..=222).contains(&bucket.len())
+        && bucket
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.'));
+    if !bucket_ok {
+        return Err(invalid("invalid bucket name"));
+    }
+    if object.is_empty() || object.len() > MAX_OBJECT_NAME_LEN {
+        return Err(invalid("invalid object name"));
+    }
+
+    Ok(GcsRef {
+        bucket: bucket.to_string(),
+        object: object.to_string(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+/// Reads prompt objects from GCS
+#[derive(Debug)]
+pub struct PromptStore {
+    http: reqwest::Client,
+    endpoint: String,
+    /// Whether requests carry a metadata-server access token
+    authenticated: bool,
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl PromptStore {
+    /// Create a store talking to `endpoint`
+    pub fn new(endpoint: impl Into<String>, authenticated: bool) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+
+        Self {
+            http,
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            authenticated,
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Create a store for GCS, or for `STORAGE_EMULATOR_HOST` when set
+    pub fn from_env() -> Self {
+        match std::env::var("STORAGE_EMULATOR_HOST")
+            .ok()
+            .filter(|v| !v.is_empty())
+        {
+            Some(host) if host.starts_with("http") => Self::new(host, false),
+            Some(host) => Self::new(format!("http://{host}"), false),
+            None => Self::new(GCS_ENDPOINT, true),
+        }
+    }
+
+    /// Fetch the object named by `reference` as UTF-8 text of at most `max_bytes`
+    pub async fn fetch(&self, reference: &str, max_bytes: usize) -> GatewayResult<String> {
+        let gcs_ref = parse_gcs_ref(reference)?;
+
+        let mut url = Url::parse(&self.endpoint)
+            .map_err(|e| GatewayError::Config(format!("invalid GCS endpoint: {e}")))?;
+        url.path_segments_mut()
+            .map_err(|_| GatewayError::Config("invalid GCS endpoint".to_string()))?
+            .extend(["storage", "v1", "b", &gcs_ref.bucket, "o", &gcs_ref.object]);
+        url.query_pairs_mut().append_pair("alt", "media");
+
+        let mut request = self.http.get(url);
+        if self.authenticated {
+            request = request.bearer_auth(self.access_token().await?);
+        }
+        debug!("Fetching prompt from {}", reference);
+        let response = request
+            .send()
+            .await
+            .map_err(|e| GatewayError::ServiceUnavailable(format!("GCS request failed: {e}")))?;
+
+        match response.status() {
+            status if status.is_success() => {}
+            StatusCode::NOT_FOUND => {
+                return Err(GatewayError::InvalidRequest(format!(
+                    "prompt_ref {reference} does not exist"
+                )));
+            }
+            StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED => {
+                return Err(GatewayError::Forbidden(format!(
+                    "gateway may not read prompt_ref {reference}"
+                )));
+            }
+            status => {
+                return Err(GatewayError::ServiceUnavailable(format!(
+                    "GCS returned {status} for {reference}"
+                )));
+            }
+        }
+
+        let too_large = || {
+            GatewayError::PromptTooLarge(format!(
+                "prompt_ref {reference} exceeds {max_bytes} bytes"
+            ))
+        };
+        let declared_len = response
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+        if declared_len.is_some_and(|len| len > max_bytes) {
+            return Err(too_large());
+        }
+
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| GatewayError::ServiceUnavailable(format!("GCS read failed: {e}")))?;
+        if body.len() > max_bytes {
+            return Err(too_large());
+        }
+        String::from_utf8(body.to_vec()).map_err(|_| {
+            GatewayError::InvalidRequest(format!("prompt_ref {reference} is not UTF-8 text"))
+        })
+    }
+
+    /// Service account token from the metadata server, cached until near expiry
+    async fn access_token(&self) -> GatewayResult<String> {
+        let mut cached = self.token.lock().await;
+        if let Some((token, expires_at)) = cached.as_ref()
+            && Instant::now() < *expires_at
+        {
+            return Ok(token.clone());
+        }
+
+        let unavailable =
+            |e: String| GatewayError::ServiceUnavailable(format!("GCS credentials: {e}"));
+        let body = self
+            .http
+            .get(METADATA_TOKEN_URL)
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| unavailable(e.to_string()))?
+            .bytes()
+            .await
+            .map_err(|e| unavailable(e.to_string()))?;
+        let token: MetadataToken =
+            serde_json::from_slice(&body).map_err(|e| unavailable(e.to_string()))?;
+
+        let lifetime = Duration::from_secs(token.expires_in).saturating_sub(TOKEN_EXPIRY_MARGIN);
+        *cached = Some((token.access_token.clone(), Instant::now() + lifetime));
+        Ok(token.access_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::method;
+    use wiremock::matchers::path;
+    use wiremock::matchers::query_param;
+
+    #[test]
+    fn test_parse_gcs_ref() -> Result<(), GatewayError> {
+        assert_eq!(
+            parse_gcs_ref("gs://my-bucket/prompts/big.txt")?,
+            GcsRef {
+                bucket: "my-bucket".to_string(),
+                object: "prompts/big.txt".to_string(),
+            }
+        );
+
+        for bad in [
+            "https://my-bucket/a",
+            "gs://my-bucket",
+            "gs://my-bucket/",
+            "gs://My_Bucket/a",
+            "gs://ab/a",
+        ] {
+            assert!(
+                matches!(parse_gcs_ref(bad), Err(GatewayError::InvalidRequest(_))),
+                "{bad} should be rejected"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_reads_object_and_enforces_size() -> Result<(), Box<dyn std::error::Error>> {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/storage/v1/b/my-bucket/o/prompts%2Fbig.txt"))
+            .and(query_param("alt", "media"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("write a parser"))
+            .mount(&server)
+            .await;
+        let store = PromptStore::new(server.uri(), false);
+
+        let prompt = store.fetch("gs://my-bucket/prompts/big.txt", 1024).await?;
+        assert_eq!(prompt, "write a parser");
+
+        let too_large = store.fetch("gs://my-bucket/prompts/big.txt", 4).await;
+        assert!(matches!(too_large, Err(GatewayError::PromptTooLarge(_))));
+
+        let missing = store.fetch("gs://my-bucket/missing.txt", 1024).await;
+        assert!(matches!(missing, Err(GatewayError::InvalidRequest(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+//! Model provider credential checks
+//!
+//! A provider whose API key is missing or mangled only fails once a turn
+//! reaches it, deep inside the event stream. These checks look at each
+//! provider configured in Codex up front: whether its `env_key` is set (or,
+//! for OpenAI-auth providers, whether an API key or stored login exists) and
+//! whether the value looks like a key for the providers we recognize. The
+//! result is logged at startup and reported on `/healthz`.
+
+use crate::error::GatewayError;
+use crate::error::GatewayResult;
+use codex_core::ModelProviderInfo;
+use codex_core::auth::CodexAuth;
+use codex_core::auth::read_openai_api_key_from_env;
+use codex_core::config::Config as CodexConfig;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use tracing::info;
+use tracing::warn;
+
+/// Key prefixes of recognized providers, by environment variable
+const KEY_PREFIXES: &[(&str, &str)] = &[
+    ("ANTHROPIC_API_KEY", "sk-ant-"),
+    ("OPENAI_API_KEY", "sk-"),
+    ("OPENROUTER_API_KEY", "sk-or-"),
+    ("GROQ_API_KEY", "gsk_"),
+];
+
+/// Provider API key variables whose presence is logged at startup
+pub const WELL_KNOWN_KEYS: &[&str] = &[
+    "ANTHROPIC_API_KEY",
+    "OPENAI_API_KEY",
+    "OPENROUTER_API_KEY",
+    "GOOGLE_API_KEY",
+];
+
+/// Shortest value accepted as an API key
+const MIN_KEY_LEN: usize = 16;
+
+/// Whether a provider can authenticate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialStatus {
+    /// A credential is present and looks valid
+    Configured,
+    /// The provider needs a credential and none is set
+    Missing,
+    /// A credential is set but does not look like a key for this provider
+    Malformed,
+    /// The provider needs no credential (e.g. a local model server)
+    NotRequired,
+}
+
+/// Check one API key value read from `env_key`
+fn key_looks_valid(env_key: &str, value: &str) -> bool {
+    if value.len() < MIN_KEY_LEN || value.chars().any(|c| c.is_whitespace() || c == '"') {
+        return false;
+    }
+    KEY_PREFIXES
+        .iter()
+        .find(|(key, _)| *key == env_key)
+        .is_none_or(|(_, prefix)| value.starts_with(prefix))
+}
+
+/// Credential status of every provider in `providers`
+///
+/// `env` reads an environment variable; `openai_login` tells whether an
+/// OpenAI API key or stored ChatGPT login is available to providers with
+/// `requires_openai_auth`.
+pub fn check_credentials(
+    providers: &HashMap<String, ModelProviderInfo>,
+    openai_login: bool,
+    env: impl Fn(&str) -> Option<String>,
+) -> BTreeMap<String, CredentialStatus> {
+    providers
+        .iter()
+        .map(|(id, provider)| {
+            let status = if provider.experimental_bearer_token.is_some() {
+                CredentialStatus::Configured
+            } else if let Some(env_key) = &provider.env_key {
+                match env(env_key).filter(|v| !v.trim().is_empty()) {
+                    Some(value) if key_looks_valid(env_key, &value) => CredentialStatus::Configured,
+                    Some(_) => CredentialStatus::Malformed,
+                    None => CredentialStatus::Missing,
+                }
+            } else if provider.requires_openai_auth {
+                if openai_login {
+                    CredentialStatus::Configured
+                } else {
+                    CredentialStatus::Missing
+                }
+            } else {
+                CredentialStatus::NotRequired
+            };
+            (id.clone(), status)
+        })
+        .collect()
+}
+
+/// Credential status of every provider in the Codex config, from the process
+/// environment and the stored Codex login
+pub fn from_env(config: &CodexConfig) -> BTreeMap<String, CredentialStatus> {
+    let openai_login = read_openai_api_key_from_env().is_some()
+        || CodexAuth::from_auth_storage(&config.codex_home, config.cli_auth_credentials_store_mode)
+            .ok()
+            .flatten()
+            .is_some();
+    check_credentials(&config.model_providers, openai_login, |key| {
+        std::env::var(key).ok()
+    })
+}
+
+/// [`WELL_KNOWN_KEYS`] that `env` has a non-empty value for
+pub fn well_known_keys_set(env: impl Fn(&str) -> Option<String>) -> Vec<&'static str> {
+    WELL_KNOWN_KEYS
+        .iter()
+        .copied()
+        .filter(|key| env(key).is_some_and(|v| !v.trim().is_empty()))
+        .collect()
+}
+
+/// Log which providers can authenticate and which [`WELL_KNOWN_KEYS`] are set
+///
+/// With `require` set, fails when no provider that needs a credential has a
+/// valid one, so a misconfigured deployment stops at startup instead of
+/// failing every turn.
+pub fn report(
+    statuses: &BTreeMap<String, CredentialStatus>,
+    keys_set: &[&str],
+    require: bool,
+) -> GatewayResult<()> {
+    let with = |wanted: CredentialStatus| {
+        statuses
+            .iter()
+            .filter(|(_, status)| **status == wanted)
+            .map(|(id, _)| id.as_str())
+            .collect::<Vec<_>>()
+    };
+    let configured = with(CredentialStatus::Configured);
+    let malformed = with(CredentialStatus::Malformed);
+
+    info!("Model providers with credentials: {:?}", configured);
+    info!("Provider API keys in the environment: {:?}", keys_set);
+    if !malformed.is_empty() {
+        warn!(
+            "Model providers with malformed credentials: {:?}",
+            malformed
+        );
+    }
+
+    if require && configured.is_empty() {
+        let keys = if keys_set.is_empty() {
+            format!("none of {} is set", WELL_KNOWN_KEYS.join(", "))
+        } else {
+            format!("set: {}", keys_set.join(", "))
+        };
+        return Err(GatewayError::Config(format!(
+            "no model provider has valid credentials (missing: {:?}, malformed: {:?}); {keys}",
+            with(CredentialStatus::Missing),
+            malformed
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codex_core::WireApi;
+
+    fn provider(env_key: Option<&str>, requires_openai_auth: bool) -> ModelProviderInfo {
+        ModelProviderInfo {
+            name: "test".to_string(),
+            base_url: None,
+            env_key: env_key.map(str::to_string),
+            env_key_instructions: None,
+            experimental_bearer_token: None,
+            wire_api: WireApi::default(),
+            query_params: None,
+            http_headers: None,
+            env_http_headers: None,
+            request_max_retries: None,
+            stream_max_retries: None,
+            stream_idle_timeout_ms: None,
+            requires_openai_auth,
+        }
+    }
+
+    #[test]
+    fn test_reports_configured_missing_and_malformed() -> Result<(), Box<dyn std::error::Error>> {
+        let providers = HashMap::from([
+            ("openai".to_string(), provider(None, true)),
+            (
+                "anthropic".to_string(),
+                provider(Some("ANTHROPIC_API_KEY"), false),
+            ),
+            (
+                "mistral".to_string(),
+                provider(Some("MISTRAL_API_KEY"), false),
+            ),
+            ("groq".to_string(), provider(Some("GROQ_API_KEY"), false)),
+            ("oss".to_string(), provider(None, false)),
+        ]);
+        let env = |key: &str| match key {
+            "ANTHROPIC_API_KEY" => Some("sk-ant-0123456789abcdef".to_string()),
+            "GROQ_API_KEY" => Some("sk-not-a-groq-key-0123".to_string()),
+            _ => None,
+        };
+
+        let statuses = check_credentials(&providers, false, env);
+        assert_eq!(statuses["openai"], CredentialStatus::Missing);
+        assert_eq!(statuses["anthropic"], CredentialStatus::Configured);
+        assert_eq!(statuses["mistral"], CredentialStatus::Missing);
+        assert_eq!(statuses["groq"], CredentialStatus::Malformed);
+        assert_eq!(statuses["oss"], CredentialStatus::NotRequired);
+        assert!(report(&statuses, &well_known_keys_set(env), true).is_ok());
+
+        let statuses = check_credentials(&providers, true, |_| None);
+        assert_eq!(statuses["openai"], CredentialStatus::Configured);
+        assert_eq!(
+            serde_json::to_value(&statuses)?["oss"],
+            serde_json::json!("not_required")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_require_fails_without_any_credential() {
+        let providers = HashMap::from([(
+            "anthropic".to_string(),
+            provider(Some("ANTHROPIC_API_KEY"), false),
+        )]);
+
+        let statuses = check_credentials(&providers, false, |_| Some("sk-ant-short".to_string()));
+        assert_eq!(statuses["anthropic"], CredentialStatus::Malformed);
+        assert!(report(&statuses, &["ANTHROPIC_API_KEY"], false).is_ok());
+        assert!(matches!(
+            report(&statuses, &["ANTHROPIC_API_KEY"], true),
+            Err(GatewayError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_require_names_the_keys_to_set() {
+        let providers = HashMap::from([
+            ("openai".to_string(), provider(None, true)),
+            (
+                "openrouter".to_string(),
+                provider(Some("OPENROUTER_API_KEY"), false),
+            ),
+        ]);
+        let env = |key: &str| (key == "GOOGLE_API_KEY").then(|| "  ".to_string());
+
+        let statuses = check_credentials(&providers, false, env);
+        let keys_set = well_known_keys_set(env);
+        assert!(keys_set.is_empty());
+
+        let message = match report(&statuses, &keys_set, true) {
+            Err(GatewayError::Config(message)) => message,
+            other => panic!("expected a config error, got {other:?}"),
+        };
+        assert!(message.contains(
+            "none of ANTHROPIC_API_KEY, OPENAI_API_KEY, OPENROUTER_API_KEY, GOOGLE_API_KEY is set"
+        ));
+        assert!(message.contains("openrouter"));
+    }
+}
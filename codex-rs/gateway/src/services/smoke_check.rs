@@ -0,0 +1,115 @@
+//! Cached result of the deep readiness probe
+//!
+//! `/healthz/deep` runs a trivial exec through the whole pipeline, which
+//! costs a model call. The result is kept for a short TTL so frequent probes
+//! don't each start one, and probes that arrive while a smoke exec is
+//! running wait for it instead of starting their own.
+
+use chrono::Utc;
+use serde::Serialize;
+use std::future::Future;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+/// How long a smoke exec result is reused
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+/// Outcome of one smoke exec
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SmokeResult {
+    /// Whether the exec completed
+    pub ok: bool,
+    pub duration_ms: u64,
+    /// RFC 3339 time the exec finished
+    pub checked_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Last smoke exec result, reused until it is older than the TTL
+#[derive(Debug)]
+pub struct SmokeCheck {
+    ttl: Duration,
+    last: Mutex<Option<(Instant, SmokeResult)>>,
+}
+
+impl SmokeCheck {
+    /// Reuse each result for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Latest result, running `smoke` first unless one is younger than the TTL
+    ///
+    /// Also returns whether the result came from the cache.
+    pub async fn check<F, Fut>(&self, smoke: F) -> (SmokeResult, bool)
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(), String>>,
+    {
+        let mut last = self.last.lock().await;
+        if let Some((finished_at, result)) = last.as_ref()
+            && finished_at.elapsed() < self.ttl
+        {
+            return (result.clone(), true);
+        }
+
+        let started_at = Instant::now();
+        let outcome = smoke().await;
+        let result = SmokeResult {
+            ok: outcome.is_ok(),
+            duration_ms: u64::try_from(started_at.elapsed().as_millis()).unwrap_or(u64::MAX),
+            checked_at: Utc::now().to_rfc3339(),
+            error: outcome.err(),
+        };
+        *last = Some((Instant::now(), result.clone()));
+        (result, false)
+    }
+}
+
+impl Default for SmokeCheck {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_result_is_cached_until_ttl() {
+        let check = SmokeCheck::new(Duration::from_secs(60));
+
+        let (result, cached) = check.check(|| async { Ok(()) }).await;
+        assert!(result.ok && !cached);
+
+        // A broken backend is not noticed until the cached success expires
+        let (result, cached) = check
+            .check(|| async { Err("should not run".to_string()) })
+            .await;
+        assert!(result.ok && cached);
+    }
+
+    #[tokio::test]
+    async fn test_failing_smoke_exec_is_reported() {
+        let check = SmokeCheck::new(Duration::ZERO);
+
+        let (result, cached) = check
+            .check(|| async { Err("exec ended with status failed: 401 Unauthorized".to_string()) })
+            .await;
+        assert!(!result.ok && !cached);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("exec ended with status failed: 401 Unauthorized")
+        );
+
+        let (result, _) = check.check(|| async { Ok(()) }).await;
+        assert!(result.ok);
+        assert_eq!(result.error, None);
+    }
+}
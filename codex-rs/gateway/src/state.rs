@@ -2,10 +2,28 @@
 
 use crate::config::GatewayConfig;
 use crate::error::GatewayError;
+use crate::metrics::ExecMetrics;
+use crate::services::ActiveExecs;
+use crate::services::AuditLog;
+use crate::services::CallbackClient;
+use crate::services::CircuitBreaker;
 use crate::services::CodexService;
+use crate::services::DailyQuota;
+use crate::services::ExecQueue;
+use crate::services::ExecResults;
+use crate::services::IdempotencyCache;
+use crate::services::PromptAllowlist;
+use crate::services::PromptStore;
+use crate::services::SmokeCheck;
 use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 use tokio::runtime::Handle;
 use tokio::runtime::Runtime;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio::sync::TryAcquireError;
 
 /// Shared application state passed to all handlers
 #[derive(Debug, Clone)]
@@ -14,12 +32,39 @@ pub struct AppState {
     pub config: Arc<GatewayConfig>,
     /// Codex service for processing AI requests
     pub codex_service: Arc<CodexService>,
+    /// Permits bounding how many agent turns run at the same time
+    pub exec_permits: Arc<Semaphore>,
+    /// Execs waiting for one of `exec_permits`, for queue position reports
+    pub exec_queue: Arc<ExecQueue>,
+    /// Exec counters and durations served on `/metrics`
+    pub metrics: Arc<ExecMetrics>,
+    /// Delivers signed completion callbacks for `callback_url` requests
+    pub callbacks: Arc<CallbackClient>,
+    /// Exec turns currently running, listed on `/sessions/active`
+    pub active_execs: Arc<ActiveExecs>,
+    /// `/exec` replies cached by `Idempotency-Key`
+    pub idempotency: Arc<IdempotencyCache>,
+    /// Last exec result per session, served on `/sessions/{id}/result`
+    pub exec_results: Arc<ExecResults>,
+    /// Rejects execs with 503 while the agent backend keeps failing
+    pub circuit_breaker: Arc<CircuitBreaker>,
+    /// Reads `prompt_ref` objects from GCS
+    pub prompt_store: Arc<PromptStore>,
+    /// Audit trail of exec turns (stdout or `CODEX_AUDIT_LOG`)
+    pub audit_log: Arc<AuditLog>,
+    /// Prompts execs may run (`CODEX_PROMPT_ALLOWLIST`); allows all when unset
+    pub prompt_allowlist: Arc<PromptAllowlist>,
+    /// Execs running or waiting for a slot, bounded by `CODEX_MAX_INFLIGHT`
+    pub inflight: Arc<AtomicUsize>,
+    /// Execs per API key in the last 24 hours, bounded by `CODEX_DAILY_QUOTA`
+    pub daily_quota: Arc<DailyQuota>,
+    /// Cached result of the smoke exec run by `/healthz/deep`
+    pub smoke_check: Arc<SmokeCheck>,
     // Add more shared state here as needed in future iterations
     // Examples:
     // - Database connections
     // - Redis connections
     // - Service discovery clients
-    // - Metrics collectors
     // - Authentication services
 }
 
@@ -28,9 +73,40 @@ impl AppState {
     pub async fn new(config: GatewayConfig) -> Result<Self, GatewayError> {
         let codex_service = CodexService::new().await?;
         //                                            ^ propaga erro ao invés de panic
+        let exec_permits = Arc::new(Semaphore::new(config.exec.max_concurrency));
+        let idempotency = Arc::new(IdempotencyCache::new(config.exec.idempotency_ttl));
+        let circuit_breaker = Arc::new(CircuitBreaker::new(
+            config.exec.breaker_threshold,
+            config.exec.breaker_window,
+            config.exec.breaker_cooldown,
+        ));
+        let daily_quota = Arc::new(DailyQuota::new(
+            config.exec.daily_quota,
+            Some(
+                codex_service
+                    .codex_config()
+                    .codex_home
+                    .join("gateway")
+                    .join("daily_quota.json"),
+            ),
+        ));
         Ok(Self {
             config: Arc::new(config),
             codex_service: Arc::new(codex_service),
+            exec_permits,
+            exec_queue: Arc::new(ExecQueue::new()),
+            metrics: Arc::new(ExecMetrics::new()),
+            callbacks: Arc::new(CallbackClient::from_env()),
+            active_execs: Arc::new(ActiveExecs::new()),
+            idempotency,
+            exec_results: Arc::new(ExecResults::new()),
+            circuit_breaker,
+            prompt_store: Arc::new(PromptStore::from_env()),
+            audit_log: Arc::new(AuditLog::from_env()?),
+            prompt_allowlist: Arc::new(PromptAllowlist::from_env()?),
+            inflight: Arc::new(AtomicUsize::new(0)),
+            daily_quota,
+            smoke_check: Arc::new(SmokeCheck::default()),
         })
     }
 
@@ -38,6 +114,77 @@ impl AppState {
     pub fn config(&self) -> &GatewayConfig {
         &self.config
     }
+
+    /// Try to take an execution slot without waiting
+    ///
+    /// Returns `None` when all slots are busy; callers should report that the
+    /// request is queued and then fall back to [`AppState::acquire_exec_permit`].
+    pub fn try_acquire_exec_permit(&self) -> Option<OwnedSemaphorePermit> {
+        match Arc::clone(&self.exec_permits).try_acquire_owned() {
+            Ok(permit) => Some(permit),
+            Err(TryAcquireError::NoPermits) | Err(TryAcquireError::Closed) => None,
+        }
+    }
+
+    /// Count a new exec as in flight, or reject it when too many already are
+    ///
+    /// Unlike the execution slots this bounds the queue: past
+    /// `CODEX_MAX_INFLIGHT` running and waiting execs, new ones fail fast with
+    /// 503 and `Retry-After` instead of waiting. The exec stops counting when
+    /// the returned guard is dropped.
+    pub fn enter_inflight(&self) -> Result<InflightGuard, GatewayError> {
+        let max_inflight = self.config.exec.max_inflight;
+        let admitted = self
+            .inflight
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                (max_inflight == 0 || current < max_inflight).then_some(current + 1)
+            });
+        if admitted.is_err() {
+            return Err(GatewayError::Overloaded {
+                message: format!("{max_inflight} execs already in flight"),
+                retry_after_secs: INFLIGHT_RETRY_AFTER_SECS,
+            });
+        }
+
+        Ok(InflightGuard {
+            inflight: Arc::clone(&self.inflight),
+        })
+    }
+
+    /// Wait for an execution slot; the slot is released when the permit is dropped
+    pub async fn acquire_exec_permit(&self) -> Result<OwnedSemaphorePermit, GatewayError> {
+        Arc::clone(&self.exec_permits)
+            .acquire_owned()
+            .await
+            .map_err(|e| GatewayError::ServiceUnavailable(format!("exec limiter closed: {e}")))
+    }
+
+    /// Rough wait for the exec at `position` in [`AppState::exec_queue`]
+    ///
+    /// Assumes every running and queued exec takes the mean execution time so
+    /// far and slots free up in batches of `max_concurrency`. `None` until an
+    /// exec has finished.
+    pub fn estimated_queue_wait(&self, position: usize) -> Option<Duration> {
+        let average = self.metrics.average_execution_time()?;
+        let slots = self.config.exec.max_concurrency.max(1);
+        let rounds = u32::try_from(position.div_ceil(slots)).unwrap_or(u32::MAX);
+        Some(average.saturating_mul(rounds))
+    }
+}
+
+/// `Retry-After` sent when `CODEX_MAX_INFLIGHT` is reached
+const INFLIGHT_RETRY_AFTER_SECS: u64 = 5;
+
+/// One exec counted in [`AppState::inflight`] until dropped
+#[derive(Debug)]
+pub struct InflightGuard {
+    inflight: Arc<AtomicUsize>,
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        self.inflight.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl Default for AppState {
@@ -60,3 +207,74 @@ impl Default for AppState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::ExecOutcome;
+
+    #[tokio::test]
+    async fn test_exec_permits_bound_concurrency() -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = GatewayConfig::default();
+        config.exec.max_concurrency = 2;
+        let state = AppState::new(config).await?;
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let mut handles = Vec::new();
+        for _ in 0..4 {
+            let state = state.clone();
+            let running = Arc::clone(&running);
+            let peak = Arc::clone(&peak);
+            handles.push(tokio::spawn(async move {
+                let _permit = state.acquire_exec_permit().await?;
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok::<(), GatewayError>(())
+            }));
+        }
+        for handle in handles {
+            handle.await??;
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_try_acquire_exec_permit_reports_full() -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = GatewayConfig::default();
+        config.exec.max_concurrency = 1;
+        let state = AppState::new(config).await?;
+
+        let held = state.try_acquire_exec_permit();
+        assert!(held.is_some());
+        assert!(state.try_acquire_exec_permit().is_none());
+
+        drop(held);
+        assert!(state.try_acquire_exec_permit().is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_estimated_queue_wait_by_position() -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = GatewayConfig::default();
+        config.exec.max_concurrency = 2;
+        let state = AppState::new(config).await?;
+        assert_eq!(state.estimated_queue_wait(1), None);
+
+        state
+            .metrics
+            .record_finished(ExecOutcome::Completed, Duration::from_millis(800));
+        state
+            .metrics
+            .record_finished(ExecOutcome::Completed, Duration::from_millis(1_200));
+        assert_eq!(state.estimated_queue_wait(1), Some(Duration::from_secs(1)));
+        assert_eq!(state.estimated_queue_wait(2), Some(Duration::from_secs(1)));
+        assert_eq!(state.estimated_queue_wait(3), Some(Duration::from_secs(2)));
+        Ok(())
+    }
+}
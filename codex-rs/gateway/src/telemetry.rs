@@ -0,0 +1,212 @@
+//! OpenTelemetry trace export
+//!
+//! Built with the `otel` feature. When `OTEL_EXPORTER_OTLP_ENDPOINT` is set,
+//! tracing spans are exported over OTLP/HTTP and an incoming W3C
+//! `traceparent` header becomes the parent of the request span, so gateway
+//! spans join the caller's trace. Without the feature every function here is
+//! a no-op and plain `tracing` output is unchanged.
+
+use axum::http::HeaderMap;
+use std::time::Duration;
+use tracing::Span;
+use tracing::Subscriber;
+use tracing_subscriber::Layer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Environment variable that turns on OTLP export
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Service name reported on exported spans
+#[cfg(feature = "otel")]
+const SERVICE_NAME: &str = "codex-gateway";
+
+/// Keeps the tracer provider alive; flushes pending spans when dropped
+#[derive(Debug, Default)]
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl TelemetryGuard {
+    /// Whether spans are being exported
+    pub fn is_enabled(&self) -> bool {
+        #[cfg(feature = "otel")]
+        {
+            self.provider.is_some()
+        }
+        #[cfg(not(feature = "otel"))]
+        {
+            false
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            let _ = provider.shutdown();
+        }
+    }
+}
+
+/// Build the OTLP export layer when `OTEL_EXPORTER_OTLP_ENDPOINT` is set
+///
+/// Also installs the W3C trace-context propagator used by
+/// [`set_remote_parent`]. Returns `None` when export is not configured.
+#[cfg(feature = "otel")]
+pub fn otlp_layer<S>() -> crate::error::GatewayResult<(Option<impl Layer<S>>, TelemetryGuard)>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+
+    let endpoint = std::env::var(OTLP_ENDPOINT_ENV).ok();
+    if endpoint.filter(|v| !v.is_empty()).is_none() {
+        return Ok((None, TelemetryGuard::default()));
+    }
+
+    // The exporter reads the endpoint (and OTEL_EXPORTER_OTLP_HEADERS) itself
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()
+        .map_err(|e| {
+            crate::error::GatewayError::Config(format!("failed to build OTLP exporter: {e}"))
+        })?;
+    let provider = SdkTracerProvider::builder()
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .with_batch_exporter(exporter)
+        .build();
+
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME));
+
+    Ok((
+        Some(layer),
+        TelemetryGuard {
+            provider: Some(provider),
+        },
+    ))
+}
+
+/// Build the OTLP export layer (no-op: built without the `otel` feature)
+#[cfg(not(feature = "otel"))]
+pub fn otlp_layer<S>() -> crate::error::GatewayResult<(Option<impl Layer<S>>, TelemetryGuard)>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    Ok((
+        None::<tracing_subscriber::layer::Identity>,
+        TelemetryGuard::default(),
+    ))
+}
+
+/// Make the trace in the request's `traceparent` header the parent of `span`
+#[cfg(feature = "otel")]
+pub fn set_remote_parent(span: &Span, headers: &HeaderMap) {
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+    span.set_parent(parent);
+}
+
+/// Make the trace in the request's `traceparent` header the parent of `span`
+/// (no-op: built without the `otel` feature)
+#[cfg(not(feature = "otel"))]
+pub fn set_remote_parent(_span: &Span, _headers: &HeaderMap) {}
+
+/// Record the outcome of an exec on its span
+///
+/// `span` is expected to declare `conversation_id`, `status` and
+/// `execution_time_ms` as empty fields; they become span attributes on export.
+pub fn record_exec_result(span: &Span, conversation_id: &str, status: &str, elapsed: Duration) {
+    span.record("conversation_id", conversation_id);
+    span.record("status", status);
+    span.record(
+        "execution_time_ms",
+        u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX),
+    );
+}
+
+/// Reads propagation headers from an HTTP request
+#[cfg(feature = "otel")]
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+#[cfg(feature = "otel")]
+impl opentelemetry::propagation::Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use super::*;
+    use opentelemetry::Value;
+    use opentelemetry::trace::TraceId;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::InMemorySpanExporter;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_exec_span_is_exported_with_attributes() -> Result<(), Box<dyn std::error::Error>> {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".parse()?,
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "exec",
+                session_id = "session-1",
+                conversation_id = tracing::field::Empty,
+                status = tracing::field::Empty,
+                execution_time_ms = tracing::field::Empty,
+            );
+            set_remote_parent(&span, &headers);
+            record_exec_result(&span, "conv-1", "completed", Duration::from_millis(42));
+        });
+        provider.force_flush()?;
+
+        let spans = exporter.get_finished_spans()?;
+        let span = spans
+            .iter()
+            .find(|s| s.name == "exec")
+            .ok_or("no exec span")?;
+        assert_eq!(
+            span.span_context.trace_id(),
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736")?
+        );
+
+        let attribute = |key: &str| {
+            span.attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(attribute("session_id"), Some(Value::from("session-1")));
+        assert_eq!(attribute("status"), Some(Value::from("completed")));
+        assert_eq!(attribute("execution_time_ms"), Some(Value::I64(42)));
+        Ok(())
+    }
+}